@@ -4,6 +4,10 @@
 
 set -e  # Exit on error
 
+# Reproducible builds: pin timestamps to the last commit. dpkg-deb honors this,
+# and nymyac switches to path-independent output when it is set.
+export SOURCE_DATE_EPOCH="${SOURCE_DATE_EPOCH:-$(git log -1 --format=%ct 2>/dev/null || date +%s)}"
+
 # Source version information from version.conf
 if [ -f "/home/erick/nymya/version.conf" ]; then
     source /home/erick/nymya/version.conf
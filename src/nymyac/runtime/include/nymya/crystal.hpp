@@ -0,0 +1,14 @@
+/*
+ * NymyaLang runtime: crystal module (`import crystal`) - console output
+ */
+#pragma once
+
+#include "nymya/version.hpp"
+
+#include <string>
+
+// Crystal utilities (console output)
+namespace crystal {
+    void manifest(const std::string& msg);
+    void print(const std::string& msg);
+}
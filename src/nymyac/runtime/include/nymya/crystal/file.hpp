@@ -0,0 +1,28 @@
+/*
+ * NymyaLang runtime: crystal.file module - file operations (Taygetan-inspired names)
+ */
+#pragma once
+
+#include "nymya/version.hpp"
+
+#include <fstream>
+#include <string>
+
+namespace crystal {
+    namespace file {
+        // dalan_orin = home food (nourish home with content)
+        bool dalan_orin(const std::string& path, const std::string& content);
+
+        // dalan_aya = home see (check if home exists)
+        bool dalan_aya(const std::string& path);
+
+        // dalan_karma = home work (read content from home)
+        std::string dalan_karma(const std::string& path);
+
+        // dalan_lora = home exist (create output stream)
+        std::ofstream dalan_lora(const std::string& path);
+
+        // dalan_shira = home love (create input stream)
+        std::ifstream dalan_shira(const std::string& path);
+    }
+}
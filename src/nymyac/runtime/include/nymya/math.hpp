@@ -0,0 +1,24 @@
+/*
+ * NymyaLang runtime: math module (`import math`)
+ */
+#pragma once
+
+#include "nymya/version.hpp"
+
+// Math utilities
+namespace math {
+    double sqrt(double x);
+    double abs(double x);
+    double min(double a, double b);
+    double max(double a, double b);
+    double pow(double base, double exp);
+    double sin(double x);
+    double cos(double x);
+    double tan(double x);
+
+    inline constexpr double PI = 3.141592653589793;
+
+    // Integer math functions
+    long long pow_int(long long base, long long exp);
+    long long gcd(long long a, long long b);
+}
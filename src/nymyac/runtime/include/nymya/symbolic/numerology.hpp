@@ -0,0 +1,21 @@
+/*
+ * NymyaLang runtime: symbolic.numerology module
+ */
+#pragma once
+
+#include "nymya/version.hpp"
+
+#include <string>
+#include <vector>
+
+// Symbolic mathematics namespace
+namespace symbolic {
+    namespace numerology {
+        struct Meaning {
+            std::string meaning;
+            std::vector<std::string> traits;
+        };
+
+        Meaning get_meaning(long long number);
+    }
+}
@@ -0,0 +1,9 @@
+/*
+ * NymyaLang runtime library (libnymya) version
+ *
+ * Generated programs and libnymya.a must come from the same nymyac release;
+ * the compiler rebuilds the library whenever this version changes.
+ */
+#pragma once
+
+#define NYMYA_RUNTIME_VERSION "0.2.0-alpha.9"
@@ -0,0 +1,13 @@
+#include "nymya/crystal.hpp"
+
+#include <iostream>
+
+namespace crystal {
+    void manifest(const std::string& msg) {
+        std::cout << msg << std::endl;
+    }
+
+    void print(const std::string& msg) {
+        std::cout << msg;
+    }
+}
@@ -0,0 +1,46 @@
+#include "nymya/crystal/file.hpp"
+
+namespace crystal {
+    namespace file {
+        bool dalan_orin(const std::string& path, const std::string& content) {
+            std::ofstream file(path);
+            if (file.is_open()) {
+                file << content;
+                file.close();
+                return true;
+            }
+            return false;
+        }
+
+        bool dalan_aya(const std::string& path) {
+            std::ifstream file(path);
+            bool exists = file.good();
+            if (exists) {
+                file.close();
+            }
+            return exists;
+        }
+
+        std::string dalan_karma(const std::string& path) {
+            std::ifstream file(path);
+            if (file.is_open()) {
+                std::string content;
+                std::string line;
+                while (std::getline(file, line)) {
+                    content += line + "\n";
+                }
+                file.close();
+                return content;
+            }
+            return "";
+        }
+
+        std::ofstream dalan_lora(const std::string& path) {
+            return std::ofstream(path);
+        }
+
+        std::ifstream dalan_shira(const std::string& path) {
+            return std::ifstream(path);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+#include "nymya/math.hpp"
+
+#include <cmath>
+
+namespace math {
+    double sqrt(double x) { return std::sqrt(x); }
+    double abs(double x) { return x < 0 ? -x : x; }
+    double min(double a, double b) { return a < b ? a : b; }
+    double max(double a, double b) { return a > b ? a : b; }
+    double pow(double base, double exp) { return std::pow(base, exp); }
+    double sin(double x) { return std::sin(x); }
+    double cos(double x) { return std::cos(x); }
+    double tan(double x) { return std::tan(x); }
+
+    long long pow_int(long long base, long long exp) {
+        if (exp <= 0) return 1;
+        long long result = 1;
+        long long b = base;
+        long long e = exp;
+        while (e > 0) {
+            if (e % 2 == 1) result *= b;
+            b *= b;
+            e /= 2;
+        }
+        return result;
+    }
+
+    long long gcd(long long a, long long b) {
+        a = a < 0 ? -a : a;
+        b = b < 0 ? -b : b;
+        while (b != 0) {
+            long long temp = b;
+            b = a % b;
+            a = temp;
+        }
+        return a;
+    }
+}
@@ -0,0 +1,12 @@
+#include "nymya/symbolic/numerology.hpp"
+
+namespace symbolic {
+    namespace numerology {
+        Meaning get_meaning(long long number) {
+            Meaning result;
+            result.meaning = "Meaning for number " + std::to_string(number);
+            result.traits = {"trait1", "trait2", "trait3"};
+            return result;
+        }
+    }
+}
@@ -0,0 +1,60 @@
+/*
+ * Standalone tests for libnymya: `make -C src/nymyac/runtime test`
+ */
+#include "nymya/crystal.hpp"
+#include "nymya/crystal/file.hpp"
+#include "nymya/math.hpp"
+#include "nymya/symbolic/numerology.hpp"
+
+#include <cstdio>
+#include <iostream>
+#include <string>
+
+static int failures = 0;
+
+static void expect(bool condition, const std::string& what) {
+    if (!condition) {
+        std::cerr << "FAILED: " << what << std::endl;
+        failures++;
+    }
+}
+
+static void test_math() {
+    expect(math::sqrt(16.0) == 4.0, "math::sqrt");
+    expect(math::abs(-2.5) == 2.5, "math::abs");
+    expect(math::min(1.0, 2.0) == 1.0, "math::min");
+    expect(math::max(1.0, 2.0) == 2.0, "math::max");
+    expect(math::pow_int(3, 4) == 81, "math::pow_int");
+    expect(math::pow_int(5, 0) == 1, "math::pow_int with zero exponent");
+    expect(math::gcd(-12, 18) == 6, "math::gcd with negative input");
+    expect(math::PI > 3.14159 && math::PI < 3.14160, "math::PI");
+}
+
+static void test_crystal_file() {
+    const std::string path = "nymya_runtime_test.tmp";
+    expect(crystal::file::dalan_orin(path, "line one\nline two"), "dalan_orin writes");
+    expect(crystal::file::dalan_aya(path), "dalan_aya sees written file");
+    expect(crystal::file::dalan_karma(path) == "line one\nline two\n", "dalan_karma reads lines");
+    std::remove(path.c_str());
+    expect(!crystal::file::dalan_aya(path), "dalan_aya after removal");
+    expect(crystal::file::dalan_karma(path).empty(), "dalan_karma of missing file");
+}
+
+static void test_numerology() {
+    symbolic::numerology::Meaning meaning = symbolic::numerology::get_meaning(7);
+    expect(meaning.meaning == "Meaning for number 7", "get_meaning text");
+    expect(meaning.traits.size() == 3, "get_meaning traits");
+}
+
+int main() {
+    test_math();
+    test_crystal_file();
+    test_numerology();
+
+    if (failures > 0) {
+        std::cerr << failures << " runtime test(s) failed" << std::endl;
+        return 1;
+    }
+    crystal::manifest(std::string("libnymya ") + NYMYA_RUNTIME_VERSION + ": all runtime tests passed");
+    return 0;
+}
@@ -0,0 +1,424 @@
+// Syntax tree produced by the parser and consumed by semantic analysis and
+// code generation. Every statement carries the span it was parsed from.
+
+use std::fmt;
+
+/// A sequence of statements, such as a file or the body of an `if`
+pub type Block = Vec<Spanned<Statement>>;
+
+#[derive(Debug)]
+pub enum Statement {
+    /// `import crystal.file`
+    Import(String),
+    /// `var name = value` or `var name: Type = value`; semantic analysis fills in
+    /// the type of function values, which C++ cannot spell with `auto`
+    VariableDeclaration { name: String, ty: Option<TypeExpr>, value: Expression },
+    /// `var (a, b) = value`: a variable for each element of a tuple; `_` skips an element
+    Destructure { names: Vec<String>, value: Expression },
+    /// `target = value`; compound assignments like `target += value` carry their operator
+    Assignment { target: AssignmentTarget, operator: Option<BinaryOperator>, value: Expression },
+    /// An expression evaluated for its effect, like a call
+    Expression(Expression),
+    /// `if condition { ... } else { ... }`; `else if` nests another `If` in the else branch
+    If { condition: Expression, then_branch: Block, else_branch: Option<Block> },
+    /// `if let name = optional { ... } else { ... }`: `name` is the unwrapped value inside `then_branch`
+    IfLet { name: String, value: Expression, then_branch: Block, else_branch: Option<Block> },
+    /// `while condition { ... }`
+    While { condition: Expression, body: Block },
+    /// `return` or `return value`
+    Return(Option<Expression>),
+    /// `func name(params) -> Type { ... }` at the top level of a file
+    Function(Function),
+    /// `class Name { fields, init, methods }` at the top level of a file
+    Class(Class),
+    /// `enum Name { Variant, Variant(Type) }` at the top level of a file
+    Enum(Enum),
+    /// `interface Name { func method(params) -> Type }` at the top level of a file
+    Interface(Interface),
+}
+
+/// A type as written in the source
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeExpr {
+    /// `Int`, `T`, `List[String]`, `Queue[T]`, `Func[Float, Void]`
+    Named { name: String, args: Vec<TypeExpr> },
+    /// `T?`: a `T` or `none`
+    Optional(Box<TypeExpr>),
+    /// `(Int, String)`: a tuple of two or more values
+    Tuple(Vec<TypeExpr>),
+}
+
+impl TypeExpr {
+    pub fn named(name: impl Into<String>) -> Self {
+        TypeExpr::Named { name: name.into(), args: Vec::new() }
+    }
+}
+
+impl fmt::Display for TypeExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeExpr::Named { name, args } if args.is_empty() => write!(f, "{}", name),
+            TypeExpr::Named { name, args } => {
+                let args: Vec<String> = args.iter().map(TypeExpr::to_string).collect();
+                write!(f, "{}[{}]", name, args.join(", "))
+            }
+            TypeExpr::Optional(inner) => write!(f, "{}?", inner),
+            TypeExpr::Tuple(elements) => {
+                let elements: Vec<String> = elements.iter().map(TypeExpr::to_string).collect();
+                write!(f, "({})", elements.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Parameter {
+    pub name: String,
+    pub ty: TypeExpr,
+}
+
+/// A function, a method, an extension method or a class initializer (`init`, which returns Void)
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    /// `Int` in `func Int.to_hex_char()`: an extension method of a built-in type
+    pub receiver: Option<TypeExpr>,
+    /// Type parameters of a generic function: `func first[T](items: List[T]) -> T`
+    pub type_params: Vec<String>,
+    /// Interfaces the type parameters must implement
+    pub bounds: Vec<Bound>,
+    pub params: Vec<Parameter>,
+    /// `Void` when the declaration has no `-> Type`
+    pub returns: TypeExpr,
+    pub body: Block,
+}
+
+impl Function {
+    /// Name of the free C++ function an extension method is lowered to, like `Int_to_hex_char`
+    pub fn extension_name(&self) -> Option<String> {
+        match &self.receiver {
+            Some(TypeExpr::Named { name, .. }) => Some(format!("{}_{}", name, self.name)),
+            Some(TypeExpr::Optional(_) | TypeExpr::Tuple(_)) | None => None,
+        }
+    }
+}
+
+/// `("T", "VectorLike")` for `[T: VectorLike]`: a type parameter and an interface it must implement
+pub type Bound = (String, String);
+
+/// `name: Type` inside a class
+#[derive(Debug)]
+pub struct FieldDeclaration {
+    pub name: String,
+    pub ty: TypeExpr,
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: String,
+    /// Type parameters of a generic class: `class Queue[T]`
+    pub type_params: Vec<String>,
+    /// Interfaces the type parameters must implement
+    pub bounds: Vec<Bound>,
+    /// `extends Base` or `extends Base[Int]`
+    pub base: Option<TypeExpr>,
+    /// `implements Shape, Named`
+    pub interfaces: Vec<String>,
+    pub fields: Vec<Spanned<FieldDeclaration>>,
+    pub init: Option<Spanned<Function>>,
+    pub methods: Vec<Spanned<Function>>,
+}
+
+/// Methods a class promises to have with `implements`. The methods have no
+/// body; `Self` in their signatures stands for the implementing class.
+#[derive(Debug)]
+pub struct Interface {
+    pub name: String,
+    pub methods: Vec<Spanned<Function>>,
+}
+
+/// A closed set of variants; a value of the enum is one of them, with the
+/// payload values that variant declares
+#[derive(Debug)]
+pub struct Enum {
+    pub name: String,
+    pub variants: Vec<Spanned<Variant>>,
+}
+
+/// `CNOT(Int, Int)` in an enum, or `Measure` with no payload
+#[derive(Debug)]
+pub struct Variant {
+    pub name: String,
+    pub payload: Vec<TypeExpr>,
+}
+
+/// `pattern => result` in a `match`
+#[derive(Debug)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: MatchBody,
+}
+
+#[derive(Debug)]
+pub enum Pattern {
+    /// `Gate.CNOT(control, target)`: binds the payload values by position; `_` skips one
+    Variant { enum_name: String, variant: String, bindings: Vec<String> },
+    /// `3`, `"variable"` or `true`: compared with `==`
+    Literal(Expression),
+    /// `_`, matching anything
+    Wildcard,
+}
+
+#[derive(Debug)]
+pub enum MatchBody {
+    Expression(Expression),
+    /// `{ statements }`, only in a `match` used as a statement
+    Block(Block),
+}
+
+/// What an assignment writes to
+#[derive(Debug)]
+pub enum AssignmentTarget {
+    /// `x = ...`
+    Variable(String),
+    /// `object.name = ...`, like `this.qubits = new_state`
+    Field { object: Expression, name: String },
+    /// `list[index] = ...`
+    Index { array: Expression, index: Expression },
+    /// `(a, b) = ...`: each target gets an element of a tuple; `_` skips an element
+    Tuple(Vec<AssignmentTarget>),
+    /// `_` in a tuple of targets
+    Ignored,
+}
+
+#[derive(Debug)]
+pub enum Expression {
+    Integer(i64),
+    Float(f64),
+    /// String literal contents, with escape sequences as written in the source
+    String(String),
+    Boolean(bool),
+    /// `none` (also spelled `null`)
+    None,
+    Variable(String),
+    /// `module.function(args)`, where `module` is a runtime or project module path
+    FunctionCall { module: String, function: String, args: Vec<Expression> },
+    /// `function(args)` or `function[Int](args)`. Semantic analysis fills in
+    /// inferred type arguments and turns calls of a class name into `Construct`.
+    Call { function: String, type_args: Vec<TypeExpr>, args: Vec<Expression> },
+    /// `Queue[Int](10)`: a new instance of a class, with all type arguments known
+    Construct { class: String, type_args: Vec<TypeExpr>, args: Vec<Expression> },
+    /// Constants and variables of a module, like `math.PI`
+    ModuleMember { module: String, name: String },
+    /// `object.name` without a call
+    Field { object: Box<Expression>, name: String },
+    /// `pair.0`: an element of a tuple, numbered from 0
+    TupleElement { tuple: Box<Expression>, index: usize },
+    /// `list[index]`
+    ArrayAccess { array: Box<Expression>, index: Box<Expression> },
+    /// List methods like `.append(x)` and `.length`; semantic analysis turns
+    /// method calls and fields of lists into these
+    ArrayMethodCall { array: Box<Expression>, method: String, args: Vec<Expression> },
+    /// Any other method call, like `qubit.measure()`
+    MethodCall { object: Box<Expression>, method: String, args: Vec<Expression> },
+    /// `handlers[i](event)` or `make_adder(1)(2)`: a call of a function value
+    /// that is not named by a variable or field
+    ValueCall { callee: Box<Expression>, args: Vec<Expression> },
+    /// `value.to_string()`: a call of an extension method, as a call of the C++
+    /// function implementing it with the receiver as the first argument. Semantic
+    /// analysis turns method calls on built-in types into these.
+    ExtensionCall { function: String, type_args: Vec<TypeExpr>, args: Vec<Expression> },
+    /// `super.method(args)` in a method; `super(args)` and `super.init(args)` call the
+    /// initializer of the base class and have `method` "init"
+    SuperCall { method: String, args: Vec<Expression> },
+    /// `|x: Float| -> Void { ... }`: a function value that captures copies of the
+    /// variables it uses; `||` starts one without parameters
+    Lambda { params: Vec<Parameter>, returns: TypeExpr, body: Block },
+    /// `Gate.CNOT(0, 1)` or `Gate.Measure`: a value of an enum. Semantic analysis
+    /// turns method calls and fields of an enum name into these.
+    EnumVariant { enum_name: String, variant: String, args: Vec<Expression> },
+    /// `match value { pattern => result, ... }`: the result of the first arm whose
+    /// pattern matches. Semantic analysis fills in the type of the result and
+    /// whether the arms cover every value.
+    Match { value: Box<Expression>, arms: Vec<MatchArm>, ty: Option<TypeExpr>, exhaustive: bool },
+    /// `[a, b, c]`; semantic analysis fills in the element type when it is known
+    ArrayLiteral { elements: Vec<Expression>, element_type: Option<TypeExpr> },
+    /// `(a, b)`; semantic analysis fills in the tuple type when it is known
+    Tuple { elements: Vec<Expression>, ty: Option<TypeExpr> },
+    /// `value` converted to `ty`. Semantic analysis wraps the arguments of a call
+    /// of an overloaded function, method or operator in these where their type
+    /// differs from the parameter of the overload it picked.
+    Convert { value: Box<Expression>, ty: TypeExpr },
+    /// `object?.name` or `object?.method(args)`: `member` is a field access or
+    /// method call on `Unwrapped`, evaluated only when `object` is not `none`.
+    /// Semantic analysis sets `void` when the method returns nothing.
+    OptionalChain { object: Box<Expression>, member: Box<Expression>, void: bool },
+    /// The value inside the `object` of the enclosing `OptionalChain`
+    Unwrapped,
+    UnaryOp { operator: UnaryOperator, operand: Box<Expression> },
+    BinaryOp { left: Box<Expression>, operator: BinaryOperator, right: Box<Expression> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Or,
+    And,
+    /// `optional ?? default`
+    Coalesce,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+impl BinaryOperator {
+    /// The operator of a compound assignment token like `+=`
+    pub fn from_compound_assignment(token: &str) -> Option<Self> {
+        Some(match token {
+            "+=" => BinaryOperator::Add,
+            "-=" => BinaryOperator::Sub,
+            "*=" => BinaryOperator::Mul,
+            "/=" => BinaryOperator::Div,
+            "%=" => BinaryOperator::Mod,
+            _ => return None,
+        })
+    }
+
+    /// The operator spelled by `token`; `and`/`&&` and `or`/`||` are synonyms
+    pub fn from_token(token: &str) -> Option<Self> {
+        Some(match token {
+            "or" | "||" => BinaryOperator::Or,
+            "and" | "&&" => BinaryOperator::And,
+            "==" => BinaryOperator::Eq,
+            "!=" => BinaryOperator::Ne,
+            "??" => BinaryOperator::Coalesce,
+            "<" => BinaryOperator::Lt,
+            "<=" => BinaryOperator::Le,
+            ">" => BinaryOperator::Gt,
+            ">=" => BinaryOperator::Ge,
+            "+" => BinaryOperator::Add,
+            "-" => BinaryOperator::Sub,
+            "*" => BinaryOperator::Mul,
+            "/" => BinaryOperator::Div,
+            "%" => BinaryOperator::Mod,
+            _ => return None,
+        })
+    }
+
+    /// Binding strength; operators with a higher precedence bind tighter.
+    /// Prefix `not` binds at `UnaryOperator::NOT_PRECEDENCE`.
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOperator::Or => 1,
+            BinaryOperator::And => 2,
+            BinaryOperator::Eq | BinaryOperator::Ne => 4,
+            BinaryOperator::Lt | BinaryOperator::Le | BinaryOperator::Gt | BinaryOperator::Ge => 5,
+            BinaryOperator::Coalesce => 6,
+            BinaryOperator::Add | BinaryOperator::Sub => 7,
+            BinaryOperator::Mul | BinaryOperator::Div | BinaryOperator::Mod => 8,
+        }
+    }
+
+    /// Method a class declares to overload the operator, from `OPERATOR_METHODS`.
+    /// `!=` uses `op_eq` and negates it.
+    pub fn method(self) -> Option<&'static str> {
+        match self {
+            BinaryOperator::Add => Some("op_add"),
+            BinaryOperator::Sub => Some("op_sub"),
+            BinaryOperator::Mul => Some("op_mul"),
+            BinaryOperator::Div => Some("op_div"),
+            BinaryOperator::Mod => Some("op_mod"),
+            BinaryOperator::Eq | BinaryOperator::Ne => Some("op_eq"),
+            _ => None,
+        }
+    }
+
+    /// NymyaLang spelling, for diagnostics
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOperator::Or => "or",
+            BinaryOperator::And => "and",
+            BinaryOperator::Eq => "==",
+            BinaryOperator::Ne => "!=",
+            BinaryOperator::Coalesce => "??",
+            BinaryOperator::Lt => "<",
+            BinaryOperator::Le => "<=",
+            BinaryOperator::Gt => ">",
+            BinaryOperator::Ge => ">=",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Sub => "-",
+            BinaryOperator::Mul => "*",
+            BinaryOperator::Div => "/",
+            BinaryOperator::Mod => "%",
+        }
+    }
+}
+
+/// Methods a class can declare to overload an operator: the method, the
+/// operator and how many parameters the method takes. `a[i]` calls `op_index`.
+pub const OPERATOR_METHODS: &[(&str, &str, usize)] = &[
+    ("op_add", "+", 1),
+    ("op_sub", "-", 1),
+    ("op_mul", "*", 1),
+    ("op_div", "/", 1),
+    ("op_mod", "%", 1),
+    ("op_eq", "==", 1),
+    ("op_neg", "-", 0),
+    ("op_index", "[]", 1),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    /// `-x`
+    Neg,
+    /// `not x` or `!x`
+    Not,
+}
+
+impl UnaryOperator {
+    /// `not` binds looser than comparisons, so `not a == b` negates the comparison
+    pub const NOT_PRECEDENCE: u8 = 3;
+
+    /// NymyaLang spelling, for diagnostics
+    pub fn symbol(self) -> &'static str {
+        match self {
+            UnaryOperator::Neg => "-",
+            UnaryOperator::Not => "not",
+        }
+    }
+
+    /// Method a class declares to overload the operator, from `OPERATOR_METHODS`
+    pub fn method(self) -> Option<&'static str> {
+        match self {
+            UnaryOperator::Neg => Some("op_neg"),
+            UnaryOperator::Not => None,
+        }
+    }
+}
+
+/// 1-based source position of a token or AST node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// An AST node together with the source position it was parsed from
+#[derive(Debug)]
+pub struct Spanned<T> {
+    pub span: Span,
+    pub node: T,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(span: Span, node: T) -> Self {
+        Spanned { span, node }
+    }
+}
@@ -0,0 +1,424 @@
+// Building projects: the front end over all modules of a project, the
+// generated translation units and the incremental compile, link and archive
+// steps.
+//
+// `analyze` parses every module, orders the modules by their imports and checks
+// them imported-first, so each module sees what its imports export. `build`
+// writes one C++ translation unit per module into target/build, recompiles
+// only the units whose inputs changed and links the objects into target/<name>
+// (executables) or archives them into target/lib<name>.a next to
+// target/<name>.hpp (libraries).
+//
+// Nothing here prints or exits: diagnostics are returned with the checked
+// files, compiler progress is passed to a callback and failures are errors.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ast::{Class, Spanned, Statement};
+use crate::codegen::{self, ModuleCode};
+use crate::diagnostics::Diagnostic;
+use crate::project::{self, display_path, Project, ProjectKind};
+use crate::toolchain::{CompileEvent, OutputKind, Toolchain, ToolchainArgs};
+use crate::{check_parsed, incremental, parser, runtime, FrontEnd};
+
+/// A parsed and checked source file of a project
+#[derive(Debug)]
+pub struct ModuleFrontEnd {
+    pub name: String,
+    /// How the file is named in diagnostics and `#line` directives
+    pub source_file: String,
+    pub path: PathBuf,
+    /// Project modules imported by this file
+    pub imports: Vec<String>,
+    pub front_end: FrontEnd,
+}
+
+impl ModuleFrontEnd {
+    /// The module for code generation; `classes` are those of the whole project
+    pub fn code<'a>(&'a self, classes: &'a BTreeMap<String, &'a Class>) -> ModuleCode<'a> {
+        ModuleCode {
+            name: &self.name,
+            source_file: &self.source_file,
+            statements: &self.front_end.statements,
+            imports: &self.imports,
+            used_modules: &self.front_end.used_modules,
+            classes,
+        }
+    }
+}
+
+/// Output of the front end for a whole project
+#[derive(Debug)]
+pub struct ProjectFrontEnd {
+    /// Importable modules, each after the modules it imports
+    pub modules: Vec<ModuleFrontEnd>,
+    /// Entry file of an executable project
+    pub entry: Option<ModuleFrontEnd>,
+    /// Runtime and project modules used anywhere in the project
+    pub used_modules: BTreeSet<String>,
+}
+
+impl ProjectFrontEnd {
+    /// The checked files in the order they were checked
+    pub fn files(&self) -> impl Iterator<Item = &ModuleFrontEnd> {
+        self.modules.iter().chain(self.entry.as_ref())
+    }
+
+    /// Every class of the project by qualified name, as classes of one module can extend those of another
+    pub fn classes(&self) -> BTreeMap<String, &Class> {
+        let mut classes = BTreeMap::new();
+        for file in self.files() {
+            codegen::collect_classes(&file.name, &file.front_end.statements, &mut classes);
+        }
+        classes
+    }
+}
+
+// Path, display path, statements and syntax diagnostics of a parsed source file
+type ParsedFile = (PathBuf, String, Vec<Spanned<Statement>>, Vec<Diagnostic>);
+
+fn parse_file(path: &Path) -> Result<ParsedFile, String> {
+    let source_file = display_path(path);
+    let source_code = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", source_file, e))?;
+    let (statements, diagnostics) = parser::parse(&source_code);
+    Ok((path.to_path_buf(), source_file, statements, diagnostics))
+}
+
+/// Parse every module of the project, order the modules by their imports and
+/// check them imported-first so each module sees the exports of its imports.
+/// Fails when a file cannot be read or the imports form a cycle; the
+/// diagnostics of each file are in its `front_end`.
+pub fn analyze(project: &Project) -> Result<ProjectFrontEnd, String> {
+    let module_names: BTreeSet<&str> = project.modules.iter().map(|module| module.name.as_str()).collect();
+
+    let mut parsed = BTreeMap::new();
+    let mut imports = BTreeMap::new();
+    for module in &project.modules {
+        let (path, source_file, statements, diagnostics) = parse_file(&module.path)?;
+        let imported: Vec<String> = statements.iter()
+            .filter_map(|statement| match &statement.node {
+                Statement::Import(name) if module_names.contains(name.as_str()) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        imports.insert(module.name.clone(), imported);
+        parsed.insert(module.name.clone(), (path, source_file, statements, diagnostics));
+    }
+    let entry = project.entry.as_deref().map(parse_file).transpose()?;
+
+    let order = project::initialization_order(&imports)?;
+
+    let mut exports = BTreeMap::new();
+    let mut used_modules = BTreeSet::new();
+    let mut check = |name: String, imports: Vec<String>, globals: bool, (path, source_file, statements, diagnostics): ParsedFile| {
+        let front_end = check_parsed(statements, diagnostics, &exports, globals);
+        used_modules.extend(front_end.used_modules.iter().cloned());
+        exports.insert(name.clone(), front_end.exports.clone());
+        ModuleFrontEnd { name, source_file, path, imports, front_end }
+    };
+
+    let modules: Vec<ModuleFrontEnd> = order.into_iter()
+        .map(|name| {
+            let module = parsed.remove(&name).expect("every ordered module was parsed");
+            let module_imports = imports.remove(&name).unwrap_or_default();
+            check(name, module_imports, true, module)
+        })
+        .collect();
+    let entry = entry.map(|file| check("main".to_string(), Vec::new(), false, file));
+
+    Ok(ProjectFrontEnd { modules, entry, used_modules })
+}
+
+/// Where `build` stops
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    /// Write the C++ sources and headers to target/build
+    Cpp,
+    /// Also compile them into object files
+    Objects,
+    /// Also link the executable or archive the library
+    Output,
+}
+
+/// Settings for `build`
+#[derive(Clone, Debug)]
+pub struct BuildOptions {
+    pub stage: Stage,
+    /// Path of the executable or library instead of the one in target/
+    pub output: Option<PathBuf>,
+    /// Toolchain options from the command line; the manifest's [build] settings are added to them
+    pub toolchain: ToolchainArgs,
+}
+
+/// A generated C++ translation unit of a project and the object file built from it
+#[derive(Debug)]
+pub struct Unit {
+    pub name: String,
+    pub source: PathBuf,
+    pub object: PathBuf,
+    /// Hash of the source, the project headers it includes and the compiler command line
+    pub hash: u64,
+}
+
+/// Progress of `build`, reported while the stale units are compiled
+pub enum BuildEvent<'a> {
+    /// A compiler was started for `unit`, the `position`th of `stale` units to compile
+    Compiling { unit: &'a Unit, position: usize, stale: usize },
+    /// The compiler for `unit` exited; `output` is everything it printed
+    Compiled { unit: &'a Unit, success: bool, output: &'a str },
+}
+
+/// What `build` produced
+#[derive(Debug)]
+pub struct Built {
+    pub build_dir: PathBuf,
+    /// Translation units of the project, and how many of them were compiled
+    pub units: usize,
+    pub compiled: usize,
+    /// The executable or library, unless the build stopped before it
+    pub output: Option<PathBuf>,
+    /// The header of a library
+    pub header: Option<PathBuf>,
+}
+
+/// Build a project from its checked front end; the caller reports its
+/// diagnostics and does not build projects with errors
+pub fn build(
+    project: &Project,
+    mut front_end: ProjectFrontEnd,
+    options: &BuildOptions,
+    mut report: impl FnMut(BuildEvent),
+) -> Result<Built, String> {
+    let reproducible = options.toolchain.is_reproducible();
+    if reproducible {
+        // Diagnostics are reported; from here on source paths only end up in the generated code
+        for module in front_end.modules.iter_mut().chain(front_end.entry.as_mut()) {
+            module.source_file = project.portable_path(&module.path);
+        }
+    }
+
+    let target_dir = project.target_dir();
+    let build_dir = target_dir.join("build");
+    fs::create_dir_all(&build_dir).map_err(|e| format!("Could not create {}: {}", build_dir.display(), e))?;
+    let name = project.name();
+    let mut toolchain = Toolchain::from_args(&project_toolchain_args(project, &options.toolchain));
+    if reproducible {
+        if let Ok(root) = project.root.canonicalize() {
+            toolchain.prefix_maps.push((root, name.to_string()));
+        }
+    }
+    // Link inputs and include directories are part of the hashed command lines
+    let link_kind = match (project.kind(), options.stage) {
+        (ProjectKind::Executable, Stage::Output) => OutputKind::Executable,
+        _ => OutputKind::Object,
+    };
+    let toolchain = if options.stage == Stage::Cpp { toolchain } else { toolchain_with_runtime(&toolchain, link_kind)? };
+    let units = write_project_units(project, &front_end, &build_dir, &toolchain)?;
+
+    let header = match project.kind() {
+        ProjectKind::Library => {
+            let header_file = target_dir.join(format!("{}.hpp", name));
+            let classes = front_end.classes();
+            let modules: Vec<ModuleCode> = front_end.modules.iter().map(|module| module.code(&classes)).collect();
+            write(&header_file, &codegen::generate_library_header(name, &modules, &front_end.used_modules))?;
+            Some(header_file)
+        },
+        ProjectKind::Executable => None,
+    };
+    let mut built = Built { build_dir, units: units.len(), compiled: 0, output: None, header };
+    if options.stage == Stage::Cpp {
+        return Ok(built);
+    }
+
+    built.compiled = compile_units(&units, &toolchain, &mut report)?;
+    if options.stage == Stage::Objects {
+        return Ok(built);
+    }
+
+    let objects: Vec<PathBuf> = units.iter().map(|unit| unit.object.clone()).collect();
+    let output = match project.kind() {
+        ProjectKind::Executable => {
+            let default_output = target_dir.join(if cfg!(windows) { format!("{}.exe", name) } else { name.to_string() });
+            let output = options.output.clone().unwrap_or(default_output);
+            let hash = link_hash(&units, &toolchain.link_command_line(&objects, &output));
+            if !incremental::is_up_to_date(&output, hash) {
+                incremental::invalidate(&output);
+                match toolchain.link_command(&objects, &output).output() {
+                    Ok(result) if result.status.success() => record(&output, hash)?,
+                    Ok(result) => {
+                        return Err(format!(
+                            "Linking failed:\n{}\nTo link manually: {}",
+                            String::from_utf8_lossy(&result.stderr),
+                            toolchain.link_command_line(&objects, &output)
+                        ));
+                    },
+                    Err(e) => return Err(format!("Failed to run {}: {}", toolchain.cxx, e)),
+                }
+            }
+            output
+        },
+        ProjectKind::Library => {
+            let library = options.output.clone().unwrap_or_else(|| target_dir.join(format!("lib{}.a", name)));
+            let hash = link_hash(&units, &format!("{} {} {}", toolchain.ar, toolchain.archive_mode(), library.display()));
+            if !incremental::is_up_to_date(&library, hash) {
+                incremental::invalidate(&library);
+                // ar appends to existing archives, so start from scratch
+                let _ = fs::remove_file(&library);
+                toolchain.archive(&objects, &library)?;
+                record(&library, hash)?;
+            }
+            library
+        },
+    };
+    built.output = Some(output);
+    Ok(built)
+}
+
+/// Install (and for executables build) libnymya for this toolchain and point the
+/// toolchain at it; object files only need the runtime headers
+pub fn toolchain_with_runtime(toolchain: &Toolchain, kind: OutputKind) -> Result<Toolchain, String> {
+    let mut toolchain = toolchain.clone();
+    if toolchain.args.is_reproducible() {
+        toolchain.prefix_maps.push((runtime::runtime_dir(), "nymya-runtime".to_string()));
+    }
+    let (include_dir, library) = match kind {
+        OutputKind::Object => (runtime::install_sources()?, None),
+        OutputKind::Executable => {
+            let runtime = runtime::prepare(&toolchain)?;
+            (runtime.include_dir, Some(runtime.library))
+        },
+    };
+    toolchain.include_dirs.push(include_dir);
+    toolchain.link_inputs.extend(library);
+    Ok(toolchain)
+}
+
+// Toolchain options from the command line plus the manifest's [build] settings.
+// Command-line flags come last so they take precedence.
+fn project_toolchain_args(project: &Project, args: &ToolchainArgs) -> ToolchainArgs {
+    let mut args = args.clone();
+    args.cxxflags = project.cxxflags.iter().cloned().chain(args.cxxflags).collect();
+    args.lib_dirs = project.library_paths.iter().map(|dir| dir.display().to_string()).chain(args.lib_dirs).collect();
+    args.libs = project.libraries.iter().cloned().chain(args.libs).collect();
+    args
+}
+
+fn write(path: &Path, code: &str) -> Result<(), String> {
+    incremental::write_if_changed(path, code).map_err(|e| format!("Could not write {}: {}", path.display(), e))
+}
+
+fn record(output: &Path, hash: u64) -> Result<(), String> {
+    incremental::record(output, hash).map_err(|e| format!("Could not record build state of {}: {}", output.display(), e))
+}
+
+// Write the generated files of a project into target/build and describe its
+// translation units: one per module, plus `main` (executables) or the library
+// initializer. Files are only rewritten when their contents change.
+fn write_project_units(
+    project: &Project,
+    front_end: &ProjectFrontEnd,
+    build_dir: &Path,
+    toolchain: &Toolchain,
+) -> Result<Vec<Unit>, String> {
+    let classes = front_end.classes();
+    let modules: Vec<ModuleCode> = front_end.modules.iter().map(|module| module.code(&classes)).collect();
+    let mut headers = BTreeMap::new();
+    for module in &modules {
+        let header = codegen::generate_module_header(module);
+        write(&build_dir.join(codegen::module_header_name(module.name)), &header)?;
+        headers.insert(module.name, header);
+    }
+
+    // A unit depends on the headers of its module and of everything that module imports
+    let mut header_closure: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for module in &modules {
+        let mut closure = BTreeSet::from([module.name]);
+        for imported in module.imports {
+            closure.insert(imported.as_str());
+            closure.extend(header_closure.get(imported.as_str()).into_iter().flatten());
+        }
+        header_closure.insert(module.name, closure);
+    }
+
+    let unit = |name: &str, code: String, includes: &BTreeSet<&str>| -> Result<Unit, String> {
+        let source = build_dir.join(format!("{}.cpp", name));
+        let object = build_dir.join(format!("{}.o", name));
+        write(&source, &code)?;
+        let command_line = toolchain.command_line(&source, &object, OutputKind::Object);
+        let hash = incremental::hash_inputs(
+            std::iter::once(code.as_str())
+                .chain(includes.iter().map(|module| headers[module].as_str()))
+                .chain(std::iter::once(command_line.as_str())),
+        );
+        Ok(Unit { name: name.to_string(), source, object, hash })
+    };
+
+    let all_modules: BTreeSet<&str> = headers.keys().copied().collect();
+    let mut units = modules.iter()
+        .map(|module| unit(module.name, codegen::generate_module_source(module), &header_closure[module.name]))
+        .collect::<Result<Vec<Unit>, String>>()?;
+    match project.kind() {
+        ProjectKind::Executable => {
+            let entry = front_end.entry.as_ref().expect("executable projects have an entry file");
+            let description = format!("project {}", project.name());
+            let code = codegen::generate_program(&description, &modules, &entry.code(&classes), &entry.front_end.used_modules);
+            units.push(unit("main", code, &all_modules)?);
+        },
+        ProjectKind::Library => {
+            let name = format!("nymya_init_{}", project.name());
+            units.push(unit(&name, codegen::generate_library_init(project.name(), &modules), &all_modules)?);
+        },
+    }
+    Ok(units)
+}
+
+// Compile the units whose object file is missing or out of date, in parallel;
+// returns how many were compiled. Once a unit failed, the error is returned
+// after the running compilers are done.
+fn compile_units(units: &[Unit], toolchain: &Toolchain, report: &mut impl FnMut(BuildEvent)) -> Result<usize, String> {
+    let stale: Vec<&Unit> = units.iter()
+        .filter(|unit| !incremental::is_up_to_date(&unit.object, unit.hash))
+        .collect();
+    let jobs: Vec<(PathBuf, PathBuf)> = stale.iter().map(|unit| (unit.source.clone(), unit.object.clone())).collect();
+    let mut started = 0;
+    let mut failed = Vec::new();
+    let mut record_error = None;
+
+    for unit in &stale {
+        incremental::invalidate(&unit.object);
+    }
+    let success = toolchain.compile_objects(&jobs, |event| match event {
+        CompileEvent::Started(index) => {
+            started += 1;
+            report(BuildEvent::Compiling { unit: stale[index], position: started, stale: stale.len() });
+        },
+        CompileEvent::Finished { index, success, output } => {
+            let unit = stale[index];
+            report(BuildEvent::Compiled { unit, success, output });
+            if !success {
+                failed.push(unit);
+            } else if let Err(message) = record(&unit.object, unit.hash) {
+                record_error.get_or_insert(message);
+            }
+        },
+    });
+
+    if !success {
+        let manual: Vec<String> = failed.iter()
+            .map(|unit| format!("To compile {} manually: {}", unit.name, toolchain.command_line(&unit.source, &unit.object, OutputKind::Object)))
+            .collect();
+        return Err(format!("C++ compilation failed\n{}", manual.join("\n")));
+    }
+    match record_error {
+        Some(message) => Err(message),
+        None => Ok(stale.len()),
+    }
+}
+
+// Hash of a link or archive step: the hashes of its objects plus the command line
+fn link_hash(units: &[Unit], command_line: &str) -> u64 {
+    let object_hashes: Vec<String> = units.iter().map(|unit| format!("{:016x}", unit.hash)).collect();
+    incremental::hash_inputs(object_hashes.iter().map(String::as_str).chain(std::iter::once(command_line)))
+}
@@ -0,0 +1,1204 @@
+// C++ code generation. A single-file program becomes one translation unit with
+// `main()`; in a project every module becomes a header and a source file in its
+// own namespace, and `main()` (or `nymya_init_<library>()`) initializes them.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+use crate::ast::{
+    AssignmentTarget, BinaryOperator, Class, Enum, Expression, Function, MatchArm, MatchBody, Pattern, Spanned, Statement,
+    TypeExpr, UnaryOperator, OPERATOR_METHODS,
+};
+use crate::runtime;
+
+// Generate C++ code from parsed statements. Each statement is preceded by a
+// `#line` directive so C++ diagnostics and debuggers refer to the .nym source.
+// With `globals` the top-level variables are declared elsewhere (module
+// variables) and only assigned here.
+fn generate_cpp_from_statements(statements: &[Spanned<Statement>], source_file: &str, globals: bool) -> String {
+    let mut cpp_code = String::new();
+    generate_block(&mut cpp_code, statements, &cpp_string_literal(source_file), 1, globals);
+    cpp_code
+}
+
+// Statements of one block at the given nesting `depth`
+fn generate_block(cpp_code: &mut String, statements: &[Spanned<Statement>], source_literal: &str, depth: usize, globals: bool) {
+    let indent = "    ".repeat(depth);
+    let declare = if globals { "" } else { "auto " };
+
+    for spanned in statements {
+        let stmt = &spanned.node;
+        // Without a source literal (in lambda bodies) the file of the enclosing code is kept
+        if !matches!(
+            stmt,
+            Statement::Import(_) | Statement::Function(_) | Statement::Class(_) | Statement::Enum(_) | Statement::Interface(_)
+        ) {
+            match source_literal {
+                "" => cpp_code.push_str(&format!("#line {}\n", spanned.span.line)),
+                _ => cpp_code.push_str(&format!("#line {} {}\n", spanned.span.line, source_literal)),
+            }
+        }
+        match stmt {
+            Statement::Import(_module) => {
+                // Import statements don't generate executable code, just ensure the namespace exists
+                continue;
+            },
+            // Generated ahead of the code by `generate_declarations`
+            Statement::Function(_) | Statement::Class(_) | Statement::Enum(_) => continue,
+            // Only checked; generic code is instantiated with the implementing classes
+            Statement::Interface(_) => continue,
+            Statement::VariableDeclaration { name, ty, value } => {
+                let value_cpp = generate_cpp_for_initializer(value);
+                let declare = match ty {
+                    Some(ty) if !globals => format!("{} ", cpp_type(ty)),
+                    _ => declare.to_string(),
+                };
+                cpp_code.push_str(&format!("{}{}{} = {};\n", indent, declare, name, value_cpp));
+            },
+            // Module variables are declared ahead, so they are assigned through `std::tie`
+            Statement::Destructure { names, value } if globals => {
+                let targets: Vec<&str> = names.iter().map(|name| if name == "_" { "std::ignore" } else { name }).collect();
+                cpp_code.push_str(&format!("{}std::tie({}) = {};\n", indent, targets.join(", "), generate_cpp_for_expression(value)));
+            },
+            Statement::Destructure { names, value } => {
+                let bindings: Vec<String> = names
+                    .iter()
+                    .enumerate()
+                    .map(|(position, name)| if name == "_" { format!("nymya_ignored_{}", position) } else { name.clone() })
+                    .collect();
+                cpp_code.push_str(&format!("{}auto [{}] = {};\n", indent, bindings.join(", "), generate_cpp_for_expression(value)));
+            },
+            Statement::Assignment { target, operator, value } => {
+                let target_cpp = generate_assignment_target(target);
+                let assign = operator.map_or("=".to_string(), |operator| format!("{}=", cpp_binary_operator(operator)));
+                let value_cpp = generate_cpp_for_expression(value);
+                cpp_code.push_str(&format!("{}{} {} {};\n", indent, target_cpp, assign, value_cpp));
+            },
+            Statement::Expression(Expression::Match { value, arms, exhaustive, .. }) => {
+                generate_match_statement(cpp_code, value, arms, *exhaustive, source_literal, depth);
+            },
+            Statement::Expression(expression) => {
+                cpp_code.push_str(&format!("{}{};\n", indent, generate_cpp_for_expression(expression)));
+            },
+            Statement::If { condition, then_branch, else_branch } => {
+                cpp_code.push_str(&format!("{}if ({}) {{\n", indent, generate_cpp_for_expression(condition)));
+                generate_block(cpp_code, then_branch, source_literal, depth + 1, false);
+                let mut else_branch = else_branch.as_deref();
+                // Flatten `else if` chains
+                while let Some([Spanned { node: Statement::If { condition, then_branch, else_branch: next }, .. }]) = else_branch {
+                    cpp_code.push_str(&format!("{}}} else if ({}) {{\n", indent, generate_cpp_for_expression(condition)));
+                    generate_block(cpp_code, then_branch, source_literal, depth + 1, false);
+                    else_branch = next.as_deref();
+                }
+                if let Some(else_branch) = else_branch {
+                    cpp_code.push_str(&format!("{}}} else {{\n", indent));
+                    generate_block(cpp_code, else_branch, source_literal, depth + 1, false);
+                }
+                cpp_code.push_str(&format!("{}}}\n", indent));
+            },
+            Statement::IfLet { name, value, then_branch, else_branch } => {
+                cpp_code.push_str(&format!("{}if (auto nymya_value = {}) {{\n", indent, generate_cpp_for_expression(value)));
+                cpp_code.push_str(&format!("{}    auto {} = *nymya_value;\n", indent, name));
+                generate_block(cpp_code, then_branch, source_literal, depth + 1, false);
+                if let Some(else_branch) = else_branch {
+                    cpp_code.push_str(&format!("{}}} else {{\n", indent));
+                    generate_block(cpp_code, else_branch, source_literal, depth + 1, false);
+                }
+                cpp_code.push_str(&format!("{}}}\n", indent));
+            },
+            Statement::While { condition, body } => {
+                cpp_code.push_str(&format!("{}while ({}) {{\n", indent, generate_cpp_for_expression(condition)));
+                generate_block(cpp_code, body, source_literal, depth + 1, false);
+                cpp_code.push_str(&format!("{}}}\n", indent));
+            },
+            Statement::Return(None) => cpp_code.push_str(&format!("{}return;\n", indent)),
+            Statement::Return(Some(value)) => {
+                cpp_code.push_str(&format!("{}return {};\n", indent, generate_cpp_for_expression(value)));
+            },
+        }
+    }
+}
+
+// `(a, _) = value` assigns through `std::tie(a, std::ignore)`
+fn generate_assignment_target(target: &AssignmentTarget) -> String {
+    match target {
+        AssignmentTarget::Variable(name) => name.clone(),
+        AssignmentTarget::Field { object, name } => format!("{}->{}", generate_cpp_for_object(object), name),
+        AssignmentTarget::Index { array, index } => {
+            format!("{}[{}]", generate_cpp_for_expression(array), generate_cpp_for_expression(index))
+        },
+        AssignmentTarget::Tuple(targets) => {
+            let targets: Vec<String> = targets.iter().map(generate_assignment_target).collect();
+            format!("std::tie({})", targets.join(", "))
+        },
+        AssignmentTarget::Ignored => "std::ignore".to_string(),
+    }
+}
+
+// A `match` statement is an `if` chain rather than `std::visit`, so that a
+// `return` in an arm returns from the enclosing function. The last arm of an
+// exhaustive match is the `else`.
+fn generate_match_statement(
+    cpp_code: &mut String,
+    value: &Expression,
+    arms: &[MatchArm],
+    exhaustive: bool,
+    source_literal: &str,
+    depth: usize,
+) {
+    let indent = "    ".repeat(depth);
+    cpp_code.push_str(&format!("{}{{\n", indent));
+    cpp_code.push_str(&format!("{}    const auto& nymya_match = {};\n", indent, generate_cpp_for_initializer(value)));
+    for (position, arm) in arms.iter().enumerate() {
+        let last = exhaustive && position + 1 == arms.len();
+        let condition = if last { None } else { match_condition(&arm.pattern) };
+        let opening = match (position, condition) {
+            (0, Some(condition)) => format!("if ({}) {{", condition),
+            (0, None) => "{".to_string(),
+            (_, Some(condition)) => format!("}} else if ({}) {{", condition),
+            (_, None) => "} else {".to_string(),
+        };
+        cpp_code.push_str(&format!("{}    {}\n", indent, opening));
+        if let Pattern::Variant { enum_name, variant, bindings } = &arm.pattern {
+            // The `nymya_variant` of an earlier condition is still in scope in
+            // the `else` and cannot be declared again there
+            let access = if last { "nymya_last->" } else { "nymya_variant->" };
+            if last && binds_values(bindings) {
+                cpp_code.push_str(&format!(
+                    "{}        const auto* nymya_last = &std::get<{}>(nymya_match);\n",
+                    indent, variant_struct_name(enum_name, variant)
+                ));
+            }
+            for binding in variant_bindings(bindings, access) {
+                cpp_code.push_str(&format!("{}        {}\n", indent, binding));
+            }
+        }
+        match &arm.body {
+            MatchBody::Block(block) => generate_block(cpp_code, block, source_literal, depth + 2, false),
+            MatchBody::Expression(body) => cpp_code.push_str(&format!("{}        {};\n", indent, generate_cpp_for_expression(body))),
+        }
+    }
+    cpp_code.push_str(&format!("{}    }}\n{}}}\n", indent, indent));
+}
+
+// A `match` used as a value: `std::visit` over the variants of an enum, or
+// else an immediately invoked lambda comparing the value with each literal
+fn generate_match_expression(value: &Expression, arms: &[MatchArm], ty: &Option<TypeExpr>, exhaustive: bool) -> String {
+    let returns = ty.as_ref().map_or_else(String::new, |ty| format!(" -> {}", cpp_type(ty)));
+    let on_enum = arms.iter().any(|arm| matches!(arm.pattern, Pattern::Variant { .. }));
+    if on_enum {
+        let handlers: Vec<String> = arms
+            .iter()
+            .map(|arm| match &arm.pattern {
+                Pattern::Variant { enum_name, variant, bindings } => {
+                    let param = if binds_values(bindings) { " nymya_variant" } else { "" };
+                    let mut statements = variant_bindings(bindings, "nymya_variant.");
+                    statements.push(format!("return {};", match_arm_value(arm)));
+                    format!(
+                        "[&](const {}&{}){} {{ {} }}",
+                        variant_struct_name(enum_name, variant), param, returns, statements.join(" ")
+                    )
+                },
+                _ => format!("[&](const auto&){} {{ return {}; }}", returns, match_arm_value(arm)),
+            })
+            .collect();
+        return format!("std::visit(nymya_overloaded{{{}}}, {})", handlers.join(", "), generate_cpp_for_expression(value));
+    }
+    let mut code = format!("[&](){} {{ const auto& nymya_match = {}; ", returns, generate_cpp_for_initializer(value));
+    for (position, arm) in arms.iter().enumerate() {
+        let last = exhaustive && position + 1 == arms.len();
+        match match_condition(&arm.pattern).filter(|_| !last) {
+            Some(condition) => code.push_str(&format!("if ({}) return {}; ", condition, match_arm_value(arm))),
+            None => code.push_str(&format!("return {}; ", match_arm_value(arm))),
+        }
+    }
+    code.push_str("}()");
+    code
+}
+
+// Semantic analysis only lets expression arms into a match used as a value
+fn match_arm_value(arm: &MatchArm) -> String {
+    match &arm.body {
+        MatchBody::Expression(body) => generate_cpp_for_expression(body),
+        MatchBody::Block(_) => String::new(),
+    }
+}
+
+// Test of an arm in the `if` chain of a match on `nymya_match`; `None` for `_`.
+// A variant whose values are used is unpacked through `nymya_variant`.
+fn match_condition(pattern: &Pattern) -> Option<String> {
+    match pattern {
+        Pattern::Wildcard => None,
+        Pattern::Literal(literal) => Some(format!("nymya_match == {}", generate_cpp_for_expression(literal))),
+        Pattern::Variant { enum_name, variant, bindings } if binds_values(bindings) => Some(format!(
+            "const auto* nymya_variant = std::get_if<{}>(&nymya_match)",
+            variant_struct_name(enum_name, variant)
+        )),
+        Pattern::Variant { enum_name, variant, .. } => {
+            Some(format!("std::holds_alternative<{}>(nymya_match)", variant_struct_name(enum_name, variant)))
+        },
+    }
+}
+
+fn binds_values(bindings: &[String]) -> bool {
+    bindings.iter().any(|name| name != "_")
+}
+
+// `auto name = <access>_0;` for each payload value a pattern names
+fn variant_bindings(bindings: &[String], access: &str) -> Vec<String> {
+    bindings
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| *name != "_")
+        .map(|(position, name)| format!("auto {} = {}_{};", name, access, position))
+        .collect()
+}
+
+// Name of the C++ struct holding the payload of an enum variant
+fn variant_struct_name(enum_name: &str, variant: &str) -> String {
+    format!("{}_{}", cpp_qualified_name(enum_name), variant)
+}
+
+// C++ spelling of a declaration of another module, like `shapes.Circle`
+fn cpp_qualified_name(name: &str) -> String {
+    name.replace('.', "::")
+}
+
+fn generate_cpp_arguments(args: &[Expression]) -> Vec<String> {
+    args.iter().map(generate_cpp_for_expression).collect()
+}
+
+fn cpp_binary_operator(operator: BinaryOperator) -> &'static str {
+    match operator {
+        BinaryOperator::Or => "||",
+        BinaryOperator::And => "&&",
+        BinaryOperator::Eq => "==",
+        BinaryOperator::Ne => "!=",
+        BinaryOperator::Lt => "<",
+        BinaryOperator::Le => "<=",
+        BinaryOperator::Gt => ">",
+        BinaryOperator::Ge => ">=",
+        BinaryOperator::Add => "+",
+        BinaryOperator::Sub => "-",
+        BinaryOperator::Mul => "*",
+        BinaryOperator::Div => "/",
+        BinaryOperator::Mod => "%",
+        BinaryOperator::Coalesce => unreachable!("`??` is lowered to `value_or`"),
+    }
+}
+
+fn cpp_unary_operator(operator: UnaryOperator) -> &'static str {
+    match operator {
+        UnaryOperator::Neg => "-",
+        UnaryOperator::Not => "!",
+    }
+}
+
+// C++ element type of a list literal, taken from its first element
+fn list_element_type(elements: &[Expression]) -> String {
+    match elements.first() {
+        None | Some(Expression::Integer(_)) => "int".to_string(),
+        Some(Expression::Float(_)) => "double".to_string(),
+        Some(Expression::Boolean(_)) => "bool".to_string(),
+        Some(Expression::String(_)) => "std::string".to_string(),
+        Some(first) => format!("std::decay_t<decltype({})>", generate_cpp_for_expression(first)),
+    }
+}
+
+// Generate C++ code for expressions
+fn generate_cpp_for_expression(expr: &Expression) -> String {
+    match expr {
+        Expression::FunctionCall { module, function, args } => {
+            let args_cpp = generate_cpp_arguments(args);
+            // Convert nested module names from dot notation to C++ namespace notation
+            let module_cpp = module.replace(".", "::");
+            format!("{}::{}({})", module_cpp, function, args_cpp.join(", "))
+        },
+        Expression::Call { function, type_args, args } => {
+            let function = match function.rsplit_once('.') {
+                Some((module, name)) => format!("{}::{}", cpp_qualified_name(module), cpp_function_name(name)),
+                None => cpp_function_name(function).to_string(),
+            };
+            format!("{}{}({})", function, cpp_type_arguments(type_args), generate_cpp_arguments(args).join(", "))
+        },
+        Expression::Construct { class, type_args, args } => {
+            let class_cpp = format!("{}{}", class_struct_name(class), cpp_type_arguments(type_args));
+            format!("std::make_shared<{}>({})", class_cpp, generate_cpp_arguments(args).join(", "))
+        },
+        Expression::ModuleMember { module, name } => format!("{}::{}", module.replace(".", "::"), name),
+        Expression::Field { object, name } => format!("{}->{}", generate_cpp_for_object(object), name),
+        Expression::ArrayAccess { array, index } => {
+            let array_cpp = generate_cpp_for_expression(array.as_ref());
+            let index_cpp = generate_cpp_for_expression(index.as_ref());
+            format!("{}[{}]", array_cpp, index_cpp)
+        },
+        Expression::ArrayMethodCall { array, method, args } => {
+            let array_cpp = generate_cpp_for_expression(array.as_ref());
+            let args_cpp = generate_cpp_arguments(args);
+
+            // Map Nya Elyria's consciousness-integrated array methods to appropriate C++ equivalents
+            match method.as_str() {
+                "append" => format!("{}.push_back({})", array_cpp, args_cpp.join(", ")),
+                "length" | "size" => format!("{}.size()", array_cpp),
+                "get" | "at" => format!("{}[{}]", array_cpp, args_cpp.join(", ")), // Use [] for simple indexing
+                "set" => {
+                    if args_cpp.len() >= 2 {
+                        format!("{}[{}] = {}", array_cpp, args_cpp[0], args_cpp[1])
+                    } else {
+                        format!("{}[0] = {}", array_cpp, args_cpp.first().unwrap_or(&"0".to_string()))
+                    }
+                },
+                _ => format!("{}->{}({})", array_cpp, method, args_cpp.join(", ")) // Fallback for other methods
+            }
+        },
+        Expression::SuperCall { method, args } => format!("nymya_base::{}({})", method, generate_cpp_arguments(args).join(", ")),
+        Expression::EnumVariant { enum_name, variant, args } => format!(
+            "{}{{{}({})}}",
+            cpp_qualified_name(enum_name), variant_struct_name(enum_name, variant), generate_cpp_arguments(args).join(", ")
+        ),
+        Expression::Match { value, arms, ty, exhaustive } => generate_match_expression(value, arms, ty, *exhaustive),
+        Expression::ValueCall { callee, args } => {
+            format!("{}({})", generate_cpp_for_object(callee), generate_cpp_arguments(args).join(", "))
+        },
+        Expression::MethodCall { object, method, args } => {
+            format!("{}->{}({})", generate_cpp_for_object(object), method, generate_cpp_arguments(args).join(", "))
+        },
+        Expression::ExtensionCall { function, type_args, args } => {
+            format!("{}{}({})", function, cpp_type_arguments(type_args), generate_cpp_arguments(args).join(", "))
+        },
+        // Captures copy the variables, so a lambda can outlive the scope it was created in.
+        // It is wrapped in its exact `std::function` type so that C++ picks the same
+        // overload as `sema` when overloads take different `Func` types.
+        Expression::Lambda { params, returns, body } => {
+            let mut func_args: Vec<TypeExpr> = params.iter().map(|param| param.ty.clone()).collect();
+            if func_args.is_empty() {
+                func_args.push(TypeExpr::named("Void"));
+            }
+            func_args.push(returns.clone());
+            let func = TypeExpr::Named { name: "Func".to_string(), args: func_args };
+            let params: Vec<String> = params.iter().map(|param| format!("{} {}", cpp_type(&param.ty), param.name)).collect();
+            let mut body_cpp = String::new();
+            generate_block(&mut body_cpp, body, "", 2, false);
+            format!("{}([=]({}) -> {} {{\n{}    }})", cpp_type(&func), params.join(", "), cpp_type(returns), body_cpp)
+        },
+        Expression::ArrayLiteral { elements, element_type } => {
+            let elements_cpp = generate_cpp_arguments(elements);
+            let element_cpp = element_type.as_ref().map_or_else(|| list_element_type(elements), cpp_type);
+            format!("std::vector<{}>{{{}}}", element_cpp, elements_cpp.join(", "))
+        },
+        // Without a known type, string literals become `std::string` rather than `const char*`
+        Expression::Tuple { elements, ty: Some(ty) } => format!("{}({})", cpp_type(ty), generate_cpp_arguments(elements).join(", ")),
+        Expression::Tuple { elements, ty: None } => {
+            let elements: Vec<String> = elements.iter().map(generate_cpp_for_initializer).collect();
+            format!("std::make_tuple({})", elements.join(", "))
+        },
+        Expression::TupleElement { tuple, index } => format!("std::get<{}>({})", index, generate_cpp_for_expression(tuple)),
+        // Objects are held by `std::shared_ptr`, so `this` as a value must share
+        // ownership; in a derived class it is cast back from the root class
+        Expression::Variable(name) if name == "this" => {
+            "std::static_pointer_cast<std::remove_pointer_t<decltype(this)>>(this->shared_from_this())".to_string()
+        },
+        Expression::Variable(name) => name.clone(),
+        Expression::Integer(value) => value.to_string(),
+        // `{:?}` always keeps a decimal point or exponent, so C++ reads a double
+        Expression::Float(value) => format!("{:?}", value),
+        Expression::Boolean(value) => value.to_string(),
+        Expression::None => "std::nullopt".to_string(),
+        Expression::Unwrapped => "(*nymya_value)".to_string(),
+        // An exact match for the overload `sema` picked, so C++ cannot rank another one higher
+        Expression::Convert { value, ty } => format!("static_cast<{}>({})", cpp_type(ty), generate_cpp_for_expression(value)),
+        // The object is evaluated once; the member only when it is not none
+        Expression::OptionalChain { object, member, void } => {
+            let object_cpp = generate_cpp_for_expression(object);
+            let member_cpp = generate_cpp_for_expression(member);
+            if *void {
+                format!("[&] {{ auto&& nymya_value = {}; if (nymya_value) {{ {}; }} }}()", object_cpp, member_cpp)
+            } else {
+                format!(
+                    "[&] {{ auto&& nymya_value = {}; return nymya_value ? std::optional({}) : std::nullopt; }}()",
+                    object_cpp, member_cpp
+                )
+            }
+        },
+        Expression::BinaryOp { left, operator: BinaryOperator::Coalesce, right } => {
+            format!("{}.value_or({})", generate_cpp_for_operand(left), generate_cpp_for_expression(right))
+        },
+        Expression::String(content) => format!("\"{}\"", escape_quotes(content)),
+        Expression::UnaryOp { operator, operand } => {
+            // `-(-x)` must not become `--x`, which decrements `x`
+            let operand_cpp = match operand.as_ref() {
+                Expression::UnaryOp { .. } => format!("({})", generate_cpp_for_expression(operand)),
+                _ => generate_cpp_for_operand(operand),
+            };
+            format!("{}{}", cpp_unary_operator(*operator), operand_cpp)
+        },
+        Expression::BinaryOp { left, operator, right } => {
+            let left_cpp = generate_cpp_for_operand(left);
+            let right_cpp = generate_cpp_for_operand(right);
+
+            // Handle string concatenation specially since C++ doesn't allow direct concatenation
+            // of string literals with other types
+            if *operator == BinaryOperator::Add {
+                // Only apply special handling when one side is an actual string literal
+                if left_cpp.starts_with('"') || right_cpp.starts_with('"') {
+                    let left_str = if left_cpp.starts_with('"') {
+                        format!("std::string({})", left_cpp)
+                    } else {
+                        left_cpp
+                    };
+                    let right_str = if right_cpp.starts_with('"') {
+                        format!("std::string({})", right_cpp)
+                    } else {
+                        right_cpp
+                    };
+                    return format!("{} + {}", left_str, right_str);
+                }
+            }
+
+            format!("{} {} {}", left_cpp, cpp_binary_operator(*operator), right_cpp)
+        },
+    }
+}
+
+// The object of a field access or method call; `this.x` is plain `this->x`, and
+// operations on class values like `(a + b).show()` keep their parentheses
+fn generate_cpp_for_object(object: &Expression) -> String {
+    match object {
+        Expression::Variable(name) if name == "this" => "this".to_string(),
+        Expression::BinaryOp { .. } | Expression::UnaryOp { .. } => format!("({})", generate_cpp_for_expression(object)),
+        _ => generate_cpp_for_expression(object),
+    }
+}
+
+// C++ type for a NymyaLang type. A class `Name` is an alias of
+// `std::shared_ptr<Name_impl>`, so classes and type parameters are spelled alike.
+fn cpp_type(ty: &TypeExpr) -> String {
+    let (name, args) = match ty {
+        TypeExpr::Named { name, args } => (name, args),
+        TypeExpr::Optional(inner) => return format!("std::optional<{}>", cpp_type(inner)),
+        TypeExpr::Tuple(elements) => {
+            let elements: Vec<String> = elements.iter().map(cpp_type).collect();
+            return format!("std::tuple<{}>", elements.join(", "));
+        },
+    };
+    match name.as_str() {
+        "Int" => "int".to_string(),
+        "Float" => "double".to_string(),
+        "Bool" => "bool".to_string(),
+        "String" => "std::string".to_string(),
+        "Void" => "void".to_string(),
+        "List" => format!("std::vector<{}>", args.first().map_or_else(|| "int".to_string(), cpp_type)),
+        // `Func[Float, Void]` is `std::function<void(double)>`; `Func[Void, R]` takes nothing
+        "Func" => {
+            let Some((returns, params)) = args.split_last() else { return "std::function<void()>".to_string() };
+            let params: Vec<String> = match params {
+                [TypeExpr::Named { name, args }] if name == "Void" && args.is_empty() => Vec::new(),
+                _ => params.iter().map(cpp_type).collect(),
+            };
+            format!("std::function<{}({})>", cpp_type(returns), params.join(", "))
+        },
+        _ => format!("{}{}", cpp_qualified_name(name), cpp_type_arguments(args)),
+    }
+}
+
+// `<int, std::string>`, or nothing without type arguments
+fn cpp_type_arguments(args: &[TypeExpr]) -> String {
+    if args.is_empty() {
+        return String::new();
+    }
+    let args: Vec<String> = args.iter().map(cpp_type).collect();
+    format!("<{}>", args.join(", "))
+}
+
+// `func main()` cannot be called `main` in C++, where that is the entry point
+fn cpp_function_name(name: &str) -> &str {
+    if name == "main" { "nymya_main" } else { name }
+}
+
+// A program declaring `func main()` without calling it at the top level runs it
+// after the top-level statements, as its entry point
+fn calls_implicit_main(statements: &[Spanned<Statement>]) -> bool {
+    let declares_main = statements.iter().any(|statement| {
+        matches!(&statement.node, Statement::Function(function) if function.name == "main" && function.params.is_empty())
+    });
+    let calls_main = statements.iter().any(|statement| {
+        matches!(&statement.node, Statement::Expression(Expression::Call { function, .. }) if function == "main")
+    });
+    declares_main && !calls_main
+}
+
+// Name of the C++ struct holding the fields and methods of a class
+fn class_struct_name(class: &str) -> String {
+    format!("{}_impl", cpp_qualified_name(class))
+}
+
+// `template <typename T, typename U>` line for generic declarations
+fn template_header(type_params: &[String]) -> String {
+    if type_params.is_empty() {
+        return String::new();
+    }
+    let params: Vec<String> = type_params.iter().map(|param| format!("typename {}", param)).collect();
+    format!("template <{}>\n", params.join(", "))
+}
+
+fn cpp_parameters(function: &Function) -> String {
+    let params: Vec<String> = function.params.iter().map(|param| format!("{} {}", cpp_type(&param.ty), param.name)).collect();
+    params.join(", ")
+}
+
+// C++ declarations of the classes, enums and functions of a file: forward
+// declarations of the classes first, so everything can refer to them in any
+// order, then the enums, the function prototypes, the class definitions and
+// finally the bodies of generic functions and methods, which C++ needs wherever
+// they are used. `globals` declares the variables of a module ahead of those
+// bodies, which may use them. The bodies of the other functions and methods are
+// returned separately, as the definitions a module keeps in its own source.
+fn generate_declarations(module: &ModuleCode, globals: &str) -> (String, String) {
+    let statements = module.statements;
+    let source_literal = cpp_string_literal(module.source_file);
+    let mut forward = String::new();
+    let mut enums = String::new();
+    let mut prototypes = String::new();
+    let mut classes = String::new();
+    let mut templates = String::new();
+    let mut bodies = String::new();
+    let declared: HashMap<&str, &Class> = statements
+        .iter()
+        .filter_map(|statement| match &statement.node {
+            Statement::Class(class) => Some((class.name.as_str(), class)),
+            _ => None,
+        })
+        .collect();
+    let mut defined = HashSet::new();
+    let declared_enums: Vec<(&Enum, usize)> = statements
+        .iter()
+        .filter_map(|statement| match &statement.node {
+            Statement::Enum(declaration) => Some((declaration, statement.span.line)),
+            _ => None,
+        })
+        .collect();
+    for (declaration, _) in &declared_enums {
+        define_enum(&mut enums, declaration, &declared_enums, &mut defined, &source_literal);
+    }
+
+    for statement in statements {
+        match &statement.node {
+            Statement::Function(function) => {
+                let (name, params) = match (&function.receiver, function.extension_name()) {
+                    // The receiver of an extension method becomes its first parameter
+                    (Some(receiver), Some(name)) => {
+                        let mut params = format!("{} nymya_this", cpp_type(receiver));
+                        if !function.params.is_empty() {
+                            params = format!("{}, {}", params, cpp_parameters(function));
+                        }
+                        (name, params)
+                    },
+                    _ => (cpp_function_name(&function.name).to_string(), cpp_parameters(function)),
+                };
+                let signature = format!("{} {}({})", cpp_type(&function.returns), name, params);
+                let template = template_header(&function.type_params);
+                prototypes.push_str(&format!("{}{};\n", template, signature));
+                let code = if template.is_empty() { &mut bodies } else { &mut templates };
+                code.push_str(&format!("#line {} {}\n", statement.span.line, source_literal));
+                code.push_str(&format!("{}{} {{\n", template, signature));
+                generate_block(code, &function.body, &source_literal, 1, false);
+                code.push_str("}\n\n");
+            },
+            Statement::Class(class) => {
+                let template = template_header(&class.type_params);
+                let struct_name = class_struct_name(&class.name);
+                let params: Vec<TypeExpr> = class.type_params.iter().map(TypeExpr::named).collect();
+                forward.push_str(&format!("{}struct {};\n", template, struct_name));
+                forward.push_str(&format!(
+                    "{}using {} = std::shared_ptr<{}{}>;\n",
+                    template, class.name, struct_name, cpp_type_arguments(&params)
+                ));
+                define_class(&mut classes, class, module, &declared, &mut defined, &source_literal);
+                let qualified = format!("{}{}::", struct_name, cpp_type_arguments(&params));
+                for member in class.init.iter().chain(&class.methods) {
+                    let function = &member.node;
+                    let mut body = &function.body[..];
+                    let signature = if class.init.as_ref().is_some_and(|init| std::ptr::eq(init, member)) {
+                        // A leading `super(...)` initializes the base class before the body runs
+                        let mut initializer = String::new();
+                        if let Some((first, rest)) = body.split_first() {
+                            if let Statement::Expression(Expression::SuperCall { method, args }) = &first.node {
+                                if method == "init" {
+                                    initializer = format!(" : nymya_base({})", generate_cpp_arguments(args).join(", "));
+                                    body = rest;
+                                }
+                            }
+                        }
+                        format!("{}{}({}){}", qualified, struct_name, cpp_parameters(function), initializer)
+                    } else {
+                        format!("{} {}{}({})", cpp_type(&function.returns), qualified, function.name, cpp_parameters(function))
+                    };
+                    let template = format!("{}{}", template, template_header(&function.type_params));
+                    let code = if template.is_empty() { &mut bodies } else { &mut templates };
+                    code.push_str(&format!("#line {} {}\n", member.span.line, source_literal));
+                    code.push_str(&format!("{}{} {{\n", template, signature));
+                    generate_block(code, body, &source_literal, 1, false);
+                    code.push_str("}\n\n");
+                }
+            },
+            _ => {},
+        }
+    }
+
+    if forward.is_empty() && enums.is_empty() && prototypes.is_empty() {
+        return (globals.to_string(), bodies);
+    }
+    let section = |code: String| if code.is_empty() { code } else { code + "\n" };
+    let globals = section(globals.to_string());
+    let declarations = format!("// Classes and functions\n{}{}{}{}{}{}", section(forward), enums, section(prototypes), classes, globals, templates);
+    (declarations, bodies)
+}
+
+// Define an enum after the enums its variants hold, since `std::variant`
+// needs complete types. Enums come before classes, whose fields may hold them.
+fn define_enum<'a>(
+    code: &mut String,
+    declaration: &'a Enum,
+    declared: &[(&'a Enum, usize)],
+    defined: &mut HashSet<&'a str>,
+    source_literal: &str,
+) {
+    if !defined.insert(&declaration.name) {
+        return;
+    }
+    let mut line = 0;
+    for (other, other_line) in declared {
+        if std::ptr::eq(*other, declaration) {
+            line = *other_line;
+        } else if declaration.variants.iter().flat_map(|variant| &variant.node.payload).any(|ty| mentions_type(ty, &other.name)) {
+            define_enum(code, other, declared, defined, source_literal);
+        }
+    }
+    code.push_str(&generate_enum(declaration, line, source_literal));
+}
+
+fn mentions_type(ty: &TypeExpr, name: &str) -> bool {
+    match ty {
+        TypeExpr::Named { name: ty_name, args } => ty_name == name || args.iter().any(|arg| mentions_type(arg, name)),
+        TypeExpr::Optional(inner) => mentions_type(inner, name),
+        TypeExpr::Tuple(elements) => elements.iter().any(|element| mentions_type(element, name)),
+    }
+}
+
+// A struct for each variant, holding its payload values as `_0`, `_1`, ..., and
+// the enum as a `std::variant` of them. Variants compare by their values, unless
+// one holds a function, which C++ cannot compare.
+fn generate_enum(declaration: &Enum, line: usize, source_literal: &str) -> String {
+    let mut code = String::new();
+    let mut alternatives = Vec::new();
+    for variant in &declaration.variants {
+        let struct_name = variant_struct_name(&declaration.name, &variant.node.name);
+        let payload = &variant.node.payload;
+        code.push_str(&format!("#line {} {}\n", variant.span.line, source_literal));
+        code.push_str(&format!("struct {} {{\n", struct_name));
+        let fields: Vec<String> = payload.iter().enumerate().map(|(position, ty)| format!("{} _{}", cpp_type(ty), position)).collect();
+        for field in &fields {
+            code.push_str(&format!("    {}{{}};\n", field));
+        }
+        // The default constructor is for class fields and module variables, which start out empty
+        if !fields.is_empty() {
+            let initializers: Vec<String> = (0..fields.len()).map(|position| format!("_{0}(_{0})", position)).collect();
+            code.push_str(&format!("    {}() = default;\n", struct_name));
+            code.push_str(&format!("    {}({}) : {} {{}}\n", struct_name, fields.join(", "), initializers.join(", ")));
+        }
+        if !payload.iter().any(|ty| mentions_type(ty, "Func")) {
+            let (params, equal) = if payload.is_empty() {
+                (format!("const {0}&, const {0}&", struct_name), "true".to_string())
+            } else {
+                let equal: Vec<String> = (0..payload.len()).map(|position| format!("a._{0} == b._{0}", position)).collect();
+                (format!("const {0}& a, const {0}& b", struct_name), equal.join(" && "))
+            };
+            code.push_str(&format!("    friend bool operator==({}) {{ return {}; }}\n", params, equal));
+            code.push_str(&format!("    friend bool operator!=(const {0}& a, const {0}& b) {{ return !(a == b); }}\n", struct_name));
+        }
+        code.push_str("};\n");
+        alternatives.push(struct_name);
+    }
+    code.push_str(&format!("#line {} {}\n", line, source_literal));
+    code.push_str(&format!("using {} = std::variant<{}>;\n\n", declaration.name, alternatives.join(", ")));
+    code
+}
+
+// Define a class after its base classes, since C++ needs a base to be
+// complete before a struct can derive from it. The headers of the modules
+// imported define their classes first.
+fn define_class<'a>(
+    code: &mut String,
+    class: &'a Class,
+    module: &ModuleCode,
+    declared: &HashMap<&str, &'a Class>,
+    defined: &mut HashSet<&'a str>,
+    source_literal: &str,
+) {
+    if !defined.insert(&class.name) {
+        return;
+    }
+    if let Some(TypeExpr::Named { name, .. }) = &class.base {
+        if let Some(base) = declared.get(name.as_str()) {
+            define_class(code, base, module, declared, defined, source_literal);
+        }
+    }
+    code.push_str(&generate_class(class, module, source_literal));
+}
+
+// Definition of the struct behind a class, with its fields and the
+// declarations of its initializer and methods. Only the root of a class
+// hierarchy derives from `std::enable_shared_from_this`, and methods are
+// virtual in classes that other classes, in any module, extend.
+fn generate_class(class: &Class, module: &ModuleCode, source_literal: &str) -> String {
+    let struct_name = class_struct_name(&class.name);
+    let params: Vec<TypeExpr> = class.type_params.iter().map(TypeExpr::named).collect();
+    let qualified = qualified_class_name(module.name, &class.name);
+    let extended = module.classes.iter().any(|(other_name, other)| {
+        let other_module = other_name.rsplit_once('.').map_or("", |(other_module, _)| other_module);
+        matches!(&other.base, Some(TypeExpr::Named { name, .. }) if qualified_class_name(other_module, name) == qualified)
+    });
+    let mut code = match &class.base {
+        Some(TypeExpr::Named { name, args }) => {
+            let base = format!("{}{}", class_struct_name(name), cpp_type_arguments(args));
+            format!(
+                "{}struct {} : {} {{\n    using nymya_base = {};\n",
+                template_header(&class.type_params), struct_name, base, base
+            )
+        },
+        _ => format!(
+            "{}struct {} : std::enable_shared_from_this<{}{}> {{\n",
+            template_header(&class.type_params), struct_name, struct_name, cpp_type_arguments(&params)
+        ),
+    };
+    if extended && class.base.is_none() {
+        code.push_str(&format!("    virtual ~{}() = default;\n", struct_name));
+    }
+    for field in &class.fields {
+        code.push_str(&format!("#line {} {}\n", field.span.line, source_literal));
+        code.push_str(&format!("    {} {}{{}};\n", cpp_type(&field.node.ty), field.node.name));
+    }
+    if let Some(init) = &class.init {
+        code.push_str(&format!("#line {} {}\n", init.span.line, source_literal));
+        code.push_str(&format!("    {}({});\n", struct_name, cpp_parameters(&init.node)));
+    }
+    for method in &class.methods {
+        code.push_str(&format!("#line {} {}\n", method.span.line, source_literal));
+        let method = &method.node;
+        let template = template_header(&method.type_params).replace('\n', " ");
+        let overrides = inherits_method(class, &method.name, module);
+        let virtual_ = if extended && !overrides && method.type_params.is_empty() { "virtual " } else { "" };
+        let override_ = if overrides { " override" } else { "" };
+        code.push_str(&format!(
+            "    {}{}{} {}({}){};\n",
+            template, virtual_, cpp_type(&method.returns), method.name, cpp_parameters(method), override_
+        ));
+    }
+    code.push_str("};\n\n");
+    code.push_str(&generate_operators(class));
+    code
+}
+
+// Free C++ operators forwarding to the operator methods of a class, so `a + b`
+// works on the `std::shared_ptr` a class value is, inside templates too. A
+// method returning the class itself also gives the compound assignment. There
+// is no `operator[]`, as semantic analysis turns `a[i]` into an `op_index` call.
+fn generate_operators(class: &Class) -> String {
+    let template = template_header(&class.type_params);
+    let params: Vec<TypeExpr> = class.type_params.iter().map(TypeExpr::named).collect();
+    let this_type = TypeExpr::Named { name: class.name.clone(), args: params };
+    let this_cpp = cpp_type(&this_type);
+    let mut code = String::new();
+    for method in &class.methods {
+        let method = &method.node;
+        let returns = cpp_type(&method.returns);
+        match (method.name.as_str(), method.params.as_slice()) {
+            ("op_neg", []) => code.push_str(&format!(
+                "{}inline {} operator-(const {}& a) {{ return a->op_neg(); }}\n",
+                template, returns, this_cpp
+            )),
+            ("op_eq", [other]) => {
+                let other = cpp_type(&other.ty);
+                code.push_str(&format!(
+                    "{}inline {} operator==(const {}& a, const {}& b) {{ return a->op_eq(b); }}\n",
+                    template, returns, this_cpp, other
+                ));
+                code.push_str(&format!(
+                    "{}inline {} operator!=(const {}& a, const {}& b) {{ return !a->op_eq(b); }}\n",
+                    template, returns, this_cpp, other
+                ));
+            },
+            (name, [other]) if name != "op_index" => {
+                let Some((_, symbol, _)) = OPERATOR_METHODS.iter().find(|(method, ..)| *method == name) else { continue };
+                let other = cpp_type(&other.ty);
+                code.push_str(&format!(
+                    "{}inline {} operator{}(const {}& a, const {}& b) {{ return a->{}(b); }}\n",
+                    template, returns, symbol, this_cpp, other, name
+                ));
+                if method.returns == this_type {
+                    code.push_str(&format!(
+                        "{}inline {}& operator{}=({}& a, const {}& b) {{ return a = a->{}(b); }}\n",
+                        template, this_cpp, symbol, this_cpp, other, name
+                    ));
+                }
+            },
+            _ => {},
+        }
+    }
+    if !code.is_empty() {
+        code.push('\n');
+    }
+    code
+}
+
+// Whether a base class of `class`, which may be in another module, declares a method called `name`
+fn inherits_method(class: &Class, name: &str, module: &ModuleCode) -> bool {
+    let mut seen = HashSet::new();
+    let mut base_module = module.name.to_string();
+    let mut base = class.base.as_ref();
+    while let Some(TypeExpr::Named { name: base_name, .. }) = base {
+        let qualified = qualified_class_name(&base_module, base_name);
+        let Some(base_class) = module.classes.get(&qualified).filter(|_| seen.insert(qualified.clone())) else { return false };
+        if base_class.methods.iter().any(|method| method.node.name == name) {
+            return true;
+        }
+        base_module = qualified.rsplit_once('.').map_or("", |(base_module, _)| base_module).to_string();
+        base = base_class.base.as_ref();
+    }
+    false
+}
+
+// Initial value of a variable. String variables are `std::string` rather than
+// `const char*` so they can be reassigned and appended to.
+fn generate_cpp_for_initializer(expr: &Expression) -> String {
+    match expr {
+        Expression::String(_) => format!("std::string({})", generate_cpp_for_expression(expr)),
+        _ => generate_cpp_for_expression(expr),
+    }
+}
+
+// Operand of a unary or binary operator; nested operations are parenthesized
+// so the tree's grouping survives C++ precedence
+fn generate_cpp_for_operand(expr: &Expression) -> String {
+    let cpp = generate_cpp_for_expression(expr);
+    match expr {
+        Expression::BinaryOp { .. } => format!("({})", cpp),
+        _ => cpp,
+    }
+}
+
+// String literal contents with the escapes of the source kept; a `"` inside a
+// single-quoted NymyaLang string needs escaping in C++
+fn escape_quotes(content: &str) -> String {
+    let mut escaped = String::new();
+    let mut chars = content.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                escaped.push(c);
+                escaped.extend(chars.next());
+            },
+            '"' => escaped.push_str("\\\""),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Quote a path for use in a C++ `#line` directive
+fn cpp_string_literal(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A project module (or single-file program) ready for code generation
+pub struct ModuleCode<'a> {
+    pub name: &'a str,
+    pub source_file: &'a str,
+    pub statements: &'a [Spanned<Statement>],
+    /// Project modules this module imports
+    pub imports: &'a [String],
+    /// Runtime and project modules this module uses
+    pub used_modules: &'a BTreeSet<String>,
+    /// Every class of the project, including this module's, by qualified name
+    pub classes: &'a BTreeMap<String, &'a Class>,
+}
+
+/// Add the classes of a module to `classes` under their qualified names, like
+/// `ui.base.Widget`, so classes extending a class of another module can be
+/// generated, and the methods of classes other modules extend are virtual
+pub fn collect_classes<'a>(module: &str, statements: &'a [Spanned<Statement>], classes: &mut BTreeMap<String, &'a Class>) {
+    for statement in statements {
+        if let Statement::Class(class) = &statement.node {
+            classes.insert(format!("{}.{}", module, class.name), class);
+        }
+    }
+}
+
+// Qualified name of a class named `name` in `module`: names of classes of
+// other modules are qualified already
+fn qualified_class_name(module: &str, name: &str) -> String {
+    if name.contains('.') { name.to_string() } else { format!("{}.{}", module, name) }
+}
+
+// C++ type of a module-level variable: the declared type, or else the
+// initializer decides, as with `auto`
+fn module_variable_type(ty: &Option<TypeExpr>, expression: &Expression) -> String {
+    if let Some(ty) = ty {
+        return cpp_type(ty);
+    }
+    match expression {
+        Expression::ArrayLiteral { element_type: Some(element_type), .. } => format!("std::vector<{}>", cpp_type(element_type)),
+        Expression::ArrayLiteral { elements, .. } => format!("std::vector<{}>", list_element_type(elements)),
+        Expression::String(_) => "std::string".to_string(),
+        _ => format!("std::decay_t<decltype({})>", generate_cpp_for_expression(expression)),
+    }
+}
+
+// Line, C++ type and name of each variable a module declares at the top level;
+// the variables of `var (a, b) = value` have the types of their tuple elements
+fn module_variables(statements: &[Spanned<Statement>]) -> Vec<(usize, String, &str)> {
+    let mut variables = Vec::new();
+    for statement in statements {
+        let line = statement.span.line;
+        match &statement.node {
+            Statement::VariableDeclaration { name, ty, value } => variables.push((line, module_variable_type(ty, value), name.as_str())),
+            Statement::Destructure { names, value } => {
+                let tuple = module_variable_type(&None, value);
+                for (position, name) in names.iter().enumerate().filter(|(_, name)| *name != "_") {
+                    variables.push((line, format!("std::tuple_element_t<{}, {}>", position, tuple), name.as_str()));
+                }
+            },
+            _ => {},
+        }
+    }
+    variables
+}
+
+// Declarations of a module's variables and initializer, as seen by the modules importing it
+fn generate_module_interface(module: &ModuleCode) -> String {
+    let mut code = format!("// Module {} ({})\nnamespace {} {{\n", module.name, module.source_file, module.name.replace('.', "::"));
+    let mut globals = String::new();
+    for (line, ty, name) in module_variables(module.statements) {
+        globals.push_str(&format!("#line {} {}\n", line, cpp_string_literal(module.source_file)));
+        globals.push_str(&format!("extern {} {};\n", ty, name));
+    }
+    code.push_str(&generate_declarations(module, &globals).0);
+    code.push_str("void nymya_init();\n}\n");
+    code
+}
+
+// Definitions of a module's variables, of its functions and methods that are
+// not generic, and of `nymya_init()`, which runs the module's top-level
+// statements once at program start
+fn generate_module_definitions(module: &ModuleCode) -> String {
+    let mut code = format!("namespace {} {{\n", module.name.replace('.', "::"));
+    for (line, ty, name) in module_variables(module.statements) {
+        code.push_str(&format!("#line {} {}\n", line, cpp_string_literal(module.source_file)));
+        code.push_str(&format!("{} {};\n", ty, name));
+    }
+    let (_, bodies) = generate_declarations(module, "");
+    if !bodies.is_empty() {
+        code.push_str(&format!("\n{}", bodies.trim_end()));
+        code.push('\n');
+    }
+    code.push_str("\nvoid nymya_init() {\n");
+    code.push_str(&generate_cpp_from_statements(module.statements, module.source_file, true));
+    code.push_str("}\n}\n");
+    code
+}
+
+// Standard headers for what the generated code refers to, then the runtime
+// headers (libnymya) for the modules the program actually uses
+fn generate_includes(code: &str, used_modules: &BTreeSet<String>) -> String {
+    let mut includes = String::from("#include <cstdio>\n#include <exception>\n");
+    if code.contains("std::string") || code.contains("std::to_string") || code.contains("nymya_bool_to_string") {
+        includes.push_str("#include <string>\n");
+    }
+    if code.contains("std::decay_t") || code.contains("std::remove_pointer_t") {
+        includes.push_str("#include <type_traits>\n");
+    }
+    if code.contains("std::vector") {
+        includes.push_str("#include <vector>\n");
+    }
+    if code.contains("std::shared_ptr") || code.contains("std::make_shared") {
+        includes.push_str("#include <memory>\n");
+    }
+    if code.contains("std::optional") || code.contains("std::nullopt") {
+        includes.push_str("#include <optional>\n");
+    }
+    if code.contains("std::function") {
+        includes.push_str("#include <functional>\n");
+    }
+    if code.contains("std::tuple") || code.contains("std::make_tuple") || code.contains("std::tie") {
+        includes.push_str("#include <tuple>\n");
+    }
+    if code.contains("std::variant") || code.contains("std::visit") || code.contains("std::get_if") || code.contains("std::holds_alternative") {
+        includes.push_str("#include <variant>\n");
+    }
+    // Overload set of lambdas for the `std::visit` of a `match`; module headers may each need it
+    if code.contains("nymya_overloaded") {
+        includes.push_str(concat!(
+            "#ifndef NYMYA_OVERLOADED\n#define NYMYA_OVERLOADED\n",
+            "template <typename... F> struct nymya_overloaded : F... { using F::operator()...; };\n",
+            "template <typename... F> nymya_overloaded(F...) -> nymya_overloaded<F...>;\n",
+            "#endif\n",
+        ));
+    }
+    // `Bool.to_string()` spells out `true` and `false` instead of `1` and `0`
+    if code.contains("nymya_bool_to_string") {
+        includes.push_str(concat!(
+            "#ifndef NYMYA_BOOL_TO_STRING\n#define NYMYA_BOOL_TO_STRING\n",
+            "inline std::string nymya_bool_to_string(bool value) { return value ? \"true\" : \"false\"; }\n",
+            "#endif\n",
+        ));
+    }
+
+    let runtime_headers = runtime::headers_for_modules(used_modules);
+    if !runtime_headers.is_empty() {
+        includes.push('\n');
+    }
+    for header in runtime_headers {
+        includes.push_str(&format!("#include \"{}\"\n", header));
+    }
+    includes
+}
+
+// Enhanced target code generator - generates C++ code with actual program execution.
+// The header comment names only the file, so the output does not depend on
+// where the source was compiled from.
+pub fn generate_target(source_file: &str, statements: &[Spanned<Statement>], used_modules: &BTreeSet<String>) -> String {
+    let mut classes = BTreeMap::new();
+    collect_classes("main", statements, &mut classes);
+    let entry = ModuleCode { name: "main", source_file, statements, imports: &[], used_modules, classes: &classes };
+    generate_program(&file_name(source_file), &[], &entry, used_modules)
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path).file_name().map_or_else(|| path.to_string(), |name| name.to_string_lossy().into_owned())
+}
+
+// A complete C++ program: `main()` initializing the project modules (whose
+// headers are included, in initialization order) and running the entry file
+pub fn generate_program(description: &str, modules: &[ModuleCode], entry: &ModuleCode, used_modules: &BTreeSet<String>) -> String {
+    let mut module_headers = String::new();
+    let mut module_init = String::new();
+    if !modules.is_empty() {
+        module_init.push_str("        // Module initialization, imported modules first\n");
+    }
+    for module in modules {
+        module_headers.push_str(&format!("#include \"{}\"\n", module_header_name(module.name)));
+        module_init.push_str(&format!("        {}::nymya_init();\n", module.name.replace('.', "::")));
+    }
+
+    if !modules.is_empty() {
+        module_headers.push('\n');
+        module_init.push('\n');
+    }
+
+    // Generate C++ code from statements
+    let mut executable_code = generate_cpp_from_statements(entry.statements, entry.source_file, false);
+    if calls_implicit_main(entry.statements) {
+        executable_code.push_str("    nymya_main();\n");
+    }
+    let (declarations, bodies) = generate_declarations(entry, "");
+    let declarations = declarations + &bodies;
+    let includes = generate_includes(&format!("{}{}", declarations, executable_code), used_modules);
+
+    // Create a C++ program with actual executable code
+    format!(r#"/*
+ * NymyaLang to C++ generated code (version {})
+ * Auto-generated from {}
+ */
+
+{}
+{}{}int main() {{
+    try {{
+        // Runtime initialization message (optional)
+        // crystal::manifest("NymyaLang runtime {} initialized");
+        // crystal::manifest("Compiled from: {}");
+
+{}        // Actual program execution
+{}
+
+        // Program completion message
+        // crystal::manifest("Program execution completed");
+    }} catch (const std::exception& e) {{
+        std::fprintf(stderr, "Runtime error: %s\n", e.what());
+        return 1;
+    }}
+    return 0;
+}}
+"#,
+        env!("CARGO_PKG_VERSION"),  // Use the crate version
+        description,
+        includes,
+        module_headers,
+        declarations,
+        env!("CARGO_PKG_VERSION"),  // Use the crate version
+        description,
+        module_init,
+        executable_code
+    )
+}
+
+// File name of the header generated for a project module
+pub fn module_header_name(module: &str) -> String {
+    format!("{}.hpp", module)
+}
+
+// Header of a project module: what it needs to compile, the headers of the
+// project modules it imports and the declarations of its variables, functions
+// and classes. The bodies of functions and methods are in the module's source,
+// unless they are generic, so changing them leaves the modules importing it alone.
+pub fn generate_module_header(module: &ModuleCode) -> String {
+    let interface = generate_module_interface(module);
+    let includes = generate_includes(&interface, module.used_modules);
+    let mut imports = String::new();
+    for imported in module.imports {
+        imports.push_str(&format!("#include \"{}\"\n", module_header_name(imported)));
+    }
+    if !imports.is_empty() {
+        imports.push('\n');
+    }
+    format!(
+        "/*\n * NymyaLang module {} (version {})\n */\n#pragma once\n\n{}\n{}{}",
+        module.name, env!("CARGO_PKG_VERSION"), includes, imports, interface
+    )
+}
+
+// Translation unit of a project module, including what its definitions need
+// on top of the module's header
+pub fn generate_module_source(module: &ModuleCode) -> String {
+    let definitions = generate_module_definitions(module);
+    let includes = generate_includes(&definitions, &BTreeSet::new());
+    format!(
+        "/*\n * NymyaLang module {} (version {})\n * Auto-generated from {}\n */\n\n{}\n#include \"{}\"\n\n{}",
+        module.name, env!("CARGO_PKG_VERSION"), module.source_file, includes, module_header_name(module.name), definitions
+    )
+}
+
+// Header of a library project: declarations of all its modules and of
+// `nymya_init_<name>()`, which initializes them in dependency order
+pub fn generate_library_header(name: &str, modules: &[ModuleCode], used_modules: &BTreeSet<String>) -> String {
+    let interfaces: String = modules.iter().map(generate_module_interface).collect();
+    let includes = generate_includes(&interfaces, used_modules);
+    format!(
+        "/*\n * NymyaLang library {} (version {})\n */\n#pragma once\n\n{}\n{}\nvoid nymya_init_{}();\n",
+        name, env!("CARGO_PKG_VERSION"), includes, interfaces, name
+    )
+}
+
+// Translation unit defining `nymya_init_<name>()` of a library project
+pub fn generate_library_init(name: &str, modules: &[ModuleCode]) -> String {
+    let mut code = format!("/*\n * NymyaLang library {} (version {})\n */\n\n", name, env!("CARGO_PKG_VERSION"));
+    for module in modules {
+        code.push_str(&format!("#include \"{}\"\n", module_header_name(module.name)));
+    }
+    code.push_str(&format!("\nvoid nymya_init_{}() {{\n", name));
+    for module in modules {
+        code.push_str(&format!("    {}::nymya_init();\n", module.name.replace('.', "::")));
+    }
+    code.push_str("}\n");
+    code
+}
+
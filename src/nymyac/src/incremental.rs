@@ -0,0 +1,64 @@
+// Incremental builds: outputs are only rebuilt when their inputs change.
+//
+// Each build output (object file, executable, archive) gets a `<output>.hash`
+// file next to it holding the hash of everything that went into it: for an
+// object file the generated C++ source, the project headers it includes and the
+// compiler command line. An output is rebuilt when it is missing or when the
+// stored hash differs from the current one.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Starting value for `fnv1a`
+pub const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+// 64-bit FNV-1a; stable across Rust releases, unlike DefaultHasher
+pub fn fnv1a(bytes: &[u8], mut hash: u64) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Hash a sequence of inputs; each input is terminated so that moving text
+/// from one input to the next changes the result
+pub fn hash_inputs<'a>(inputs: impl IntoIterator<Item = &'a str>) -> u64 {
+    inputs.into_iter().fold(FNV_OFFSET, |hash, input| fnv1a(&[0], fnv1a(input.as_bytes(), hash)))
+}
+
+fn hash_file(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_owned();
+    name.push(".hash");
+    PathBuf::from(name)
+}
+
+/// Whether `output` exists and was built from inputs with this hash
+pub fn is_up_to_date(output: &Path, hash: u64) -> bool {
+    output.exists()
+        && fs::read_to_string(hash_file(output)).is_ok_and(|stored| stored.trim() == format!("{:016x}", hash))
+}
+
+/// Remember the input hash of a freshly built `output`
+pub fn record(output: &Path, hash: u64) -> std::io::Result<()> {
+    fs::write(hash_file(output), format!("{:016x}\n", hash))
+}
+
+/// Forget how `output` was built, so the next build redoes it
+pub fn invalidate(output: &Path) {
+    let _ = fs::remove_file(hash_file(output));
+}
+
+// Write a file only when its contents differ, so concurrent compilers and
+// make-style timestamps are not disturbed needlessly
+pub fn write_if_changed(path: &Path, contents: &str) -> std::io::Result<()> {
+    if fs::read_to_string(path).is_ok_and(|existing| existing == contents) {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp = path.with_extension(format!("tmp{}", std::process::id()));
+    fs::write(&temp, contents)?;
+    fs::rename(&temp, path)
+}
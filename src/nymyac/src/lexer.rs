@@ -0,0 +1,173 @@
+// Tokenizer for NymyaLang: splits source text into tokens with the position
+// of their first character.
+
+use crate::ast::Span;
+
+/// A token and the position of its first character
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub text: String,
+    pub span: Span,
+}
+
+const TWO_CHAR_OPERATORS: &[&str] = &["==", "!=", "<=", ">=", "&&", "||", "->", "+=", "-=", "*=", "/=", "%=", "?.", "??", "=>"];
+
+// Character iterator that keeps track of the current line and column
+struct Cursor<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(source: &'a str) -> Self {
+        Cursor { chars: source.chars().peekable(), line: 1, column: 1 }
+    }
+
+    fn position(&self) -> Span {
+        Span { line: self.line, column: self.column }
+    }
+
+    fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+}
+
+// Basic tokenizer for NymyaLang - returns owned strings with their source positions
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut current_token = String::new();
+    let mut current_start = Span::default();
+    let mut in_string = false;
+    let mut quote_char = '"';
+
+    let mut chars = Cursor::new(source);
+
+    // Push the token being accumulated, if any
+    fn flush(tokens: &mut Vec<Token>, current_token: &mut String, start: Span) {
+        if !current_token.trim().is_empty() {
+            tokens.push(Token { text: current_token.clone(), span: start });
+        }
+        current_token.clear();
+    }
+
+    loop {
+        let position = chars.position();
+        let Some(c) = chars.next() else { break };
+        if current_token.is_empty() {
+            current_start = position;
+        }
+
+        match c {
+            '"' | '\'' => {
+                if !in_string {
+                    flush(&mut tokens, &mut current_token, current_start);
+                    current_start = position;
+                    in_string = true;
+                    quote_char = c;
+                    current_token.push(c);
+                } else if quote_char == c {
+                    current_token.push(c);
+                    tokens.push(Token { text: current_token.clone(), span: current_start });
+                    current_token.clear();
+                    in_string = false;
+                } else {
+                    current_token.push(c);
+                }
+            }
+            _ if in_string => {
+                current_token.push(c);
+            }
+            ' ' | '\t' | '\n' | '\r' => {
+                flush(&mut tokens, &mut current_token, current_start);
+            }
+            '(' | ')' | ',' | ';' | '[' | ']' => {
+                flush(&mut tokens, &mut current_token, current_start);
+                tokens.push(Token { text: c.to_string(), span: position });
+            }
+            '.' => {
+                // Check if we're handling a floating-point number: if current_token is numeric, append the dot and next digits.
+                // A name after the dot is a method call on the number, like `255.to_hex()`.
+                let method_call = chars.peek().is_some_and(|&next_ch| next_ch.is_alphabetic() || next_ch == '_');
+                if !method_call && !current_token.is_empty() && current_token.chars().all(|ch| ch.is_ascii_digit()) {
+                    current_token.push(c); // Add the dot
+
+                    // Gather any following digits to form the decimal part
+                    while chars.peek().is_some_and(|&next_ch| next_ch.is_ascii_digit()) {
+                        current_token.push(chars.next().unwrap());
+                    }
+                } else {
+                    // If not part of a number, handle as a separate token (e.g., in module.function)
+                    flush(&mut tokens, &mut current_token, current_start);
+                    tokens.push(Token { text: c.to_string(), span: position });
+                }
+            }
+            '@' => {
+                // Handle @-tags like @shira, @sela, @nora, etc.
+                flush(&mut tokens, &mut current_token, current_start);
+
+                // Collect the entire @tag
+                current_token.push(c); // Add the '@'
+
+                // Collect alphanumeric characters, underscores and dashes (`@---` separators) after @
+                while let Some(&next_char) = chars.peek() {
+                    if next_char.is_alphanumeric() || next_char == '_' || next_char == '-' {
+                        current_token.push(chars.next().unwrap());
+                    } else {
+                        break;
+                    }
+                }
+
+                // Push the complete @tag as a single token
+                tokens.push(Token { text: current_token.clone(), span: position });
+                current_token.clear();
+            }
+            '#' => {
+                // Line comment (also covers `#!` lesson metadata)
+                flush(&mut tokens, &mut current_token, current_start);
+                while chars.peek().is_some_and(|&next_ch| next_ch != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                // Line comment
+                flush(&mut tokens, &mut current_token, current_start);
+                while chars.peek().is_some_and(|&next_ch| next_ch != '\n') {
+                    chars.next();
+                }
+            }
+            _ if c.is_alphanumeric() || c == '_' => {
+                current_token.push(c);
+            }
+            _ => {
+                // Operator or other separators; two-character operators are one token
+                flush(&mut tokens, &mut current_token, current_start);
+                let mut operator = c.to_string();
+                if let Some(&next_ch) = chars.peek() {
+                    let pair: String = [c, next_ch].iter().collect();
+                    if TWO_CHAR_OPERATORS.contains(&pair.as_str()) {
+                        chars.next();
+                        operator = pair;
+                    }
+                }
+                tokens.push(Token { text: operator, span: position });
+            }
+        }
+    }
+
+    flush(&mut tokens, &mut current_token, current_start);
+
+    tokens
+}
+
@@ -0,0 +1,94 @@
+// The NymyaLang compiler as a library.
+//
+// The pipeline is `lexer` -> `parser` -> `sema` -> `codegen`: source text is
+// tokenized, parsed into the `ast`, checked for name and type errors and
+// finally lowered to C++. `compile` runs all stages on a single source file;
+// `build` does the same for the modules of a project and runs the C++
+// toolchain on them. The `nymyac` binary adds the command line.
+//
+//     let output = nymyac::compile(source, &CompileOptions::new("hello.nym"))?;
+//     std::fs::write("hello.cpp", output.cpp)?;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+pub mod ast;
+pub mod build;
+pub mod codegen;
+pub mod diagnostics;
+pub mod incremental;
+pub mod lexer;
+pub mod parser;
+pub mod project;
+pub mod runtime;
+pub mod sema;
+pub mod toolchain;
+
+use ast::{Spanned, Statement};
+use diagnostics::{Diagnostic, Diagnostics};
+
+/// Settings for `compile`
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    /// Path of the source file, used in `#line` directives and the header comment
+    pub source_file: String,
+    /// Exports of the project modules the source may import
+    pub modules: BTreeMap<String, sema::ModuleExports>,
+}
+
+impl CompileOptions {
+    pub fn new(source_file: impl Into<String>) -> Self {
+        CompileOptions { source_file: source_file.into(), modules: BTreeMap::new() }
+    }
+}
+
+/// Result of a successful `compile`
+#[derive(Debug)]
+pub struct Output {
+    /// The generated C++ translation unit, including `main()`
+    pub cpp: String,
+    /// Runtime modules the program uses; link against libnymya when not empty
+    pub used_modules: BTreeSet<String>,
+    /// Warnings found on the way
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// Compile NymyaLang source to C++. Fails with all diagnostics (errors and
+/// warnings) when the front end finds any error.
+pub fn compile(source: &str, options: &CompileOptions) -> Result<Output, Diagnostics> {
+    let front_end = analyze(source, &options.modules);
+    if front_end.diagnostics.iter().any(Diagnostic::is_error) {
+        return Err(Diagnostics(front_end.diagnostics));
+    }
+    let cpp = codegen::generate_target(&options.source_file, &front_end.statements, &front_end.used_modules);
+    Ok(Output { cpp, used_modules: front_end.used_modules, warnings: front_end.diagnostics })
+}
+
+/// Output of the front end: the parsed program plus what analysis found out about it
+#[derive(Debug)]
+pub struct FrontEnd {
+    pub statements: Vec<Spanned<Statement>>,
+    pub diagnostics: Vec<Diagnostic>,
+    pub used_modules: BTreeSet<String>,
+    pub exports: sema::ModuleExports,
+}
+
+/// Run the front end: parse and then check names and types. `modules` are the
+/// exports of the project modules the source may import.
+pub fn analyze(source_code: &str, modules: &BTreeMap<String, sema::ModuleExports>) -> FrontEnd {
+    let (statements, diagnostics) = parser::parse(source_code);
+    check_parsed(statements, diagnostics, modules, false)
+}
+
+/// Check already parsed statements; `diagnostics` are those of the parser.
+/// `globals` is set for project modules, whose top-level variables are
+/// globals their functions can use.
+pub fn check_parsed(
+    mut statements: Vec<Spanned<Statement>>,
+    mut diagnostics: Vec<Diagnostic>,
+    modules: &BTreeMap<String, sema::ModuleExports>,
+    globals: bool,
+) -> FrontEnd {
+    let analysis = sema::check(&mut statements, modules, globals);
+    diagnostics.extend(analysis.diagnostics);
+    FrontEnd { statements, diagnostics, used_modules: analysis.used_modules, exports: analysis.exports }
+}
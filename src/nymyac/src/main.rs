@@ -1,1351 +1,535 @@
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
-use clap::Parser;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::time::{SystemTime, UNIX_EPOCH};
+use clap::{Parser, Subcommand, ValueEnum};
+
+use nymyac::build::{self, BuildEvent, BuildOptions, ProjectFrontEnd, Stage};
+use nymyac::diagnostics::{Diagnostic, Diagnostics};
+use nymyac::lexer::tokenize;
+use nymyac::parser::parse;
+use nymyac::project::{self, display_path};
+use nymyac::toolchain::{OutputKind, Toolchain, ToolchainArgs};
+use nymyac::{analyze, runtime, CompileOptions};
 
 /// NymyaLang Compiler - Compiles .nym files to executable code
 #[derive(Parser)]
-#[clap(author, version, about)]
-struct Args {
-    /// Input .nym file to compile
-    #[clap(value_parser)]
-    input: Option<String>,
-
-    /// Output executable name
-    #[clap(short, long, value_parser)]
-    output: Option<String>,
-}
-
-#[derive(Debug)]
-enum Statement {
-    Import(String),
-    VariableAssignment { var_name: String, expression: Expression },
-    FunctionCall { module: String, function: String, args: Vec<String> },
-    ExpressionStmt { expression: Expression },  // For expressions that are statements (like obj.method())
-    ArrayMethodCall { array_var: String, method: String, args: Vec<String> },
+#[clap(author, version, about, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Option<Commands>,
+
+    /// Shorthand for `nymyac build <input>`
+    #[clap(flatten)]
+    build: BuildArgs,
 }
 
-#[derive(Debug)]
-enum Expression {
-    FunctionCall { module: String, function: String, args: Vec<String> },
-    ArrayAccess { array: Box<Expression>, index: Box<Expression> },
-    ArrayMethodCall { array: Box<Expression>, method: String, args: Vec<String> },  // For methods like .append(), .length
-    MethodCall { object: Box<Expression>, method: String, args: Vec<String> },  // For general method calls like var.method()
-    ArrayLiteral(Vec<Expression>), // For array literals like []
-    Variable(String),
-    Number(f64),
-    StringLiteral(String),
-    BinaryOperation { left: Box<Expression>, operator: String, right: Box<Expression> },
+#[derive(Subcommand)]
+enum Commands {
+    /// Compile a .nym file next to its source, or a project into its target/ directory
+    Build(BuildArgs),
+    /// Compile into a temporary directory, run the program and clean up
+    Run(RunArgs),
+    /// Validate a .nym file or a project without invoking the C++ compiler
+    Check(CheckArgs),
+    /// Write out a single compilation stage
+    Emit(EmitArgs),
 }
 
-// Basic tokenizer for NymyaLang - returns owned strings to avoid borrowing issues
-fn tokenize(source: &str) -> Vec<String> {
-    let mut tokens = Vec::new();
-    let mut current_token = String::new();
-    let mut in_string = false;
-    let mut quote_char = '"';
-
-    let mut chars = source.chars().peekable();
-
-    while let Some(c) = chars.next() {
-        match c {
-            '"' | '\'' => {
-                if !in_string {
-                    if !current_token.trim().is_empty() {
-                        tokens.push(current_token.trim().to_string());
-                        current_token.clear();
-                    }
-                    in_string = true;
-                    quote_char = c;
-                    current_token.push(c);
-                } else if quote_char == c {
-                    current_token.push(c);
-                    tokens.push(current_token.clone());
-                    current_token.clear();
-                    in_string = false;
-                } else {
-                    current_token.push(c);
-                }
-            }
-            _ if in_string => {
-                current_token.push(c);
-            }
-            ' ' | '\t' | '\n' | '\r' => {
-                if !current_token.trim().is_empty() {
-                    tokens.push(current_token.clone());
-                    current_token.clear();
-                }
-            }
-            '(' | ')' | ',' | ';' => {
-                if !current_token.trim().is_empty() {
-                    tokens.push(current_token.clone());
-                    current_token.clear();
-                }
-                tokens.push(c.to_string());
-            }
-            '.' => {
-                // Check if we're handling a floating-point number: if current_token is numeric, append the dot and next digits
-                if !current_token.is_empty() && current_token.chars().all(|ch| ch.is_ascii_digit()) {
-                    // Look ahead to see if the next characters form a decimal number
-                    current_token.push(c); // Add the dot
-
-                    // Gather any following digits to form the decimal part
-                    while chars.peek().map_or(false, |&next_ch| next_ch.is_ascii_digit()) {
-                        current_token.push(chars.next().unwrap());
-                    }
-                } else {
-                    // If not part of a number, handle as a separate token (e.g., in module.function)
-                    if !current_token.trim().is_empty() {
-                        tokens.push(current_token.clone());
-                        current_token.clear();
-                    }
-                    tokens.push(c.to_string());
-                }
-            }
-            '[' | ']' => {
-                if !current_token.trim().is_empty() {
-                    tokens.push(current_token.clone());
-                    current_token.clear();
-                }
-                tokens.push(c.to_string());
-            }
-            '@' => {
-                // Handle @-tags like @shira, @sela, @nora, etc.
-                if !current_token.trim().is_empty() {
-                    tokens.push(current_token.clone());
-                    current_token.clear();
-                }
-
-                // Collect the entire @tag
-                current_token.push(c); // Add the '@'
+#[derive(clap::Args)]
+struct BuildArgs {
+    /// Input .nym file, project directory or nymya.toml (default: the project containing the current directory)
+    #[clap(value_parser)]
+    input: Option<String>,
 
-                // Collect alphanumeric characters and underscores after @
-                while let Some(&next_char) = chars.peek() {
-                    if next_char.is_alphanumeric() || next_char == '_' {
-                        current_token.push(chars.next().unwrap());
-                    } else {
-                        break;
-                    }
-                }
+    /// Output file name
+    #[clap(short, long, value_parser)]
+    output: Option<String>,
 
-                // Push the complete @tag as a single token
-                tokens.push(current_token.clone());
-                current_token.clear();
-            }
-            _ if c.is_alphanumeric() || c == '_' => {
-                current_token.push(c);
-            }
-            _ => {
-                // Operator or other separators
-                if !current_token.trim().is_empty() {
-                    tokens.push(current_token.clone());
-                    current_token.clear();
-                }
-                tokens.push(c.to_string());
-            }
-        }
-    }
+    /// Stage to stop at and what to write out
+    #[clap(long, value_enum, default_value = "exe")]
+    emit: Emit,
 
-    if !current_token.trim().is_empty() {
-        tokens.push(current_token);
-    }
+    /// Keep the generated C++ file after a successful build
+    #[clap(long)]
+    keep_cpp: bool,
 
-    tokens
+    #[clap(flatten)]
+    toolchain: ToolchainArgs,
 }
 
-// Parse NymyaLang source code into AST
-fn parse(source: &str) -> Vec<Statement> {
-    let tokens: Vec<String> = tokenize(source);
-    let mut statements = Vec::new();
-    let mut i = 0;
+#[derive(clap::Args)]
+struct RunArgs {
+    /// Input .nym file to run
+    #[clap(value_parser)]
+    input: String,
 
-    while i < tokens.len() {
-        // Skip any @-tags that appear (they are treated as metadata/comments for now)
-        while i < tokens.len() && tokens[i].starts_with('@') {
-            i += 1;  // Skip the tag token
-        }
+    /// Arguments passed through to the program (after `--`)
+    #[clap(last = true, value_parser)]
+    program_args: Vec<String>,
 
-        if i >= tokens.len() {
-            break;
-        }
+    #[clap(flatten)]
+    toolchain: ToolchainArgs,
+}
 
-        if tokens[i] == "import" {
-            i += 1;
-            if i < tokens.len() {
-                statements.push(Statement::Import(tokens[i].clone()));
-            }
-        } else if i + 1 < tokens.len() && tokens[i] == "var" {
-            // Handle variable assignment: var result = module.function(args) or var list = []
-            i += 1; // Skip "var"
-            if i < tokens.len() {
-                let var_name = tokens[i].clone();
-                i += 1; // Move to "="
-                if i < tokens.len() && tokens[i] == "=" {
-                    i += 1; // Skip "="
-                    let expr = parse_expression(&tokens, &mut i);
-                    statements.push(Statement::VariableAssignment {
-                        var_name,
-                        expression: expr
-                    });
-                }
-            }
-        } else if i + 2 < tokens.len() && tokens[i + 1] == "." {
-            // Look ahead to see if we have a nested namespace pattern like: graphics.stl_basic.function()
-            let mut is_nested_namespace = false;
-            let mut module_name = "".to_string();
-            let mut function_name = "".to_string();
-            let mut advance_amount = 0;
+#[derive(clap::Args)]
+struct CheckArgs {
+    /// Input .nym file, project directory or nymya.toml (default: the project containing the current directory)
+    #[clap(value_parser)]
+    input: Option<String>,
+}
 
-            // Check for 3-token pattern: module . submodule . function
-            if i + 4 < tokens.len() && &tokens[i + 1] == "." && &tokens[i + 3] == "." {
-                // Potential nested namespace: check if first part is a known module
-                let potential_module = format!("{}.{}", tokens[i], tokens[i + 2]);
-                let known_modules = ["crystal", "math", "quantum", "symbolic", "networking", "system", "image", "graphics", "physics", "datetime", "lowlevel", "ml", "gui"];
+#[derive(clap::Args)]
+struct EmitArgs {
+    /// Stage to write out
+    #[clap(value_enum)]
+    stage: Emit,
 
-                if known_modules.contains(&tokens[i].as_str()) {
-                    // This looks like a nested namespace call: module.submodule.function()
-                    module_name = potential_module;
-                    function_name = tokens[i + 4].clone();
-                    advance_amount = 5; // module . submodule . function
-                    is_nested_namespace = true;
-                }
-            }
+    /// Input .nym file
+    #[clap(value_parser)]
+    input: String,
 
-            if is_nested_namespace {
-                // Handle nested namespace call: module.submodule.function(args)
-                let mut args = Vec::new();
-                if i + advance_amount < tokens.len() && tokens[i + advance_amount] == "(" {
-                    let mut arg_i = i + advance_amount + 1; // Skip '('
-                    while arg_i < tokens.len() && tokens[arg_i] != ")" {
-                        if tokens[arg_i] != "," {
-                            args.push(tokens[arg_i].clone());
-                        }
-                        arg_i += 1;
-                    }
+    /// Output file (stdout for tokens and ast when omitted)
+    #[clap(short, long, value_parser)]
+    output: Option<String>,
 
-                    // Skip closing parenthesis if present and use the updated position
-                    if arg_i < tokens.len() && tokens[arg_i] == ")" {
-                        arg_i += 1;  // Increment to skip the ')'
-                    }
-                    // Use arg_i to update the main loop variable i
-                    i = arg_i - 1; // Compensate for the i += 1 at the end of the main loop
-                }
+    #[clap(flatten)]
+    toolchain: ToolchainArgs,
+}
 
-                // Check if this is a known array method call
-                if module_name.chars().next().map_or(false, |c| c.is_ascii_lowercase()) &&
-                   (function_name == "append" || function_name == "length" || function_name == "size" ||
-                    function_name == "get" || function_name == "at" || function_name == "set") {
-                    statements.push(Statement::ArrayMethodCall {
-                        array_var: module_name,
-                        method: function_name,
-                        args
-                    });
-                } else {
-                    // This is a nested namespace function call
-                    statements.push(Statement::FunctionCall {
-                        module: module_name,
-                        function: function_name,
-                        args
-                    });
-                }
+/// Compiler output kinds selectable with `--emit`
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// Token stream produced by the tokenizer, one `line:column<TAB>token` per line
+    Tokens,
+    /// Pretty-printed parsed statements
+    Ast,
+    /// Generated C++ source
+    Cpp,
+    /// Object file compiled from the generated C++
+    Obj,
+    /// Linked executable (default)
+    Exe,
+}
 
-                i += advance_amount;
-                if i < tokens.len() && tokens[i] == "(" {
-                    // We already handled the arguments above, so advance past them
-                    while i < tokens.len() && tokens[i] != ")" {
-                        if tokens[i] == "(" {
-                            // Handle nested function calls by counting parenthesis
-                            let mut paren_count = 1;
-                            i += 1;
-                            while i < tokens.len() && paren_count > 0 {
-                                if tokens[i] == "(" {
-                                    paren_count += 1;
-                                } else if tokens[i] == ")" {
-                                    paren_count -= 1;
-                                }
-                                if paren_count > 0 {
-                                    i += 1;
-                                }
-                            }
-                        } else {
-                            i += 1;
-                        }
-                    }
-                    if i < tokens.len() && tokens[i] == ")" {
-                        i += 1;
-                    }
-                }
+// Default output path for an emit mode; `None` means the result goes to stdout
+fn default_output(input: &str, emit: Emit) -> Option<String> {
+    let base_name = input.trim_end_matches(".nym");
+    match emit {
+        Emit::Tokens | Emit::Ast => None,
+        Emit::Cpp => Some(format!("{}.cpp", base_name)),
+        Emit::Obj => Some(format!("{}.o", base_name)),
+        Emit::Exe => {
+            // Default output: replace .nym with executable extension
+            if cfg!(windows) {
+                Some(format!("{}.exe", base_name))
             } else {
-                // Handle regular module.function() or variable.method() call
-                let object = tokens[i].clone();
-                let function = tokens[i + 2].clone();
-                i += 3; // Skip module.function
-
-                // Parse arguments
-                let mut args = Vec::new();
-                if i < tokens.len() && tokens[i] == "(" {
-                    i += 1; // Skip '('
-
-                    // Collect arguments until closing parenthesis
-                    while i < tokens.len() && tokens[i] != ")" {
-                        if tokens[i] != "," {
-                            args.push(tokens[i].clone());
-                        }
-                        i += 1;
-                    }
-
-                    if i < tokens.len() && tokens[i] == ")" {
-                        i += 1; // Skip ')'
-                    }
-                }
-
-                // Check if this is an array method call (when object is a variable name starting with lowercase)
-                // Known array methods: append, length, size, get, at, set
-                if object.chars().next().map_or(false, |c| c.is_ascii_lowercase()) &&
-                   (function == "append" || function == "length" || function == "size" ||
-                    function == "get" || function == "at" || function == "set") {
-                    // This is an array method call on a variable
-                    statements.push(Statement::ArrayMethodCall {
-                        array_var: object,
-                        method: function,
-                        args
-                    });
-                } else if object.chars().next().map_or(false, |c| c.is_ascii_lowercase()) {
-                    // Check if this is a known module name (starts with lowercase but is a module, not a variable)
-                    // Known modules: crystal, math, quantum, symbolic, networking, physics, etc.
-                    let known_modules = ["crystal", "math", "quantum", "symbolic", "networking", "physics", "datetime", "lowlevel", "ml", "gui"];
-                    let is_known_module = known_modules.contains(&object.as_str());
-
-                    if is_known_module {
-                        // This is a module.function() call, not a variable method call
-                        statements.push(Statement::FunctionCall {
-                            module: object,
-                            function,
-                            args
-                        });
-                    } else if function == "append" || function == "length" || function == "size" ||
-                              function == "get" || function == "at" || function == "set" {
-                        // This is an array method call on a variable (like list.length or list.append(value))
-                        statements.push(Statement::ArrayMethodCall {
-                            array_var: object,
-                            method: function,
-                            args
-                        });
-                    } else {
-                        // This is a general method call on a variable (not a module.function)
-                        // Create an expression statement for the method call
-                        let method_call_expr = Expression::MethodCall {
-                            object: Box::new(Expression::Variable(object)),
-                            method: function,
-                            args
-                        };
-                        statements.push(Statement::ExpressionStmt {
-                            expression: method_call_expr
-                        });
-                    }
-                } else {
-                    // This is a regular module.function() call (object starts with uppercase or non-lowercase)
-                    statements.push(Statement::FunctionCall {
-                        module: object,
-                        function,
-                        args
-                    });
-                }
+                Some(base_name.to_string())
             }
         }
-
-        i += 1;
     }
-
-    statements
 }
 
-// Helper function to parse expressions with precedence and method chaining support
-fn parse_expression(tokens: &[String], i: &mut usize) -> Expression {
-    if *i >= tokens.len() {
-        return Expression::StringLiteral("".to_string());
-    }
-
-    // Parse left-hand side of expression (primary expression)
-    let mut expr = parse_primary_expr(tokens, i);
-
-    // Handle postfix operators like method calls (obj.method() or array[index].method())
-    loop {
-        if *i < tokens.len() && &tokens[*i] == "." {
-            *i += 1; // Skip '.'
-
-            if *i >= tokens.len() {
-                break; // No more tokens after '.'
-            }
-
-            let method_name = tokens[*i].clone();
-            *i += 1;
-
-            if *i < tokens.len() && &tokens[*i] == "(" {
-                // This is a method call: obj.method(args) or array[index].method(args)
-                *i += 1; // Skip '('
-
-                let mut args = Vec::new();
-                while *i < tokens.len() && &tokens[*i] != ")" {
-                    if &tokens[*i] != "," {
-                        args.push(tokens[*i].clone());
-                    }
-                    *i += 1;
-                }
-
-                if *i < tokens.len() && &tokens[*i] == ")" {
-                    *i += 1; // Skip ')'
-                }
-
-                // Check if this is an array method call
-                if method_name == "append" || method_name == "length" || method_name == "size" ||
-                   method_name == "get" || method_name == "at" || method_name == "set" {
-                    // Check if expression is a variable that looks like an array (starts with lowercase)
-                    if let Expression::Variable(var_name) = &expr {
-                        if var_name.chars().next().map_or(false, |c| c.is_ascii_lowercase()) {
-                            expr = Expression::ArrayMethodCall {
-                                array: Box::new(expr),
-                                method: method_name.clone(),  // Use clone() to avoid move issues
-                                args
-                            };
-                            continue;
-                        }
-                    }
-                }
-
-                // General method call
-                expr = Expression::MethodCall {
-                    object: Box::new(expr),
-                    method: method_name,
-                    args
-                };
-            } else {
-                // This is property access without arguments (e.g., obj.length, obj.size)
-                // Check if this is an array property method
-                if method_name == "append" || method_name == "length" || method_name == "size" ||
-                   method_name == "get" || method_name == "at" || method_name == "set" {
-                    // Check if expression is a variable that looks like an array (starts with lowercase)
-                    if let Expression::Variable(var_name) = &expr {
-                        if var_name.chars().next().map_or(false, |c| c.is_ascii_lowercase()) {
-                            expr = Expression::ArrayMethodCall {
-                                array: Box::new(expr),
-                                method: method_name.clone(),
-                                args: vec![]  // No arguments for property access
-                            };
-                            continue;
-                        }
-                    }
-                    // If not an array variable, fall through to create a general MethodCall
-                }
-
-                // General method call without arguments (or non-array property)
-                expr = Expression::MethodCall {
-                    object: Box::new(expr),
-                    method: method_name,
-                    args: vec![]  // No arguments
-                };
-            }
-        } else {
-            break; // No more method calls or property access
+// Write a textual dump either to the requested file or to stdout
+fn write_text_output(output: Option<&str>, text: &str) {
+    match output {
+        Some(path) => {
+            fs::write(path, text).expect("Should have been able to write the output file");
+            println!("Wrote: {}", path);
         }
+        None => print!("{}", text),
     }
-
-    // Check for binary operators (currently just +)
-    if *i < tokens.len() && &tokens[*i] == "+" {
-        let op = tokens[*i].clone();
-        *i += 1; // Skip operator
-        let right = parse_expression(tokens, i);
-        return Expression::BinaryOperation {
-            left: Box::new(expr),
-            operator: op,
-            right: Box::new(right)
-        };
-    }
-
-    expr
 }
 
-// Parse primary expressions (variables, literals, array access, function calls)
-fn parse_primary_expr(tokens: &[String], i: &mut usize) -> Expression {
-    if *i >= tokens.len() {
-        return Expression::StringLiteral("".to_string());
-    }
-
-    let token = &tokens[*i];
-
-    // String literals
-    if (token.starts_with('"') && token.ends_with('"')) || (token.starts_with('\'') && token.ends_with('\'')) {
-        *i += 1;
-        return Expression::StringLiteral(token[1..token.len()-1].to_string());
+// Validate the input path and read the NymyaLang source, exiting on error
+fn read_source(input: &str) -> String {
+    if !input.ends_with(".nym") {
+        eprintln!("Error: Input file must have .nym extension");
+        std::process::exit(1);
     }
 
-    // Number literals
-    if let Ok(num) = token.parse::<f64>() {
-        *i += 1;
-        return Expression::Number(num);
+    if !Path::new(input).exists() {
+        eprintln!("Error: Input file does not exist: {}", input);
+        std::process::exit(1);
     }
 
-    // Array literals: []
-    if token == "[" && *i + 1 < tokens.len() && &tokens[*i + 1] == "]" {
-        *i += 2; // Skip '[' and ']'
-        return Expression::ArrayLiteral(vec![]);
-    }
-
-    // Array access: array[index] - look ahead to see if we have [ after the variable
-    if *i + 2 < tokens.len() && &tokens[*i + 1] == "[" {
-        let array_name = tokens[*i].clone();
-        *i += 1; // Skip array name
-        *i += 1; // Skip '['
-
-        let index_expr = parse_expression(tokens, i); // Parse the index (could be complex)
+    fs::read_to_string(input).expect("Should have been able to read the file")
+}
 
-        if *i < tokens.len() && &tokens[*i] == "]" {
-            *i += 1; // Skip ']'
-            let array_var = Expression::Variable(array_name);
-            return Expression::ArrayAccess {
-                array: Box::new(array_var),
-                index: Box::new(index_expr)
-            };
-        } else {
-            // Malformed - no closing bracket, return variable for now
-            return Expression::Variable(array_name);
-        }
+// Compile a C++ file into an object file or executable with the configured toolchain.
+// On failure the error carries the compiler diagnostics.
+fn compile_cpp(cpp_file: &Path, output_filename: &Path, kind: OutputKind, toolchain: &Toolchain) -> Result<(), String> {
+    match toolchain.command(cpp_file, output_filename, kind).output() {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(format!("C++ compilation failed:\n{}", String::from_utf8_lossy(&output.stderr))),
+        Err(e) => Err(format!("Failed to run {}: {}", toolchain.cxx, e)),
     }
+}
 
-    // Check for nested namespace pattern first: module.submodule.function()
-    if *i + 4 < tokens.len() && &tokens[*i + 1] == "." && &tokens[*i + 3] == "." {
-        // This is the pattern: module . submodule . function ( args )
-        let potential_module = format!("{}.{}", tokens[*i], tokens[*i + 2]);
-        let function = tokens[*i + 4].clone();
-        let known_modules = ["crystal", "math", "quantum", "symbolic", "networking", "system", "image", "graphics", "physics", "datetime", "lowlevel", "ml", "gui"];
-
-        if known_modules.contains(&tokens[*i].as_str()) {
-            *i += 5; // Skip module.submodule.function
-
-            // Check if this is followed by arguments
-            if *i < tokens.len() && &tokens[*i] == "(" {
-                *i += 1; // Skip '('
-
-                let mut args = Vec::new();
-                while *i < tokens.len() && &tokens[*i] != ")" {
-                    if &tokens[*i] != "," {
-                        args.push(tokens[*i].clone());
-                    }
-                    *i += 1;
-                }
+// Write a single compilation stage for `input` to `output`
+fn emit_stage(input: &str, output: Option<String>, emit: Emit, keep_cpp: bool, toolchain: &Toolchain) {
+    let source_code = read_source(input);
 
-                if *i < tokens.len() && &tokens[*i] == ")" {
-                    *i += 1; // Skip ')'
-                }
+    // Determine output filename
+    let output_filename = output.or_else(|| default_output(input, emit));
 
-                // Check if this is an array method call (when potential_module is a variable name starting with lowercase)
-                if potential_module.chars().next().map_or(false, |c| c.is_ascii_lowercase()) &&
-                   (function == "append" || function == "length" || function == "size" ||
-                    function == "get" || function == "at" || function == "set") {
-                    let array_var = Expression::Variable(potential_module);
-                    return Expression::ArrayMethodCall {
-                        array: Box::new(array_var),
-                        method: function,
-                        args
-                    };
-                } else {
-                    return Expression::FunctionCall { module: potential_module, function, args };
-                }
-            } else {
-                // Just a module.submodule.function without arguments
-                return Expression::FunctionCall { module: potential_module, function, args: vec![] };
+    match emit {
+        Emit::Tokens => {
+            let mut dump = String::new();
+            for token in tokenize(&source_code) {
+                dump.push_str(&format!("{}:{}\t{}\n", token.span.line, token.span.column, token.text));
             }
-        }
-    }
-
-    // If next token is dot (.), this could be a module.function() pattern
-    if *i + 2 < tokens.len() && &tokens[*i + 1] == "." {
-        let module = tokens[*i].clone();
-        let function = tokens[*i + 2].clone();
-        *i += 3; // Skip module.function
-
-        // Check if this is followed by arguments
-        if *i < tokens.len() && &tokens[*i] == "(" {
-            *i += 1; // Skip '('
-
-            let mut args = Vec::new();
-            while *i < tokens.len() && &tokens[*i] != ")" {
-                if &tokens[*i] != "," {
-                    args.push(tokens[*i].clone());
+            write_text_output(output_filename.as_deref(), &dump);
+        },
+        Emit::Ast => {
+            let (statements, diagnostics) = parse(&source_code);
+            report_diagnostics(input, &diagnostics);
+            write_text_output(output_filename.as_deref(), &format!("{:#?}\n", statements));
+        },
+        Emit::Cpp => {
+            let output = compile_or_exit(input, &source_code, toolchain);
+            write_text_output(output_filename.as_deref(), &output.cpp);
+            if output_filename.is_some() {
+                if let Ok(include_dir) = runtime::install_sources() {
+                    println!("Runtime headers: -I{}", include_dir.display());
                 }
-                *i += 1;
             }
+        },
+        Emit::Obj | Emit::Exe => {
+            println!("Compiling {}...", input);
+            let cpp_code = compile_or_exit(input, &source_code, toolchain).cpp;
+            let output_filename = output_filename.expect("object and executable builds always have an output path");
 
-            if *i < tokens.len() && &tokens[*i] == ")" {
-                *i += 1; // Skip ')'
-            }
+            // Write the generated C++ code to a temporary file first
+            let temp_cpp_file = format!(
+                "{}.cpp",
+                output_filename.trim_end_matches(".exe").trim_end_matches(".o")
+            );
+            fs::write(&temp_cpp_file, &cpp_code)
+                .expect("Should have been able to write the C++ source file");
 
-            // Check if this is an array method call (module is actually a variable name starting with lowercase)
-            if module.chars().next().map_or(false, |c| c.is_ascii_lowercase()) &&
-               (function == "append" || function == "length" || function == "size" ||
-                function == "get" || function == "at" || function == "set") {
-                let array_var = Expression::Variable(module);
-                return Expression::ArrayMethodCall {
-                    array: Box::new(array_var),
-                    method: function,
-                    args
-                };
-            } else {
-                return Expression::FunctionCall { module, function, args };
-            }
-        } else {
-            // Just a module.function without arguments
-            return Expression::FunctionCall { module, function, args: vec![] };
+            let kind = if emit == Emit::Obj { OutputKind::Object } else { OutputKind::Executable };
+            let toolchain = exit_on_error(build::toolchain_with_runtime(toolchain, kind));
+            compile_generated_cpp(Path::new(&temp_cpp_file), Path::new(&output_filename), kind, &toolchain, keep_cpp);
+            println!("Compiled successfully to: {}", output_filename);
         }
     }
-
-    // Regular variable name
-    let var_name = tokens[*i].clone();
-    *i += 1;
-    Expression::Variable(var_name)
 }
 
-// Parse primary expressions (variables, literals, array access)
-#[allow(dead_code)]
-fn parse_primary_expression_expr(tokens: &[String], i: &mut usize) -> Expression {
-    if *i >= tokens.len() {
-        return Expression::StringLiteral("".to_string());
-    }
+// Compile a generated C++ file, removing it afterwards unless `keep_cpp` is set.
+// On failure the C++ file is kept for debugging and the process exits.
+fn compile_generated_cpp(cpp_file: &Path, output: &Path, kind: OutputKind, toolchain: &Toolchain, keep_cpp: bool) {
+    match compile_cpp(cpp_file, output, kind, toolchain) {
+        Ok(()) => {
+            if keep_cpp {
+                println!("C++ source code kept at: {}", cpp_file.display());
+            } else {
+                // Successful compilation - remove the temporary C++ file
+                fs::remove_file(cpp_file)
+                    .expect("Should have been able to remove the temporary C++ file");
+            }
+        },
+        Err(message) => {
+            eprintln!("{}", message);
 
-    let token = &tokens[*i];
+            // Keep the C++ source file for debugging if compilation fails
+            println!("C++ source code saved to: {} for manual compilation", cpp_file.display());
+            println!("To compile manually: {}", toolchain.command_line(cpp_file, output, kind));
 
-    // String literals
-    if (token.starts_with('"') && token.ends_with('"')) || (token.starts_with('\'') && token.ends_with('\'')) {
-        *i += 1;
-        return Expression::StringLiteral(token[1..token.len()-1].to_string());
+            std::process::exit(1);
+        }
     }
+}
 
-    // Numeric literals
-    if let Ok(num) = token.parse::<f64>() {
-        *i += 1;
-        return Expression::Number(num);
+// Create a fresh directory only readable by the current user
+fn create_private_temp_dir() -> std::io::Result<PathBuf> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let dir = std::env::temp_dir().join(format!("nymyac-run-{}-{}", std::process::id(), nanos));
+
+    // Not recursive, so an existing directory is never reused; on Unix it is
+    // private from the moment it exists
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
     }
+    builder.create(&dir)?;
+    Ok(dir)
+}
 
-    // Array access: array[index]
-    if *i + 2 < tokens.len() && &tokens[*i + 1] == "[" {
-        let array_name = tokens[*i].clone();
-        *i += 2; // Skip array name and '['
-
-        let index_expr = parse_expression(tokens, i);
-
-        if *i < tokens.len() && &tokens[*i] == "]" {
-            *i += 1; // Skip ']'
-            let array_var = Expression::Variable(array_name);
-            return Expression::ArrayAccess {
-                array: Box::new(array_var),
-                index: Box::new(index_expr)
-            };
-        } else {
-            // Malformed array access
-            return Expression::Variable(array_name);
+// Compile `input` in a temporary directory, execute it and return its exit code
+fn run_program(input: &str, program_args: &[String], toolchain: &Toolchain) -> i32 {
+    let source_code = read_source(input);
+    let cpp_code = compile_or_exit(input, &source_code, toolchain).cpp;
+    let toolchain = exit_on_error(build::toolchain_with_runtime(toolchain, OutputKind::Executable));
+
+    let temp_dir = match create_private_temp_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("Error: Could not create temporary build directory: {}", e);
+            return 1;
         }
-    }
-
-    // Function call: module.function(args) or object.method(args) - this is handled in the main loop but also here for completion
-    if *i + 2 < tokens.len() && &tokens[*i + 1] == "." && &tokens[*i + 2] != "(" {
-        // Handle: module.function() pattern
-        let module = tokens[*i].clone();
-        let function = tokens[*i + 2].clone();
-        *i += 3; // Skip module.function
-
-        if *i < tokens.len() && &tokens[*i] == "(" {
-            *i += 1; // Skip '('
-
-            let mut args = Vec::new();
-            while *i < tokens.len() && &tokens[*i] != ")" {
-                if &tokens[*i] != "," {
-                    args.push(tokens[*i].clone());
+    };
+
+    let stem = Path::new(input)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "program".to_string());
+    let cpp_file = temp_dir.join(format!("{}.cpp", stem));
+    let exe_file = temp_dir.join(if cfg!(windows) { format!("{}.exe", stem) } else { stem });
+
+    let exit_code = match fs::write(&cpp_file, &cpp_code) {
+        Err(e) => {
+            eprintln!("Error: Could not write C++ source file: {}", e);
+            1
+        },
+        Ok(()) => match compile_cpp(&cpp_file, &exe_file, OutputKind::Executable, &toolchain) {
+            Err(message) => {
+                eprintln!("{}", message);
+                1
+            },
+            Ok(()) => match Command::new(&exe_file).args(program_args).status() {
+                Ok(status) => exit_code_of(status),
+                Err(e) => {
+                    eprintln!("Error: Could not execute {}: {}", exe_file.display(), e);
+                    1
                 }
-                *i += 1;
-            }
+            },
+        },
+    };
 
-            if *i < tokens.len() && &tokens[*i] == ")" {
-                *i += 1; // Skip ')'
-            }
+    let _ = fs::remove_dir_all(&temp_dir);
+    exit_code
+}
 
-            return Expression::FunctionCall {
-                module,
-                function,
-                args
-            };
+// Map a child exit status to our own exit code, following the shell's 128+signal convention
+fn exit_code_of(status: ExitStatus) -> i32 {
+    if let Some(code) = status.code() {
+        return code;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
         }
     }
+    1
+}
 
-    // Array literal: [] (handled as separate tokens)
-    if token == "[" && *i + 1 < tokens.len() && &tokens[*i + 1] == "]" {
-        *i += 2; // Skip '[' and ']'
-        return Expression::ArrayLiteral(vec![]);
+// Path of a single source file in `#line` directives: as given on the command
+// line, or just the file name for reproducible builds
+fn generated_source_path(input: &str, toolchain: &Toolchain) -> String {
+    match Path::new(input).file_name() {
+        Some(name) if toolchain.args.is_reproducible() => name.to_string_lossy().into_owned(),
+        _ => input.to_string(),
     }
-
-    // Regular variable name
-    let var_name = tokens[*i].clone();
-    *i += 1;
-    Expression::Variable(var_name)
 }
 
-// Parse binary operations
-#[allow(dead_code)]
-fn parse_binary_op(tokens: &[String], i: &mut usize, left: Expression, operator: String) -> Expression {
-    if *i < tokens.len() && tokens[*i] == operator {
-        *i += 1; // Skip operator
-
-        let right = parse_expression(tokens, i);
-
-        return Expression::BinaryOperation {
-            left: Box::new(left),
-            operator,
-            right: Box::new(right)
-        };
+// Print diagnostics to stderr prefixed with the file name; returns the error count
+fn report_diagnostics(input: &str, diagnostics: &[Diagnostic]) -> usize {
+    for diagnostic in diagnostics {
+        match diagnostic.span {
+            Some(span) => eprintln!("{}:{}:{}: {}", input, span.line, span.column, diagnostic),
+            None => eprintln!("{}: {}", input, diagnostic),
+        }
     }
-
-    left
+    diagnostics.iter().filter(|d| d.is_error()).count()
 }
 
-// Helper to parse simple expressions (non-binary)
-#[allow(dead_code)]
-fn parse_simple_expression(tokens: &[String], i: &mut usize) -> Expression {
-    if *i < tokens.len() {
-        // Handle array literal: []
-        if *i + 1 < tokens.len() && &tokens[*i] == "[" && &tokens[*i + 1] == "]" {
-            *i += 2; // Skip '[' and ']'
-            // Return an actual ArrayLiteral expression
-            return Expression::ArrayLiteral(vec![]); // Empty array literal
-        }
-        // Handle array access like: array[index]
-        else if *i + 2 < tokens.len() && &tokens[*i + 1] == "[" {
-            let array_name = tokens[*i].clone(); // Get the array name
-            *i += 1; // Skip '['
-            let index_expr = parse_simple_expression(tokens, i);
-            if *i < tokens.len() && &tokens[*i] == "]" {
-                *i += 1; // Skip ']'
-                let array_var = Expression::Variable(array_name);
-                return Expression::ArrayAccess {
-                    array: Box::new(array_var),
-                    index: Box::new(index_expr)
-                };
-            }
-            // If no closing bracket, just return as variable
-            return Expression::Variable(array_name);
+// Compile the source to C++, reporting diagnostics; exits if there are errors
+fn compile_or_exit(input: &str, source_code: &str, toolchain: &Toolchain) -> nymyac::Output {
+    match nymyac::compile(source_code, &CompileOptions::new(generated_source_path(input, toolchain))) {
+        Ok(output) => {
+            report_diagnostics(input, &output.warnings);
+            output
+        },
+        Err(Diagnostics(diagnostics)) => {
+            let errors = report_diagnostics(input, &diagnostics);
+            eprintln!("{}: aborting due to {} error(s)", input, errors);
+            std::process::exit(1);
         }
-        // Handle function calls like: module.function() or variable.method() - this is handled in the main loop but also here for completion
-        else if *i + 2 < tokens.len() && &tokens[*i + 1] == "." {
-            // Look ahead to see if we have a nested namespace pattern like: graphics.stl_basic.function()
-            let mut is_nested_namespace = false;
-            let mut module_name = "".to_string();
-            let mut function_name = "".to_string();
-            let mut advance_amount = 0;
-
-            // Check for 3-token pattern: module . submodule . function
-            if *i + 4 < tokens.len() && &tokens[*i + 1] == "." && &tokens[*i + 3] == "." {
-                // Potential nested namespace: check if first part is a known module
-                let potential_module = format!("{}.{}", tokens[*i], tokens[*i + 2]);
-                let known_modules = ["crystal", "math", "quantum", "symbolic", "networking", "system", "image", "graphics", "physics", "datetime", "lowlevel", "ml", "gui"];
-
-                if known_modules.contains(&tokens[*i].as_str()) {
-                    // This looks like a nested namespace call: module.submodule.function()
-                    module_name = potential_module;
-                    function_name = tokens[*i + 4].clone();
-                    advance_amount = 5; // module . submodule . function
-                    is_nested_namespace = true;
-                }
-            }
-
-            if is_nested_namespace {
-                *i += advance_amount; // Skip module.submodule.function
-
-                let mut args = Vec::new();
-                if *i < tokens.len() && &tokens[*i] == "(" {
-                    *i += 1; // Skip '('
+    }
+}
 
-                    while *i < tokens.len() && &tokens[*i] != ")" {
-                        if &tokens[*i] != "," {
-                            args.push(tokens[*i].clone());
-                        }
-                        *i += 1;
-                    }
+// Front-end-only validation for `nymyac check`; never invokes the C++ compiler
+fn check_file(input: &str) -> i32 {
+    let source_code = read_source(input);
+    let diagnostics = analyze(&source_code, &BTreeMap::new()).diagnostics;
+    let errors = report_diagnostics(input, &diagnostics);
+    let warnings = diagnostics.len() - errors;
+
+    if errors > 0 {
+        eprintln!("{}: {} error(s), {} warning(s)", input, errors, warnings);
+        1
+    } else {
+        println!("{}: ok ({} warning(s))", input, warnings);
+        0
+    }
+}
 
-                    if *i < tokens.len() && &tokens[*i] == ")" {
-                        *i += 1; // Skip ')'
-                    }
+fn main() {
+    let cli = Cli::parse();
 
-                    // Check if this is an array method call (when module is a variable name starting with lowercase)
-                    if module_name.chars().next().map_or(false, |c| c.is_ascii_lowercase()) &&
-                       (function_name == "append" || function_name == "length" || function_name == "size" ||
-                        function_name == "get" || function_name == "at" || function_name == "set") {
-                        let array_var = Expression::Variable(module_name);
-                        return Expression::ArrayMethodCall {
-                            array: Box::new(array_var),
-                            method: function_name,
-                            args
-                        };
-                    } else {
-                        return Expression::FunctionCall { module: module_name, function: function_name, args };
-                    }
-                } else {
-                    // This is a nested namespace reference without parentheses
-                    return Expression::Variable(module_name); // Treat as variable/namespace reference
+    match cli.command {
+        Some(Commands::Build(build)) => build_command(build),
+        Some(Commands::Run(run)) => {
+            let toolchain = Toolchain::from_args(&run.toolchain);
+            std::process::exit(run_program(&run.input, &run.program_args, &toolchain))
+        },
+        Some(Commands::Check(check)) => {
+            let code = match resolve_input(check.input.as_deref()) {
+                Some(Input::File(input)) => check_file(&input),
+                Some(Input::Project(manifest)) => check_project(&manifest),
+                None => {
+                    eprintln!("Error: No input file given and no {} found", project::MANIFEST_FILE);
+                    1
                 }
-            } else {
-                // Handle regular module.function() or variable.method() call
-                let module = tokens[*i].clone();
-                let function = tokens[*i + 2].clone();
-                *i += 3; // Skip module.function
-
-                let mut args = Vec::new();
-                if *i < tokens.len() && &tokens[*i] == "(" {
-                    *i += 1; // Skip '('
-
-                    while *i < tokens.len() && &tokens[*i] != ")" {
-                        if &tokens[*i] != "," {
-                            args.push(tokens[*i].clone());
-                        }
-                        *i += 1;
-                    }
-
-                    if *i < tokens.len() && &tokens[*i] == ")" {
-                        *i += 1; // Skip ')'
-                    }
+            };
+            std::process::exit(code)
+        },
+        Some(Commands::Emit(emit)) => {
+            let toolchain = Toolchain::from_args(&emit.toolchain);
+            emit_stage(&emit.input, emit.output, emit.stage, false, &toolchain)
+        },
+        None => build_command(cli.build),
+    }
+}
 
-                    // Check if this is an array method call (when module is a variable name starting with lowercase)
-                    if module.chars().next().map_or(false, |c| c.is_ascii_lowercase()) &&
-                       (function == "append" || function == "length" || function == "size" ||
-                        function == "get" || function == "at" || function == "set") {
-                        let array_var = Expression::Variable(module);
-                        return Expression::ArrayMethodCall {
-                            array: Box::new(array_var),
-                            method: function,
-                            args
-                        };
-                    } else {
-                        return Expression::FunctionCall { module, function, args };
-                    }
-                } else {
-                    return Expression::FunctionCall { module, function, args: vec![] };
-                }
-            }
-        } else if tokens[*i].starts_with('"') && tokens[*i].ends_with('"') {
-            // String literal
-            let content = tokens[*i][1..tokens[*i].len()-1].to_string(); // Remove quotes
-            *i += 1;
-            return Expression::StringLiteral(content);
-        } else if tokens[*i].parse::<f64>().is_ok() {
-            // Number literal
-            let num = tokens[*i].parse::<f64>().unwrap();
-            *i += 1;
-            return Expression::Number(num);
-        } else {
-            // Variable name
-            let var_name = tokens[*i].clone();
-            *i += 1;
-            return Expression::Variable(var_name);
+// `nymyac build` and the bare `nymyac <input>` form
+fn build_command(args: BuildArgs) {
+    match resolve_input(args.input.as_deref()) {
+        Some(Input::File(input)) => {
+            let toolchain = Toolchain::from_args(&args.toolchain);
+            emit_stage(&input, args.output.clone(), args.emit, args.keep_cpp, &toolchain)
+        },
+        Some(Input::Project(manifest)) => build_project(&manifest, &args),
+        None => {
+            eprintln!("Error: No input file given and no {} found", project::MANIFEST_FILE);
+            eprintln!("NymyaLang Compiler v{}", env!("CARGO_PKG_VERSION"));
+            eprintln!("Usage: nymyac [build|run|check|emit] <input.nym> [-o output]");
+            eprintln!("       nymyac [build|check]   (inside a project with a {})", project::MANIFEST_FILE);
+            eprintln!("Run `nymyac --help` for all options");
+            std::process::exit(1)
         }
     }
-
-    Expression::StringLiteral("".to_string())
 }
 
-// Generate C++ code from parsed statements
-fn generate_cpp_from_statements(statements: &[Statement]) -> String {
-    let mut cpp_code = String::new();
-
-    for stmt in statements {
-        match stmt {
-            Statement::Import(_module) => {
-                // Import statements don't generate executable code, just ensure the namespace exists
-                continue;
-            },
-            Statement::VariableAssignment { var_name, expression } => {
-                // Handle array method calls properly - need to distinguish between initialization and method calls
-                match expression {
-                    Expression::ArrayMethodCall { array, method, args } => {
-                        // This is an array method call like list.length or list.get(index) that returns a value
-                        let array_cpp = generate_cpp_for_expression(array.as_ref());
-                        let args_cpp: Vec<String> = args.iter()
-                            .map(|arg| {
-                                if (arg.starts_with('"') && arg.ends_with('"')) || (arg.starts_with('\'') && arg.ends_with('\'')) {
-                                    // Convert single quotes to double quotes for C++
-                                    let content = &arg[1..arg.len()-1];
-                                    format!("\"{}\"", content)
-                                } else {
-                                    arg.to_string() // Variables or other expressions
-                                }
-                            })
-                            .collect();
-
-                        // Map NymyaLang array methods to C++ equivalents - these methods return values for assignment
-                        let method_call = match method.as_str() {
-                            "append" => format!("{}.push_back({})", array_cpp, args_cpp.join(", ")), // Side-effect, returns void
-                            "length" | "size" => format!("{}.size()", array_cpp), // Returns size value
-                            "get" | "at" => format!("{}[{}]", array_cpp, args_cpp.join(", ")), // Returns element value
-                            "set" => {
-                                if args_cpp.len() >= 2 {
-                                    format!("{}[{}] = {}", array_cpp, args_cpp[0], args_cpp[1]) // Assignment, returns void
-                                } else {
-                                    format!("{}[0] = {}", array_cpp, args_cpp.get(0).unwrap_or(&"0".to_string()))
-                                }
-                            },
-                            _ => format!("{}->{}({})", array_cpp, method, args_cpp.join(", ")) // General method call
-                        };
-
-                        // For method calls that return values (length, get, at), generate assignment
-                        // For side-effect calls (append, set), we might need special handling
-                        if method == "append" || method == "set" {
-                            // These are void-returning operations that modify the array
-                            cpp_code.push_str(&format!("    {};\n", method_call));
-                        } else {
-                            // These return values for assignment
-                            cpp_code.push_str(&format!("    auto {} = {};\n", var_name, method_call));
-                        }
-                    },
-                    Expression::ArrayAccess { array, index } => {
-                        // This is an array access like array[index]
-                        let array_cpp = generate_cpp_for_expression(array.as_ref());
-                        let index_cpp = generate_cpp_for_expression(index.as_ref());
-                        cpp_code.push_str(&format!("    auto {} = {}[{}];\n", var_name, array_cpp, index_cpp));
-                    },
-                    Expression::ArrayLiteral(_) => {
-                        // This is an array initialization like: var my_list = []
-                        cpp_code.push_str(&format!("    std::vector<int> {};\n", var_name));  // Initialize as empty vector
-                    },
-                    _ => {
-                        // Regular variable assignment - use auto for better type inference
-                        let expr_cpp = generate_cpp_for_expression(expression);
-                        cpp_code.push_str(&format!("    auto {} = {};\n", var_name, expr_cpp));
-                    }
-                }
-            },
-            Statement::FunctionCall { module, function, args } => {
-                if module == "crystal" && function == "manifest" && args.len() == 1 {
-                    // Extract string content from the argument (remove quotes if simple string literal)
-                    let arg = &args[0];
+// What a command-line input refers to
+enum Input {
+    File(String),
+    /// Path of a project's nymya.toml
+    Project(PathBuf),
+}
 
-                    // Check if it's a simple string literal (surrounded by quotes)
-                    if (arg.starts_with("\"") && arg.ends_with("\"")) || (arg.starts_with("\'") && arg.ends_with("\'")) {
-                        let content = &arg[1..arg.len()-1]; // Remove surrounding quotes
-                        cpp_code.push_str(&format!("    crystal::manifest(\"{}\");\n", content));
-                    } else {
-                        // For complex expressions like "text" + x.to_string(), output as-is
-                        // These should be already in proper C++ expression format after parsing
-                        cpp_code.push_str(&format!("    crystal::manifest({});\n", arg));
-                    }
-                } else {
-                    // Generic function call generation
-                    let args_cpp: Vec<String> = args.iter()
-                        .map(|arg| {
-                            // Handle different types of arguments
-                            if (arg.starts_with("\"") && arg.ends_with("\"")) || (arg.starts_with("\'") && arg.ends_with("\'")) {
-                                // String literals - convert single quotes to double quotes for C++
-                                let content = &arg[1..arg.len()-1];
-                                format!("\"{}\"", content)
-                            } else {
-                                // Variables or complex expressions - output as-is
-                                arg.to_string()
-                            }
-                        })
-                        .collect();
+// A `.nym` path is a single file; a directory or nymya.toml is a project. Without an
+// input, use the project containing the current directory, if any.
+fn resolve_input(input: Option<&str>) -> Option<Input> {
+    match input {
+        None => std::env::current_dir().ok()
+            .and_then(|dir| project::find_manifest(&dir))
+            .map(Input::Project),
+        Some(path) if Path::new(path).is_dir() => Some(Input::Project(Path::new(path).join(project::MANIFEST_FILE))),
+        Some(path) if Path::new(path).file_name().is_some_and(|name| name == project::MANIFEST_FILE) => {
+            Some(Input::Project(PathBuf::from(path)))
+        },
+        Some(path) => Some(Input::File(path.to_string())),
+    }
+}
 
-                    // Convert nested module names from dot notation to C++ namespace notation
-                    let module_cpp = module.replace(".", "::");
-                    cpp_code.push_str(&format!("    {}::{}({});\n", module_cpp, function, args_cpp.join(", ")));
-                }
-            },
-            Statement::ArrayMethodCall { array_var, method, args } => {
-                // Generate C++ code for array method calls like: array.append(item)
-                let args_cpp: Vec<String> = args.iter()
-                    .map(|arg| {
-                        if (arg.starts_with('"') && arg.ends_with('"')) || (arg.starts_with('\'') && arg.ends_with('\'')) {
-                            // Convert single quotes to double quotes for C++
-                            let content = &arg[1..arg.len()-1];
-                            format!("\"{}\"", content)
-                        } else {
-                            arg.to_string() // Variables or other expressions
-                        }
-                    })
-                    .collect();
+// Print the diagnostics of every file of a project; returns the error and warning counts
+fn report_project(front_end: &ProjectFrontEnd) -> (usize, usize) {
+    let mut errors = 0;
+    let mut warnings = 0;
+    for file in front_end.files() {
+        let file_errors = report_diagnostics(&file.source_file, &file.front_end.diagnostics);
+        errors += file_errors;
+        warnings += file.front_end.diagnostics.len() - file_errors;
+    }
+    (errors, warnings)
+}
 
-                // Map NymyaLang array methods to C++ STL equivalents
-                let method_call = match method.as_str() {
-                    "append" => format!("{}.push_back({})", array_var, args_cpp.join(", ")),
-                    "length" | "size" => format!("{}.size()", array_var),
-                    "get" | "at" => format!("{}[{}]", array_var, args_cpp.join(", ")),
-                    "set" => {
-                        if args_cpp.len() >= 2 {
-                            format!("{}[{}] = {}", array_var, args_cpp[0], args_cpp[1])
-                        } else {
-                            format!("{}[0] = {}", array_var, args_cpp.get(0).unwrap_or(&"0".to_string()))
-                        }
-                    },
-                    _ => format!("{}->{}({})", array_var, method, args_cpp.join(", ")) // Fallback for other methods
-                };
+fn exit_on_error<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|message| {
+        eprintln!("Error: {}", message);
+        std::process::exit(1);
+    })
+}
 
-                cpp_code.push_str(&format!("    {};\n", method_call));
-            }
-            Statement::ExpressionStmt { expression } => {
-                // Generate C++ code for expression statements (like obj.method() calls that are statements)
-                let expr_cpp = generate_cpp_for_expression(expression);
-                cpp_code.push_str(&format!("    {};\n", expr_cpp));
-            }
-        }
+// `nymyac check` for a project
+fn check_project(manifest: &Path) -> i32 {
+    let project = exit_on_error(project::load(manifest));
+    let front_end = exit_on_error(build::analyze(&project));
+    let (errors, warnings) = report_project(&front_end);
+
+    if errors > 0 {
+        eprintln!("{}: {} error(s), {} warning(s)", project.name(), errors, warnings);
+        1
+    } else {
+        println!("{}: ok, {} module(s) ({} warning(s))", project.name(), front_end.files().count(), warnings);
+        0
     }
-
-    cpp_code
 }
 
-// Generate C++ code for expressions
-fn generate_cpp_for_expression(expr: &Expression) -> String {
-    match expr {
-        Expression::FunctionCall { module, function, args } => {
-            let args_cpp: Vec<String> = args.iter()
-                .map(|arg| {
-                    if (arg.starts_with('"') && arg.ends_with('"')) || (arg.starts_with('\'') && arg.ends_with('\'')) {
-                        // Convert single quotes to double quotes for C++
-                        let content = &arg[1..arg.len()-1];
-                        format!("\"{}\"", content)
-                    } else {
-                        arg.to_string() // Variables or other expressions
-                    }
-                })
-                .collect();
-            // Convert nested module names from dot notation to C++ namespace notation
-            let module_cpp = module.replace(".", "::");
-            format!("{}::{}({})", module_cpp, function, args_cpp.join(", "))
+// `nymyac build` for a project
+fn build_project(manifest: &Path, args: &BuildArgs) {
+    let stage = match args.emit {
+        Emit::Tokens | Emit::Ast => {
+            eprintln!("Error: --emit tokens and --emit ast work on single files; use `nymyac emit <stage> <file.nym>`");
+            std::process::exit(1);
         },
-        Expression::ArrayAccess { array, index } => {
-            let array_cpp = generate_cpp_for_expression(array.as_ref());
-            let index_cpp = generate_cpp_for_expression(index.as_ref());
-            format!("{}[{}]", array_cpp, index_cpp)
+        Emit::Cpp | Emit::Obj if args.output.is_some() => {
+            eprintln!("Error: -o cannot be used with --emit cpp or --emit obj for projects; the files are written to target/build");
+            std::process::exit(1);
         },
-        Expression::ArrayMethodCall { array, method, args } => {
-            let array_cpp = generate_cpp_for_expression(array.as_ref());
-            let args_cpp: Vec<String> = args.iter()
-                .map(|arg| {
-                    if (arg.starts_with('"') && arg.ends_with('"')) || (arg.starts_with('\'') && arg.ends_with('\'')) {
-                        // Convert single quotes to double quotes for C++
-                        let content = &arg[1..arg.len()-1];
-                        format!("\"{}\"", content)
-                    } else {
-                        arg.to_string() // Variables or other expressions
-                    }
-                })
-                .collect();
+        Emit::Cpp => Stage::Cpp,
+        Emit::Obj => Stage::Objects,
+        Emit::Exe => Stage::Output,
+    };
+
+    let project = exit_on_error(project::load(manifest));
+    println!("Building {} v{} ({})", project.name(), project.manifest.project.version, display_path(&project.root));
+
+    let front_end = exit_on_error(build::analyze(&project));
+    let (errors, _) = report_project(&front_end);
+    if errors > 0 {
+        eprintln!("{}: aborting due to {} error(s)", project.name(), errors);
+        std::process::exit(1);
+    }
 
-            // Map Nya Elyria's consciousness-integrated array methods to appropriate C++ equivalents
-            match method.as_str() {
-                "append" => format!("{}.push_back({})", array_cpp, args_cpp.join(", ")),
-                "length" | "size" => format!("{}.size()", array_cpp),
-                "get" | "at" => format!("{}[{}]", array_cpp, args_cpp.join(", ")), // Use [] for simple indexing
-                "set" => {
-                    if args_cpp.len() >= 2 {
-                        format!("{}[{}] = {}", array_cpp, args_cpp[0], args_cpp[1])
-                    } else {
-                        format!("{}[0] = {}", array_cpp, args_cpp.get(0).unwrap_or(&"0".to_string()))
-                    }
-                },
-                _ => format!("{}->{}({})", array_cpp, method, args_cpp.join(", ")) // Fallback for other methods
-            }
+    let options = BuildOptions { stage, output: args.output.as_ref().map(PathBuf::from), toolchain: args.toolchain.clone() };
+    let result = build::build(&project, front_end, &options, |event| match event {
+        BuildEvent::Compiling { unit, position, stale } => {
+            println!("   [{}/{}] Compiling {} ({})", position, stale, unit.name, display_path(&unit.source));
         },
-        Expression::MethodCall { object, method, args } => {
-            let object_cpp = generate_cpp_for_expression(object.as_ref());
-            let args_cpp: Vec<String> = args.iter()
-                .map(|arg| {
-                    if (arg.starts_with('"') && arg.ends_with('"')) || (arg.starts_with('\'') && arg.ends_with('\'')) {
-                        // Convert single quotes to double quotes for C++
-                        let content = &arg[1..arg.len()-1];
-                        format!("\"{}\"", content)
-                    } else {
-                        arg.to_string() // Variables or other expressions
-                    }
-                })
-                .collect();
-
-            // Handle common NymyaLang methods that map to appropriate C++ equivalents
-            match method.as_str() {
-                "to_string" => format!("std::to_string({})", object_cpp),  // Convert primitives to string using std::to_string
-                _ => format!("{}->{}({})", object_cpp, method, args_cpp.join(", ")) // General method call format using -> pointer syntax
+        BuildEvent::Compiled { unit, success, output } => {
+            // Warnings of successful units are shown too
+            eprint!("{}", output);
+            if !success {
+                eprintln!("error: C++ compilation of {} failed", unit.name);
             }
         },
-        Expression::ArrayLiteral(_elements) => {
-            // For now, represent empty array initialization as a default constructor
-            "std::vector<int>{}".to_string()  // For empty arrays represented as []
+    });
+    let built = exit_on_error(result);
+
+    match (stage, &built.output, &built.header) {
+        (Stage::Cpp, _, _) => println!("Wrote {} translation unit(s) to: {}", built.units, display_path(&built.build_dir)),
+        (Stage::Objects, _, _) => {
+            println!("   Compiled {} of {} unit(s)", built.compiled, built.units);
+            println!("Object files are in: {}", display_path(&built.build_dir));
         },
-        Expression::Variable(name) => name.clone(),
-        Expression::Number(val) => val.to_string(),
-        Expression::StringLiteral(content) => format!("\"{}\"", content),
-        Expression::BinaryOperation { left, operator, right } => {
-            let left_cpp = generate_cpp_for_expression(left.as_ref());
-            let right_cpp = generate_cpp_for_expression(right.as_ref());
-
-            // Handle string concatenation specially since C++ doesn't allow direct concatenation
-            // of string literals with other types
-            if operator == "+" {
-                // Only apply special handling when one side is an actual string literal
-                if left_cpp.starts_with('"') || right_cpp.starts_with('"') {
-                    let left_str = if left_cpp.starts_with('"') {
-                        format!("std::string({})", left_cpp)
-                    } else {
-                        left_cpp
-                    };
-                    let right_str = if right_cpp.starts_with('"') {
-                        format!("std::string({})", right_cpp)
-                    } else {
-                        right_cpp
-                    };
-                    return format!("{} + {}", left_str, right_str);
-                }
+        (Stage::Output, Some(output), header) => {
+            println!("   Compiled {} of {} unit(s)", built.compiled, built.units);
+            match header {
+                Some(header) => println!("Compiled successfully to: {} (header: {})", display_path(output), display_path(header)),
+                None => println!("Compiled successfully to: {}", display_path(output)),
             }
-
-            format!("{} {} {}", left_cpp, operator, right_cpp)
         },
+        (Stage::Output, None, _) => unreachable!("complete builds have an output"),
     }
 }
-
-// Enhanced target code generator - generates C++ code with actual program execution
-fn generate_target(source_file: &str, source_code: String) -> String {
-    // Parse the source code to extract actual statements
-    let statements = parse(&source_code);
-
-    // Generate C++ code from statements
-    let executable_code = generate_cpp_from_statements(&statements);
-
-    // Create a C++ program with actual executable code
-    format!(r#"/*
- * NymyaLang to C++ generated code (version {})
- * Auto-generated from {}
- */
-
-#include <iostream>
-#include <string>
-#include <vector>
-#include <cmath>
-#include <fstream>
-
-// Math utilities
-namespace math {{
-    double sqrt(double x) {{ return std::sqrt(x); }}
-    double abs(double x) {{ return x < 0 ? -x : x; }}
-    double min(double a, double b) {{ return a < b ? a : b; }}
-    double max(double a, double b) {{ return a > b ? a : b; }}
-    double pow(double base, double exp) {{ return std::pow(base, exp); }}
-    double sin(double x) {{ return std::sin(x); }}
-    double cos(double x) {{ return std::cos(x); }}
-    double tan(double x) {{ return std::tan(x); }}
-
-    const double PI = 3.141592653589793;
-
-    // Integer math functions
-    long long pow_int(long long base, long long exp) {{
-        if (exp <= 0) return 1;
-        long long result = 1;
-        long long b = base;
-        long long e = exp;
-        while (e > 0) {{
-            if (e % 2 == 1) result *= b;
-            b *= b;
-            e /= 2;
-        }}
-        return result;
-    }}
-
-    long long gcd(long long a, long long b) {{
-        a = a < 0 ? -a : a;
-        b = b < 0 ? -b : b;
-        while (b != 0) {{
-            long long temp = b;
-            b = a % b;
-            a = temp;
-        }}
-        return a;
-    }}
-}}
-
-// Crystal utilities (console output and file operations)
-namespace crystal {{
-    void manifest(const std::string& msg) {{
-        std::cout << msg << std::endl;
-    }}
-
-    void print(const std::string& msg) {{
-        std::cout << msg;
-    }}
-
-    // File operations namespace (Taygetan-inspired names)
-    namespace file {{
-        // dalan_orin = home food (nourish home with content)
-        bool dalan_orin(const std::string& path, const std::string& content) {{
-            std::ofstream file(path);
-            if (file.is_open()) {{
-                file << content;
-                file.close();
-                return true;
-            }}
-            return false;
-        }}
-
-        // dalan_aya = home see (check if home exists)
-        bool dalan_aya(const std::string& path) {{
-            std::ifstream file(path);
-            bool exists = file.good();
-            if (exists) {{
-                file.close();
-            }}
-            return exists;
-        }}
-
-        // dalan_karma = home work (read content from home)
-        std::string dalan_karma(const std::string& path) {{
-            std::ifstream file(path);
-            if (file.is_open()) {{
-                std::string content;
-                std::string line;
-                while (std::getline(file, line)) {{
-                    content += line + "\\n";
-                }}
-                file.close();
-                return content;
-            }}
-            return "";
-        }}
-
-        // dalan_lora = home exist (create output stream)
-        std::ofstream dalan_lora(const std::string& path) {{
-            return std::ofstream(path);
-        }}
-
-        // dalan_shira = home love (create input stream)
-        std::ifstream dalan_shira(const std::string& path) {{
-            return std::ifstream(path);
-        }}
-    }}
-}}
-
-// Symbolic mathematics namespace
-namespace symbolic {{
-    namespace numerology {{
-        struct Meaning {{
-            std::string meaning;
-            std::vector<std::string> traits;
-        }};
-
-        Meaning get_meaning(long long number) {{
-            Meaning result;
-            result.meaning = "Meaning for number " + std::to_string(number);
-            result.traits = {{"trait1", "trait2", "trait3"}};
-            return result;
-        }}
-    }}
-}}
-
-int main() {{
-    try {{
-        // Runtime initialization message (optional)
-        // crystal::manifest("NymyaLang runtime {} initialized");
-        // crystal::manifest("Compiled from: {}");
-
-        // Actual program execution
-{}
-
-        // Program completion message
-        // crystal::manifest("Program execution completed");
-    }} catch (const std::exception& e) {{
-        std::cerr << "Runtime error: " << e.what() << std::endl;
-        return 1;
-    }}
-    return 0;
-}}
-"#,
-        env!("CARGO_PKG_VERSION"),  // Use the crate version
-        source_file,
-        env!("CARGO_PKG_VERSION"),  // Use the crate version
-        source_file,
-        executable_code
-    )
-}
-
-fn main() {
-    let args = Args::parse();
-
-    match &args.input {
-        Some(input) => {
-            if !input.ends_with(".nym") {
-                eprintln!("Error: Input file must have .nym extension");
-                std::process::exit(1);
-            }
-
-            if !Path::new(input).exists() {
-                eprintln!("Error: Input file does not exist: {}", input);
-                std::process::exit(1);
-            }
-
-            println!("Compiling {}...", input);
-
-            // Read the source file
-            let source_code = fs::read_to_string(input)
-                .expect("Should have been able to read the file");
-
-            // Generate target code
-            let cpp_code = generate_target(input, source_code);
-
-            // Determine output filename
-            let output_filename = if let Some(name) = &args.output {
-                name.clone()
-            } else {
-                // Default output: replace .nym with executable extension
-                let base_name = input.trim_end_matches(".nym");
-                if cfg!(windows) {
-                    format!("{}.exe", base_name)
-                } else {
-                    base_name.to_string()
-                }
-            };
-
-            // Write the generated C++ code to a temporary file first
-            let temp_cpp_file = format!("{}.cpp", output_filename.trim_end_matches(".exe"));
-            fs::write(&temp_cpp_file, &cpp_code)
-                .expect("Should have been able to write the C++ source file");
-
-            // Compile the C++ code to executable using g++
-            use std::process::Command;
-            let compile_result = Command::new("g++")
-                .arg(&temp_cpp_file)
-                .arg("-o")
-                .arg(&output_filename)
-                .arg("-std=c++17")
-                .arg("-O2")
-                .arg("-lm")  // Link math library
-                .output();
-
-            match compile_result {
-                Ok(output) => {
-                    if output.status.success() {
-                        // Successful compilation - remove the temporary C++ file
-                        std::fs::remove_file(&temp_cpp_file)
-                            .expect("Should have been able to remove the temporary C++ file");
-
-                        println!("Compiled successfully to: {}", output_filename);
-                    } else {
-                        eprintln!("C++ compilation failed:");
-                        eprintln!("{}", String::from_utf8_lossy(&output.stderr));
-
-                        // Keep the C++ source file for debugging if compilation fails
-                        println!("C++ source code saved to: {} for manual compilation", temp_cpp_file);
-
-                        std::process::exit(1);
-                    }
-                },
-                Err(e) => {
-                    eprintln!("Failed to run g++: {}. Keeping C++ source file: {}", e, temp_cpp_file);
-                    println!("To compile manually: g++ -std=c++17 {} -o {}", temp_cpp_file, output_filename);
-                    std::process::exit(1);
-                }
-            }
-        },
-        None => {
-            println!("NymyaLang Compiler v0.2.0-alpha~6");
-            println!("Usage: nymyac <input.nym> [-o output]");
-        }
-    }
-}
\ No newline at end of file
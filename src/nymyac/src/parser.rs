@@ -0,0 +1,1106 @@
+// Parser for NymyaLang: a recursive-descent parser over the token stream that
+// reports syntax problems as diagnostics instead of stopping at the first one.
+// After an error it skips to the end of the statement and carries on.
+
+use std::collections::BTreeSet;
+
+use crate::ast::{
+    AssignmentTarget, BinaryOperator, Block, Bound, Class, Enum, Expression, FieldDeclaration, Function, Interface,
+    MatchArm, MatchBody, Parameter, Pattern, Span, Spanned, Statement, TypeExpr, UnaryOperator, Variant,
+};
+use crate::diagnostics::Diagnostic;
+use crate::lexer::{tokenize, Token};
+use crate::runtime;
+
+// Top-level standard library namespaces; `name.member` on these is a module
+// access even when the module is not imported (sema reports that)
+const STANDARD_LIBRARY_ROOTS: &[&str] = &[
+    "crystal", "math", "quantum", "symbolic", "networking", "system", "image", "graphics", "physics", "datetime",
+    "lowlevel", "ml", "gui",
+];
+
+// Statements of the language the compiler skips with a warning for now; the
+// standard library sources use them
+const UNSUPPORTED_STATEMENTS: &[&str] = &["namespace", "export", "for", "struct", "val", "const", "try", "macro"];
+
+// Words that cannot be used as variable names
+const KEYWORDS: &[&str] = &[
+    "var", "import", "if", "let", "else", "while", "return", "func", "class", "enum", "interface", "extends",
+    "implements", "this", "super", "true", "false", "none", "null", "and", "or", "not",
+];
+
+// Merge dotted module paths (`crystal . file`, `geometry . shapes`) into one token
+// when they name a runtime module or a module the source imports, so the parser
+// sees `module.member` no matter how deeply the module is nested. Also returns
+// the module paths that were recognized.
+fn join_module_paths(tokens: Vec<Token>) -> (Vec<Token>, BTreeSet<String>) {
+    let mut modules: BTreeSet<String> = runtime::MODULES.iter().map(|module| module.name.to_string()).collect();
+    for (i, token) in tokens.iter().enumerate() {
+        if token.text == "import" {
+            let mut path = String::new();
+            let mut j = i + 1;
+            while j < tokens.len() {
+                path.push_str(&tokens[j].text);
+                modules.insert(path.clone());
+                if tokens.get(j + 1).is_some_and(|t| t.text == ".") {
+                    path.push('.');
+                    j += 2;
+                } else {
+                    break;
+                }
+            }
+        }
+    }
+
+    let mut joined: Vec<Token> = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        // Longest `a . b . c` starting here that names a module
+        let mut path = tokens[i].text.clone();
+        let mut end = i;
+        let mut j = i;
+        while j + 2 < tokens.len() && tokens[j + 1].text == "." {
+            path = format!("{}.{}", path, tokens[j + 2].text);
+            j += 2;
+            if modules.contains(&path) {
+                end = j;
+            }
+        }
+        let text = tokens[i..=end].iter().map(|t| t.text.as_str()).collect::<String>();
+        joined.push(Token { text, span: tokens[i].span });
+        i = end + 1;
+    }
+    (joined, modules)
+}
+
+// Parse NymyaLang source code into AST, collecting syntax diagnostics on the way
+pub fn parse(source: &str) -> (Vec<Spanned<Statement>>, Vec<Diagnostic>) {
+    let (tokens, modules) = join_module_paths(tokenize(source));
+    let mut parser = Parser { tokens, pos: 0, modules, variables: BTreeSet::new(), diagnostics: Vec::new() };
+
+    for token in &parser.tokens {
+        let quote = token.text.chars().next().filter(|c| *c == '"' || *c == '\'');
+        if let Some(quote) = quote {
+            if token.text.len() < 2 || !token.text.ends_with(quote) {
+                parser.diagnostics.push(Diagnostic::error(format!("unterminated string literal {}", token.text)).at(token.span));
+            }
+        }
+    }
+
+    let statements = parser.parse_statements(false);
+    (statements, parser.diagnostics)
+}
+
+fn is_identifier(text: &str) -> bool {
+    text.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+}
+
+fn is_call(expression: &Expression) -> bool {
+    match expression {
+        Expression::FunctionCall { .. }
+        | Expression::Call { .. }
+        | Expression::MethodCall { .. }
+        | Expression::ValueCall { .. }
+        | Expression::SuperCall { .. } => true,
+        Expression::OptionalChain { member, .. } => is_call(member),
+        _ => false,
+    }
+}
+
+// The diagnostic has already been recorded when a parse function fails
+struct ParseError;
+
+type ParseResult<T> = Result<T, ParseError>;
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    // Module paths the source can refer to (runtime modules and imports)
+    modules: BTreeSet<String>,
+    // Names declared with `var`; these are never module references
+    variables: BTreeSet<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&str> {
+        self.tokens.get(self.pos + offset).map(|token| token.text.as_str())
+    }
+
+    // Position of the current token; the end of input maps to the last token
+    fn span(&self) -> Span {
+        self.tokens.get(self.pos).or(self.tokens.last()).map(|token| token.span).unwrap_or_default()
+    }
+
+    fn advance(&mut self) -> String {
+        let text = self.tokens[self.pos].text.clone();
+        self.pos += 1;
+        text
+    }
+
+    fn check(&self, text: &str) -> bool {
+        self.peek() == Some(text)
+    }
+
+    fn eat(&mut self, text: &str) -> bool {
+        let found = self.check(text);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    // Whether the current token starts a new line (or there is no current token)
+    fn at_line_start(&self) -> bool {
+        match (self.pos.checked_sub(1).and_then(|i| self.tokens.get(i)), self.tokens.get(self.pos)) {
+            (Some(previous), Some(current)) => current.span.line > previous.span.line,
+            _ => true,
+        }
+    }
+
+    fn found(&self) -> String {
+        match self.peek() {
+            Some(text) => format!("`{}`", text),
+            None => "end of input".to_string(),
+        }
+    }
+
+    fn error<T>(&mut self, message: String) -> ParseResult<T> {
+        let span = self.span();
+        self.diagnostics.push(Diagnostic::error(message).at(span));
+        Err(ParseError)
+    }
+
+    fn expect(&mut self, text: &str, context: &str) -> ParseResult<()> {
+        if self.eat(text) {
+            return Ok(());
+        }
+        let found = self.found();
+        self.error(format!("expected `{}` {}, found {}", text, context, found))
+    }
+
+    fn expect_identifier(&mut self, what: &str) -> ParseResult<String> {
+        match self.peek() {
+            Some(text) if is_identifier(text) && !KEYWORDS.contains(&text) => Ok(self.advance()),
+            _ => {
+                let found = self.found();
+                self.error(format!("expected {}, found {}", what, found))
+            }
+        }
+    }
+
+    // A name, or `module.Name` for a type or interface of an imported module
+    fn expect_qualified_name(&mut self, what: &str) -> ParseResult<String> {
+        let name = self.expect_identifier(what)?;
+        if !self.modules.contains(&name) || !self.eat(".") {
+            return Ok(name);
+        }
+        let member = self.expect_identifier(&format!("a name after `{}.`", name))?;
+        Ok(format!("{}.{}", name, member))
+    }
+
+    // Skip the rest of the current statement: up to the end of the line,
+    // including any bracketed block that starts on it. Returns the skipped tokens.
+    fn skip_statement(&mut self) -> Vec<String> {
+        let mut skipped = Vec::new();
+        let mut depth = 0usize;
+        while let Some(text) = self.peek() {
+            if depth == 0 && !skipped.is_empty() && self.at_line_start() {
+                break;
+            }
+            match text {
+                "{" | "(" | "[" => depth += 1,
+                // A `}` at depth 0 closes the enclosing block; stray `)` and `]` are skipped
+                "}" if depth == 0 => break,
+                "}" | ")" | "]" => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            skipped.push(self.advance());
+        }
+        skipped
+    }
+
+    // Statements until the end of input, or until the closing `}` of a block
+    fn parse_statements(&mut self, in_block: bool) -> Block {
+        let mut statements = Vec::new();
+        loop {
+            // @-tags are metadata, and `;` may separate statements on one line
+            while self.peek().is_some_and(|text| text.starts_with('@') || text == ";") {
+                self.pos += 1;
+            }
+            match self.peek() {
+                None => break,
+                Some("}") if in_block => break,
+                Some("}") => {
+                    let _: ParseResult<()> = self.error("unexpected `}`".to_string());
+                    self.pos += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let span = self.span();
+            let start = self.pos;
+            match self.parse_statement(in_block) {
+                Ok(Some(statement)) => {
+                    statements.push(Spanned::new(span, statement));
+                    if !self.at_line_start() && !matches!(self.peek(), Some(";") | Some("}")) {
+                        let found = self.found();
+                        let _: ParseResult<()> = self.error(format!("expected the end of the statement, found {}", found));
+                        self.skip_statement();
+                    }
+                }
+                Ok(None) => {}
+                // An error found at the start of the next line leaves that line to be parsed
+                Err(ParseError) if self.pos > start && self.at_line_start() => {}
+                Err(ParseError) => {
+                    self.skip_statement();
+                }
+            }
+        }
+        statements
+    }
+
+    // `{ statements }`
+    fn parse_block(&mut self, context: &str) -> ParseResult<Block> {
+        self.expect("{", context)?;
+        let statements = self.parse_statements(true);
+        self.expect("}", "to close the block")?;
+        Ok(statements)
+    }
+
+    // One statement; `None` when it was skipped as unsupported
+    fn parse_statement(&mut self, in_block: bool) -> ParseResult<Option<Statement>> {
+        let keyword = self.peek().unwrap_or_default().to_string();
+        match keyword.as_str() {
+            "func" | "class" | "enum" | "interface" if in_block => {
+                self.error(format!("`{}` declarations are only allowed at the top level of a file", keyword))
+            }
+            "func" => {
+                self.pos += 1;
+                Ok(Some(Statement::Function(self.parse_function()?)))
+            }
+            "class" => self.parse_class().map(|class| Some(Statement::Class(class))),
+            "enum" => self.parse_enum().map(|declaration| Some(Statement::Enum(declaration))),
+            "interface" => self.parse_interface().map(|interface| Some(Statement::Interface(interface))),
+            "match" if self.at_match() => Ok(Some(Statement::Expression(self.parse_expression()?))),
+            "return" => {
+                self.pos += 1;
+                if self.at_line_start() || self.check("}") || self.check(";") {
+                    return Ok(Some(Statement::Return(None)));
+                }
+                // `return a, b` returns the tuple `(a, b)`
+                let value = self.parse_expression()?;
+                if !self.check(",") {
+                    return Ok(Some(Statement::Return(Some(value))));
+                }
+                let mut elements = vec![value];
+                while self.eat(",") {
+                    elements.push(self.parse_expression()?);
+                }
+                Ok(Some(Statement::Return(Some(Expression::Tuple { elements, ty: None }))))
+            }
+            "import" => {
+                self.pos += 1;
+                if self.peek().is_none() || self.at_line_start() {
+                    return self.error("expected a module name after `import`".to_string());
+                }
+                // Module paths may be nested: import symbolic.numerology
+                let mut module = self.expect_identifier("a module name after `import`")?;
+                while self.check(".") {
+                    self.pos += 1;
+                    module.push('.');
+                    module.push_str(&self.expect_identifier("a module name after `.`")?);
+                }
+                Ok(Some(Statement::Import(module)))
+            }
+            "var" if self.peek_at(1) == Some("(") => self.parse_destructure().map(Some),
+            "var" => {
+                self.pos += 1;
+                let name = self.expect_identifier("a variable name after `var`")?;
+                let ty = if self.eat(":") { Some(self.parse_type()?) } else { None };
+                if !self.eat("=") {
+                    return self.error(format!("expected `=` after `var {}`", name));
+                }
+                let value = self.parse_expression()?;
+                self.variables.insert(name.clone());
+                Ok(Some(Statement::VariableDeclaration { name, ty, value }))
+            }
+            "if" => self.parse_if().map(Some),
+            "while" => {
+                self.pos += 1;
+                let condition = self.parse_expression()?;
+                let body = self.parse_block("after the `while` condition")?;
+                Ok(Some(Statement::While { condition, body }))
+            }
+            "else" => self.error("`else` without a matching `if`".to_string()),
+            "(" => self.parse_assignment_or_call().map(Some),
+            _ if UNSUPPORTED_STATEMENTS.contains(&keyword.as_str()) => {
+                // A construct of the language the compiler does not support yet
+                let span = self.span();
+                let skipped = self.skip_statement();
+                let preview: Vec<&str> = skipped.iter().take(6).map(|t| t.as_str()).collect();
+                let ellipsis = if skipped.len() > 6 { " ..." } else { "" };
+                let message = match skipped.get(1) {
+                    Some(name) if keyword == "namespace" => {
+                        format!("namespace `{}` is not compiled yet; its contents were skipped without checking", name)
+                    }
+                    _ => format!("unsupported statement `{}{}` was ignored", preview.join(" "), ellipsis),
+                };
+                self.diagnostics.push(Diagnostic::warning(message).at(span));
+                Ok(None)
+            }
+            _ if is_identifier(&keyword) => self.parse_assignment_or_call().map(Some),
+            _ => {
+                let found = self.found();
+                self.error(format!("expected a statement, found {}", found))
+            }
+        }
+    }
+
+    // `Name`, `List[T]`, `Queue[Int]`, `(Int, String)`
+    fn parse_type(&mut self) -> ParseResult<TypeExpr> {
+        let ty = if self.eat("(") {
+            let mut elements = Vec::new();
+            loop {
+                elements.push(self.parse_type()?);
+                if !self.eat(",") {
+                    break;
+                }
+            }
+            self.expect(")", "after the tuple element types")?;
+            if elements.len() < 2 {
+                return self.error("a tuple type needs at least two element types, like `(Int, String)`".to_string());
+            }
+            TypeExpr::Tuple(elements)
+        } else {
+            let name = self.expect_qualified_name("a type")?;
+            let mut args = Vec::new();
+            if self.eat("[") {
+                loop {
+                    args.push(self.parse_type()?);
+                    if !self.eat(",") {
+                        break;
+                    }
+                }
+                self.expect("]", "after the type arguments")?;
+            }
+            TypeExpr::Named { name, args }
+        };
+        if self.eat("?") {
+            return Ok(TypeExpr::Optional(Box::new(ty)));
+        }
+        Ok(ty)
+    }
+
+    // `[T, U]` after the name of a generic function or class, with the bounds
+    // of `[T: Shape + Named]`
+    fn parse_type_params(&mut self) -> ParseResult<(Vec<String>, Vec<Bound>)> {
+        let mut type_params = Vec::new();
+        let mut bounds = Vec::new();
+        if self.eat("[") {
+            loop {
+                let param = self.expect_identifier("a type parameter name")?;
+                if self.eat(":") {
+                    loop {
+                        bounds.push((param.clone(), self.expect_qualified_name("an interface name after `:`")?));
+                        if !self.eat("+") {
+                            break;
+                        }
+                    }
+                }
+                type_params.push(param);
+                if !self.eat(",") {
+                    break;
+                }
+            }
+            self.expect("]", "after the type parameters")?;
+        }
+        Ok((type_params, bounds))
+    }
+
+    // `(name: Type, ...)`
+    fn parse_parameters(&mut self) -> ParseResult<Vec<Parameter>> {
+        self.expect("(", "before the parameters")?;
+        let params = self.parse_parameter_list(")")?;
+        self.expect(")", "after the parameters")?;
+        Ok(params)
+    }
+
+    // `name: Type` pairs up to the closing `close`, which is left for the caller
+    fn parse_parameter_list(&mut self, close: &str) -> ParseResult<Vec<Parameter>> {
+        let mut params = Vec::new();
+        while !self.check(close) {
+            let name = self.expect_identifier("a parameter name")?;
+            self.expect(":", &format!("after parameter `{}`", name))?;
+            let ty = self.parse_type()?;
+            self.variables.insert(name.clone());
+            params.push(Parameter { name, ty });
+            if !self.eat(",") {
+                break;
+            }
+        }
+        Ok(params)
+    }
+
+    // `|params| -> Type { body }`, or `|| { body }` without parameters
+    fn parse_lambda(&mut self) -> ParseResult<Expression> {
+        let params = if self.eat("||") {
+            Vec::new()
+        } else {
+            self.expect("|", "before the parameters of a lambda")?;
+            let params = self.parse_parameter_list("|")?;
+            self.expect("|", "after the parameters of a lambda")?;
+            params
+        };
+        let returns = if self.eat("->") { self.parse_type()? } else { TypeExpr::named("Void") };
+        let body = self.parse_block("before the body of the lambda")?;
+        Ok(Expression::Lambda { params, returns, body })
+    }
+
+    // `name[T](params) -> Type { body }` after `func`, or `Type.name...` for an extension method
+    fn parse_function(&mut self) -> ParseResult<Function> {
+        let receiver = if self.at_receiver() {
+            let receiver = self.parse_type()?;
+            self.expect(".", "after the type of an extension method")?;
+            Some(receiver)
+        } else {
+            None
+        };
+        let name = self.expect_identifier("a function name after `func`")?;
+        let (type_params, bounds) = self.parse_type_params()?;
+        let params = self.parse_parameters()?;
+        let returns = if self.eat("->") { self.parse_type()? } else { TypeExpr::named("Void") };
+        let body = self.parse_block(&format!("before the body of `{}`", name))?;
+        Ok(Function { name, receiver, type_params, bounds, params, returns, body })
+    }
+
+    // Whether the name after `func` is a type followed by `.`, like `Int.` or `List[T].`
+    fn at_receiver(&self) -> bool {
+        let mut offset = 1;
+        if self.peek_at(offset) == Some("[") {
+            let mut depth = 0;
+            while let Some(token) = self.peek_at(offset) {
+                match token {
+                    "[" => depth += 1,
+                    "]" => depth -= 1,
+                    "(" | "{" => return false,
+                    _ => {},
+                }
+                offset += 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+        }
+        self.peek_at(offset) == Some(".")
+    }
+
+    // `class Name[T] extends Base implements Shape { fields, init and methods }`
+    fn parse_class(&mut self) -> ParseResult<Class> {
+        self.pos += 1;
+        let name = self.expect_identifier("a class name after `class`")?;
+        let (type_params, bounds) = self.parse_type_params()?;
+        let base = if self.eat("extends") { Some(self.parse_type()?) } else { None };
+        let mut interfaces = Vec::new();
+        if self.eat("implements") {
+            loop {
+                interfaces.push(self.expect_qualified_name("an interface name after `implements`")?);
+                if !self.eat(",") {
+                    break;
+                }
+            }
+        }
+        self.expect("{", &format!("before the body of class `{}`", name))?;
+        let mut class =
+            Class { name, type_params, bounds, base, interfaces, fields: Vec::new(), init: None, methods: Vec::new() };
+        loop {
+            while self.peek().is_some_and(|text| text.starts_with('@') || text == ";") {
+                self.pos += 1;
+            }
+            if self.eat("}") {
+                return Ok(class);
+            }
+            let span = self.span();
+            if let Err(ParseError) = self.parse_class_member(&mut class, span) {
+                self.skip_statement();
+                if self.peek().is_none() {
+                    return self.error(format!("expected `}}` to close class `{}`, found end of input", class.name));
+                }
+            }
+        }
+    }
+
+    fn parse_class_member(&mut self, class: &mut Class, span: Span) -> ParseResult<()> {
+        match self.peek() {
+            Some("func") => {
+                self.pos += 1;
+                let method = self.parse_function()?;
+                if method.receiver.is_some() {
+                    let message = "extension methods can only be declared at the top level of a file";
+                    self.diagnostics.push(Diagnostic::error(message.to_string()).at(span));
+                } else {
+                    class.methods.push(Spanned::new(span, method));
+                }
+            }
+            Some("init") if self.peek_at(1) == Some("(") => {
+                self.pos += 1;
+                let params = self.parse_parameters()?;
+                let body = self.parse_block("before the body of `init`")?;
+                if class.init.is_some() {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("class `{}` already has an `init`", class.name)).at(span),
+                    );
+                }
+                let init = Function {
+                    name: "init".to_string(),
+                    receiver: None,
+                    type_params: Vec::new(),
+                    bounds: Vec::new(),
+                    params,
+                    returns: TypeExpr::named("Void"),
+                    body,
+                };
+                class.init = Some(Spanned::new(span, init));
+            }
+            Some(text) if is_identifier(text) && self.peek_at(1) == Some(":") => {
+                let name = self.advance();
+                self.pos += 1;
+                let ty = self.parse_type()?;
+                class.fields.push(Spanned::new(span, FieldDeclaration { name, ty }));
+            }
+            _ => {
+                let found = self.found();
+                return self.error(format!("expected a field, `init` or `func` in class `{}`, found {}", class.name, found));
+            }
+        }
+        Ok(())
+    }
+
+    // `interface Name { func method(params) -> Type }`, one method per line
+    fn parse_interface(&mut self) -> ParseResult<Interface> {
+        self.pos += 1;
+        let name = self.expect_identifier("an interface name after `interface`")?;
+        if self.check("[") {
+            return self.error(format!(
+                "interface `{}` cannot be generic; its methods can use `Self` for the implementing class", name
+            ));
+        }
+        self.expect("{", &format!("before the methods of interface `{}`", name))?;
+        let mut interface = Interface { name, methods: Vec::new() };
+        loop {
+            while self.peek().is_some_and(|text| text.starts_with('@') || text == ";") {
+                self.pos += 1;
+            }
+            if self.eat("}") {
+                return Ok(interface);
+            }
+            let span = self.span();
+            match self.parse_interface_method(&interface.name) {
+                Ok(method) => interface.methods.push(Spanned::new(span, method)),
+                Err(ParseError) => {
+                    self.skip_statement();
+                    if self.peek().is_none() {
+                        return self.error(format!("expected `}}` to close interface `{}`, found end of input", interface.name));
+                    }
+                }
+            }
+        }
+    }
+
+    // `func name(params) -> Type` without a body
+    fn parse_interface_method(&mut self, interface: &str) -> ParseResult<Function> {
+        if !self.eat("func") {
+            let found = self.found();
+            return self.error(format!("expected `func` in interface `{}`, found {}", interface, found));
+        }
+        let name = self.expect_identifier("a method name after `func`")?;
+        if self.check("[") {
+            return self.error(format!("method `{}` of interface `{}` cannot be generic", name, interface));
+        }
+        let params = self.parse_parameters()?;
+        let returns = if self.eat("->") { self.parse_type()? } else { TypeExpr::named("Void") };
+        if self.check("{") {
+            return self.error(format!(
+                "method `{}` of interface `{}` cannot have a body; the classes implementing it provide one", name, interface
+            ));
+        }
+        Ok(Function { name, receiver: None, type_params: Vec::new(), bounds: Vec::new(), params, returns, body: Vec::new() })
+    }
+
+    // `enum Name { Variant, Variant(Type, Type) }`; variants are separated by
+    // commas or new lines, and payload values may be labelled like `(name: String)`
+    fn parse_enum(&mut self) -> ParseResult<Enum> {
+        self.pos += 1;
+        let name = self.expect_identifier("an enum name after `enum`")?;
+        self.expect("{", &format!("before the variants of enum `{}`", name))?;
+        let mut variants = Vec::new();
+        while !self.eat("}") {
+            if self.peek().is_none() {
+                return self.error(format!("expected `}}` to close enum `{}`, found end of input", name));
+            }
+            let span = self.span();
+            let variant = self.expect_identifier(&format!("a variant of enum `{}`", name))?;
+            let mut payload = Vec::new();
+            if self.eat("(") {
+                while !self.check(")") {
+                    if self.peek().is_some_and(is_identifier) && self.peek_at(1) == Some(":") {
+                        self.pos += 2;
+                    }
+                    payload.push(self.parse_type()?);
+                    if !self.eat(",") {
+                        break;
+                    }
+                }
+                self.expect(")", &format!("after the values of variant `{}`", variant))?;
+            }
+            variants.push(Spanned::new(span, Variant { name: variant, payload }));
+            self.eat(",");
+        }
+        Ok(Enum { name, variants })
+    }
+
+    // `match` starts a match when a value follows it on the same line; otherwise
+    // it is an ordinary name, as in `var match = ...`
+    fn at_match(&self) -> bool {
+        let Some(next) = self.tokens.get(self.pos + 1) else { return false };
+        let same_line = next.span.line == self.tokens[self.pos].span.line;
+        let starts_value = is_identifier(&next.text) && !matches!(next.text.as_str(), "in" | "and" | "or")
+            || next.text.starts_with(|c: char| c.is_ascii_digit() || c == '"' || c == '\'' || c == '(' || c == '[');
+        self.check("match") && same_line && starts_value
+    }
+
+    // `match value { pattern => result, pattern => { statements } }`; arms may
+    // be separated by commas
+    fn parse_match(&mut self) -> ParseResult<Expression> {
+        self.pos += 1;
+        let value = self.parse_expression()?;
+        self.expect("{", "after the `match` value")?;
+        let mut arms = Vec::new();
+        while !self.eat("}") {
+            if self.peek().is_none() {
+                return self.error("expected `}` to close the `match`, found end of input".to_string());
+            }
+            let pattern = self.parse_pattern()?;
+            self.expect("=>", "after the `match` pattern")?;
+            let body = if self.check("{") {
+                MatchBody::Block(self.parse_block("after `=>`")?)
+            } else {
+                MatchBody::Expression(self.parse_expression()?)
+            };
+            arms.push(MatchArm { pattern, body });
+            self.eat(",");
+        }
+        if arms.is_empty() {
+            return self.error("a `match` needs at least one arm".to_string());
+        }
+        Ok(Expression::Match { value: Box::new(value), arms, ty: None, exhaustive: false })
+    }
+
+    // `_`, a literal, or `Enum.Variant` with the names its payload values are bound to
+    fn parse_pattern(&mut self) -> ParseResult<Pattern> {
+        let token = self.peek().unwrap_or_default().to_string();
+        if token == "_" {
+            self.pos += 1;
+            return Ok(Pattern::Wildcard);
+        }
+        let literal = matches!(token.as_str(), "true" | "false" | "-")
+            || token.starts_with(|c: char| c.is_ascii_digit() || c == '"' || c == '\'');
+        if literal {
+            return Ok(Pattern::Literal(self.parse_unary()?));
+        }
+        if !is_identifier(&token) || self.peek_at(1) != Some(".") {
+            let found = self.found();
+            return self.error(format!("expected a pattern like `Gate.H(qubit)`, a literal or `_`, found {}", found));
+        }
+        let enum_name = self.expect_qualified_name("an enum name")?;
+        self.expect(".", &format!("after `{}`", enum_name))?;
+        let variant = self.expect_identifier(&format!("a variant of `{}` after `.`", enum_name))?;
+        let mut bindings = Vec::new();
+        if self.eat("(") {
+            while !self.check(")") {
+                bindings.push(self.expect_identifier("a name for the value")?);
+                if !self.eat(",") {
+                    break;
+                }
+            }
+            self.expect(")", "after the names of the values")?;
+        }
+        Ok(Pattern::Variant { enum_name, variant, bindings })
+    }
+
+    // `var (a, b) = value`
+    fn parse_destructure(&mut self) -> ParseResult<Statement> {
+        self.pos += 2;
+        let mut names = Vec::new();
+        loop {
+            names.push(self.expect_identifier("a variable name or `_` in `var (...)`")?);
+            if !self.eat(",") {
+                break;
+            }
+        }
+        self.expect(")", "after the variable names")?;
+        if names.len() < 2 {
+            return self.error("`var (...)` needs at least two names; use `var name = value` for one".to_string());
+        }
+        if !self.eat("=") {
+            return self.error(format!("expected `=` after `var ({})`", names.join(", ")));
+        }
+        let value = self.parse_expression()?;
+        self.variables.extend(names.iter().filter(|name| *name != "_").cloned());
+        Ok(Statement::Destructure { names, value })
+    }
+
+    // `target = value`, `target += value` or a call evaluated for its effect
+    fn parse_assignment_or_call(&mut self) -> ParseResult<Statement> {
+        let span = self.span();
+        let expression = self.parse_expression()?;
+        let operator = match self.peek() {
+            Some("=") => None,
+            Some(token) if BinaryOperator::from_compound_assignment(token).is_some() => {
+                BinaryOperator::from_compound_assignment(token)
+            }
+            _ if is_call(&expression) => return Ok(Statement::Expression(expression)),
+            _ => {
+                let message = match (&expression, self.peek()) {
+                    // A misspelled keyword, like `vra y = 2`
+                    (Expression::Variable(name), Some(next)) if is_identifier(next) && !self.at_line_start() => {
+                        format!("unknown statement `{}`; only calls and assignments can be used as statements", name)
+                    }
+                    _ => "only calls and assignments can be used as statements".to_string(),
+                };
+                self.diagnostics.push(Diagnostic::error(message).at(span));
+                return Err(ParseError);
+            }
+        };
+        let target = match expression {
+            Expression::Tuple { elements, .. } => {
+                if let Some(operator) = operator {
+                    self.diagnostics.push(Diagnostic::error(format!(
+                        "`{}=` cannot assign to several targets at once", operator.symbol()
+                    )).at(span));
+                    return Err(ParseError);
+                }
+                let targets = elements
+                    .into_iter()
+                    .map(|element| match element {
+                        Expression::Variable(name) if name == "_" => Ok(AssignmentTarget::Ignored),
+                        element => self.assignment_target(element, span),
+                    })
+                    .collect::<ParseResult<_>>()?;
+                AssignmentTarget::Tuple(targets)
+            }
+            expression => self.assignment_target(expression, span)?,
+        };
+        self.pos += 1;
+        let value = self.parse_expression()?;
+        Ok(Statement::Assignment { target, operator, value })
+    }
+
+    fn assignment_target(&mut self, expression: Expression, span: Span) -> ParseResult<AssignmentTarget> {
+        match expression {
+            Expression::Variable(name) => Ok(AssignmentTarget::Variable(name)),
+            Expression::Field { object, name } => Ok(AssignmentTarget::Field { object: *object, name }),
+            Expression::ArrayAccess { array, index } => Ok(AssignmentTarget::Index { array: *array, index: *index }),
+            Expression::ModuleMember { module, name } => {
+                self.diagnostics.push(Diagnostic::error(format!(
+                    "cannot assign to `{}.{}`; module variables can only be assigned by their own module", module, name
+                )).at(span));
+                Err(ParseError)
+            }
+            Expression::TupleElement { .. } => {
+                self.diagnostics.push(Diagnostic::error(
+                    "cannot assign to an element of a tuple; assign a new tuple to the variable instead".to_string()
+                ).at(span));
+                Err(ParseError)
+            }
+            _ => {
+                self.diagnostics.push(Diagnostic::error(
+                    "cannot assign to this expression; assign to a variable, a field or a list element".to_string()
+                ).at(span));
+                Err(ParseError)
+            }
+        }
+    }
+
+    // `if condition { ... }` or `if let name = value { ... }`, with optional
+    // `else { ... }` or `else if ...`
+    fn parse_if(&mut self) -> ParseResult<Statement> {
+        self.pos += 1;
+        let binding = if self.eat("let") {
+            let name = self.expect_identifier("a variable name after `if let`")?;
+            if !self.eat("=") {
+                return self.error(format!("expected `=` after `if let {}`", name));
+            }
+            self.variables.insert(name.clone());
+            Some(name)
+        } else {
+            None
+        };
+        let condition = self.parse_expression()?;
+        let then_branch = self.parse_block("after the `if` condition")?;
+        let else_branch = if self.eat("else") {
+            if self.check("if") {
+                let span = self.span();
+                Some(vec![Spanned::new(span, self.parse_if()?)])
+            } else {
+                Some(self.parse_block("after `else`")?)
+            }
+        } else {
+            None
+        };
+        match binding {
+            Some(name) => Ok(Statement::IfLet { name, value: condition, then_branch, else_branch }),
+            None => Ok(Statement::If { condition, then_branch, else_branch }),
+        }
+    }
+
+    fn parse_expression(&mut self) -> ParseResult<Expression> {
+        self.parse_binary(1)
+    }
+
+    // Precedence climbing over binary operators that bind at least as tight as
+    // `min_precedence`; all binary operators are left-associative
+    fn parse_binary(&mut self, min_precedence: u8) -> ParseResult<Expression> {
+        let mut left = if min_precedence <= UnaryOperator::NOT_PRECEDENCE && self.eat("not") {
+            let operand = self.parse_binary(UnaryOperator::NOT_PRECEDENCE)?;
+            Expression::UnaryOp { operator: UnaryOperator::Not, operand: Box::new(operand) }
+        } else {
+            self.parse_unary()?
+        };
+        while let Some(operator) = self
+            .peek()
+            .and_then(BinaryOperator::from_token)
+            .filter(|operator| operator.precedence() >= min_precedence)
+        {
+            self.pos += 1;
+            let right = self.parse_binary(operator.precedence() + 1)?;
+            left = Expression::BinaryOp { left: Box::new(left), operator, right: Box::new(right) };
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> ParseResult<Expression> {
+        let operator = match self.peek() {
+            Some("-") => Some(UnaryOperator::Neg),
+            Some("!") => Some(UnaryOperator::Not),
+            _ => None,
+        };
+        if let Some(operator) = operator {
+            self.pos += 1;
+            let operand = self.parse_unary()?;
+            return Ok(Expression::UnaryOp { operator, operand: Box::new(operand) });
+        }
+        let primary = self.parse_primary()?;
+        self.parse_postfix(primary)
+    }
+
+    // Member access, method calls and indexing after a primary expression
+    fn parse_postfix(&mut self, mut expression: Expression) -> ParseResult<Expression> {
+        loop {
+            if self.eat(".") {
+                expression = match self.peek() {
+                    Some(token) if token.starts_with(|c: char| c.is_ascii_digit()) => self.parse_tuple_element(expression)?,
+                    _ => self.parse_member(expression)?,
+                };
+            } else if self.eat("?.") {
+                let member = self.parse_member(Expression::Unwrapped)?;
+                expression = Expression::OptionalChain { object: Box::new(expression), member: Box::new(member), void: false };
+            } else if self.check("[") && !self.at_line_start() {
+                self.pos += 1;
+                let index = self.parse_expression()?;
+                self.expect("]", "after the index")?;
+                expression = Expression::ArrayAccess { array: Box::new(expression), index: Box::new(index) };
+            } else if self.check("(") && !self.at_line_start() {
+                let args = self.parse_arguments()?;
+                expression = Expression::ValueCall { callee: Box::new(expression), args };
+            } else {
+                return Ok(expression);
+            }
+        }
+    }
+
+    // `0` after `.`; the lexer reads `pair.0.1` as `pair`, `.`, `0.1`
+    fn parse_tuple_element(&mut self, mut tuple: Expression) -> ParseResult<Expression> {
+        let token = self.peek().unwrap_or_default().to_string();
+        for index in token.split('.') {
+            let Ok(index) = index.parse::<usize>() else {
+                return self.error(format!("invalid tuple element `{}`; elements are numbered like `pair.0`", token));
+            };
+            tuple = Expression::TupleElement { tuple: Box::new(tuple), index };
+        }
+        self.pos += 1;
+        Ok(tuple)
+    }
+
+    // `name` or `name(args)` after `.` or `?.`
+    fn parse_member(&mut self, object: Expression) -> ParseResult<Expression> {
+        let name = self.expect_identifier("a field or method name after `.`")?;
+        if self.check("(") {
+            let args = self.parse_arguments()?;
+            return Ok(Expression::MethodCall { object: Box::new(object), method: name, args });
+        }
+        Ok(Expression::Field { object: Box::new(object), name })
+    }
+
+    // `(a, b, c)`
+    fn parse_arguments(&mut self) -> ParseResult<Vec<Expression>> {
+        self.expect("(", "before the arguments")?;
+        let mut args = Vec::new();
+        while !self.check(")") {
+            args.push(self.parse_expression()?);
+            if !self.eat(",") {
+                break;
+            }
+        }
+        self.expect(")", "after the arguments")?;
+        Ok(args)
+    }
+
+    // Whether `name` refers to a module rather than a variable
+    fn is_module_reference(&self, name: &str) -> bool {
+        !self.variables.contains(name)
+            && (name.contains('.') || self.modules.contains(name) || STANDARD_LIBRARY_ROOTS.contains(&name))
+    }
+
+    fn parse_primary(&mut self) -> ParseResult<Expression> {
+        let Some(token) = self.peek().map(str::to_string) else {
+            return self.error("expected an expression, found end of input".to_string());
+        };
+
+        if token.starts_with('"') || token.starts_with('\'') {
+            // Unterminated strings were reported before parsing started
+            self.pos += 1;
+            let quote = token.chars().next().unwrap_or('"');
+            let contents = token[1..].strip_suffix(quote).unwrap_or(&token[1..]);
+            return Ok(Expression::String(contents.to_string()));
+        }
+
+        if token.starts_with(|c: char| c.is_ascii_digit()) {
+            self.pos += 1;
+            if token.contains('.') {
+                if let Ok(value) = token.parse::<f64>() {
+                    return Ok(Expression::Float(value));
+                }
+            } else if let Ok(value) = token.parse::<i64>() {
+                return Ok(Expression::Integer(value));
+            }
+            self.pos -= 1;
+            return self.error(format!("invalid number literal `{}`", token));
+        }
+
+        match token.as_str() {
+            "true" | "false" => {
+                self.pos += 1;
+                return Ok(Expression::Boolean(token == "true"));
+            }
+            "none" | "null" => {
+                self.pos += 1;
+                return Ok(Expression::None);
+            }
+            "(" => {
+                self.pos += 1;
+                let expression = self.parse_expression()?;
+                if !self.check(",") {
+                    self.expect(")", "to close the parenthesized expression")?;
+                    return Ok(expression);
+                }
+                let mut elements = vec![expression];
+                while self.eat(",") {
+                    elements.push(self.parse_expression()?);
+                }
+                self.expect(")", "to close the tuple")?;
+                return Ok(Expression::Tuple { elements, ty: None });
+            }
+            "[" => {
+                self.pos += 1;
+                let mut elements = Vec::new();
+                while !self.check("]") {
+                    elements.push(self.parse_expression()?);
+                    if !self.eat(",") {
+                        break;
+                    }
+                }
+                self.expect("]", "to close the list literal")?;
+                return Ok(Expression::ArrayLiteral { elements, element_type: None });
+            }
+            "this" => {
+                self.pos += 1;
+                return Ok(Expression::Variable(token));
+            }
+            "|" | "||" => return self.parse_lambda(),
+            "match" if self.at_match() => return self.parse_match(),
+            "super" => {
+                self.pos += 1;
+                let method = if self.eat(".") {
+                    self.expect_identifier("a method name after `super.`")?
+                } else {
+                    "init".to_string()
+                };
+                let args = self.parse_arguments()?;
+                return Ok(Expression::SuperCall { method, args });
+            }
+            _ => {}
+        }
+
+        if !is_identifier(&token) || KEYWORDS.contains(&token.as_str()) {
+            return self.error(format!("expected an expression, found `{}`", token));
+        }
+        self.pos += 1;
+
+        if self.is_module_reference(&token) && self.check(".") {
+            self.pos += 1;
+            let name = self.expect_identifier("a module member after `.`")?;
+            if self.check("(") {
+                let args = self.parse_arguments()?;
+                return Ok(Expression::FunctionCall { module: token, function: name, args });
+            }
+            return Ok(Expression::ModuleMember { module: token, name });
+        }
+        if self.check("(") && !self.at_line_start() {
+            let args = self.parse_arguments()?;
+            return Ok(Expression::Call { function: token, type_args: Vec::new(), args });
+        }
+        if self.check("[") && !self.at_line_start() {
+            if let Some(type_args) = self.try_parse_type_arguments() {
+                let args = self.parse_arguments()?;
+                return Ok(Expression::Call { function: token, type_args, args });
+            }
+        }
+        Ok(Expression::Variable(token))
+    }
+
+    // `[Int, String]` directly followed by `(`, as in `first[Int](items)`.
+    // Anything else is left alone, to be parsed as an index.
+    fn try_parse_type_arguments(&mut self) -> Option<Vec<TypeExpr>> {
+        let start = self.pos;
+        let diagnostics = self.diagnostics.len();
+        self.pos += 1;
+        let mut type_args = Vec::new();
+        let parsed = loop {
+            let starts_type = self.peek().is_some_and(|text| {
+                text.starts_with(|c: char| c.is_ascii_uppercase()) && !KEYWORDS.contains(&text)
+            });
+            if !starts_type {
+                break false;
+            }
+            match self.parse_type() {
+                Ok(ty) => type_args.push(ty),
+                Err(ParseError) => break false,
+            }
+            if !self.eat(",") {
+                break self.eat("]") && self.check("(");
+            }
+        };
+        if parsed {
+            return Some(type_args);
+        }
+        self.pos = start;
+        self.diagnostics.truncate(diagnostics);
+        None
+    }
+}
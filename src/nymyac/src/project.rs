@@ -0,0 +1,387 @@
+// NymyaLang projects: the `nymya.toml` manifest, module discovery and
+// dependencies on local library projects.
+//
+// A project is a directory with a manifest and one or more source directories:
+//
+//   nymya.toml
+//   src/main.nym              entry point of an executable
+//   src/util.nym              module `util`
+//   src/geometry/shapes.nym   module `geometry.shapes`
+//
+// Modules of a library project are prefixed with the library name, so
+// `src/circle.nym` in library `shapes` is imported as `shapes.circle`, both
+// inside the library and in projects depending on it. The entry file of a
+// library (default `src/lib.nym`) is the module named after the library itself.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::runtime;
+
+pub const MANIFEST_FILE: &str = "nymya.toml";
+
+/// Contents of a `nymya.toml`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub project: ProjectSection,
+    #[serde(default)]
+    pub build: BuildSection,
+    /// Local library projects, keyed by library name
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Dependency>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ProjectSection {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub kind: ProjectKind,
+    /// Entry file relative to the project root (`src/main.nym` or `src/lib.nym` by default)
+    pub entry: Option<String>,
+    /// Directories searched for modules, relative to the project root
+    #[serde(default = "default_source_dirs")]
+    pub source_dirs: Vec<String>,
+}
+
+fn default_source_dirs() -> Vec<String> {
+    vec!["src".to_string()]
+}
+
+/// What `nymyac build` produces for a project
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectKind {
+    #[default]
+    Executable,
+    /// A static library (`lib<name>.a`) plus a C++ header declaring its modules
+    Library,
+}
+
+/// C++ settings applied when building the project
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BuildSection {
+    /// Extra flags passed to the C++ compiler
+    #[serde(default)]
+    pub cxxflags: Vec<String>,
+    /// Library search directories (-L), relative to the project root
+    #[serde(default)]
+    pub library_paths: Vec<String>,
+    /// Libraries to link (-l)
+    #[serde(default)]
+    pub libraries: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dependency {
+    /// Directory of the library project, relative to the depending project
+    pub path: String,
+}
+
+/// A source file of a project and the module path it is imported as
+#[derive(Debug)]
+pub struct Module {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// A loaded project together with all of its (transitive) dependencies
+#[derive(Debug)]
+pub struct Project {
+    pub root: PathBuf,
+    pub manifest: Manifest,
+    /// Entry file of an executable; a library's entry is one of `modules`
+    pub entry: Option<PathBuf>,
+    /// Importable modules of the project and its dependencies, sorted by name
+    pub modules: Vec<Module>,
+    /// Build settings of the project and its dependencies, with paths resolved
+    pub cxxflags: Vec<String>,
+    pub library_paths: Vec<PathBuf>,
+    pub libraries: Vec<String>,
+    /// Root directory of the project and of each dependency, by project name
+    pub roots: BTreeMap<String, PathBuf>,
+}
+
+impl Project {
+    pub fn name(&self) -> &str {
+        &self.manifest.project.name
+    }
+
+    pub fn kind(&self) -> ProjectKind {
+        self.manifest.project.kind
+    }
+
+    /// Directory for build outputs
+    pub fn target_dir(&self) -> PathBuf {
+        self.root.join("target")
+    }
+
+    /// A source path that does not depend on where the projects are checked
+    /// out: relative to the root of the project containing it, prefixed with
+    /// that project's name (`shapes/src/circle.nym`)
+    pub fn portable_path(&self, path: &Path) -> String {
+        self.roots.iter()
+            .filter_map(|(name, root)| path.strip_prefix(root).ok().map(|relative| (name, root, relative)))
+            .max_by_key(|(_, root, _)| root.components().count())
+            .map(|(name, _, relative)| format!("{}/{}", name, relative.display()))
+            .unwrap_or_else(|| path.file_name().unwrap_or_default().to_string_lossy().into_owned())
+    }
+}
+
+/// Path in messages and `#line` directives, relative to the current directory when possible
+pub fn display_path(path: &Path) -> String {
+    let relative = std::env::current_dir().ok()
+        .and_then(|dir| path.strip_prefix(dir).ok().map(Path::to_path_buf));
+    match relative.unwrap_or_else(|| path.to_path_buf()).display().to_string() {
+        empty if empty.is_empty() => ".".to_string(),
+        path => path,
+    }
+}
+
+/// Find the manifest of the project containing `start`, looking upwards like cargo does
+pub fn find_manifest(start: &Path) -> Option<PathBuf> {
+    start.ancestors()
+        .map(|dir| dir.join(MANIFEST_FILE))
+        .find(|manifest| manifest.is_file())
+}
+
+/// Load a project from its manifest, discovering the modules of the project and its dependencies
+pub fn load(manifest_path: &Path) -> Result<Project, String> {
+    let manifest = read_manifest(manifest_path)?;
+    let root = manifest_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+
+    let mut contents = Contents::default();
+    let mut loaded = BTreeMap::new();
+    loaded.insert(manifest.project.name.clone(), root.clone());
+    let entry = add_project(&manifest, &root, &mut contents, &mut loaded)?;
+
+    let mut seen = BTreeSet::new();
+    for module in &contents.modules {
+        if !seen.insert(module.name.as_str()) {
+            return Err(format!("module `{}` is defined more than once ({})", module.name, module.path.display()));
+        }
+    }
+    contents.modules.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(Project {
+        root,
+        entry,
+        modules: contents.modules,
+        cxxflags: contents.cxxflags,
+        library_paths: contents.library_paths,
+        libraries: contents.libraries,
+        roots: loaded,
+        manifest,
+    })
+}
+
+fn read_manifest(path: &Path) -> Result<Manifest, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let manifest: Manifest = toml::from_str(&text)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    if !is_identifier(&manifest.project.name) {
+        return Err(format!(
+            "{}: project name `{}` must start with a letter or `_` and contain only letters, digits and `_`",
+            path.display(), manifest.project.name
+        ));
+    }
+    Ok(manifest)
+}
+
+// Modules and build settings collected from a project and its dependencies
+#[derive(Default)]
+struct Contents {
+    modules: Vec<Module>,
+    cxxflags: Vec<String>,
+    library_paths: Vec<PathBuf>,
+    libraries: Vec<String>,
+}
+
+// Add the modules and build settings of the project at `root` and of its
+// dependencies; returns the entry file if the project is an executable
+fn add_project(
+    manifest: &Manifest,
+    root: &Path,
+    contents: &mut Contents,
+    loaded: &mut BTreeMap<String, PathBuf>,
+) -> Result<Option<PathBuf>, String> {
+    let section = &manifest.project;
+
+    // Library modules live under the library name; executables use bare names
+    let prefix = match section.kind {
+        ProjectKind::Library => Some(section.name.as_str()),
+        ProjectKind::Executable => None,
+    };
+    let default_entry = match section.kind {
+        ProjectKind::Library => "src/lib.nym",
+        ProjectKind::Executable => "src/main.nym",
+    };
+    let entry = root.join(section.entry.as_deref().unwrap_or(default_entry));
+    let mut modules = Vec::new();
+
+    match section.kind {
+        ProjectKind::Executable => {
+            if !entry.is_file() {
+                return Err(format!("entry file {} of project `{}` does not exist", entry.display(), section.name));
+            }
+        },
+        ProjectKind::Library => {
+            if entry.is_file() {
+                modules.push(Module { name: section.name.clone(), path: entry.clone() });
+            } else if section.entry.is_some() {
+                return Err(format!("entry file {} of library `{}` does not exist", entry.display(), section.name));
+            }
+        },
+    }
+
+    for dir in &section.source_dirs {
+        let dir = root.join(dir);
+        let mut files = Vec::new();
+        collect_sources(&dir, &mut files)
+            .map_err(|e| format!("Could not read source directory {}: {}", dir.display(), e))?;
+        for file in files {
+            if same_file(&file, &entry) {
+                continue;
+            }
+            let name = module_name(&dir, &file, prefix)?;
+            modules.push(Module { name, path: file });
+        }
+    }
+
+    for module in &modules {
+        let root_segment = module.name.split('.').next().unwrap_or_default();
+        if runtime::MODULES.iter().any(|m| m.name.split('.').next() == Some(root_segment)) {
+            return Err(format!(
+                "module `{}` ({}) clashes with the runtime module `{}`; rename the file or directory",
+                module.name, module.path.display(), root_segment
+            ));
+        }
+    }
+    contents.modules.extend(modules);
+    contents.library_paths.extend(manifest.build.library_paths.iter().map(|path| root.join(path)));
+    contents.libraries.extend(manifest.build.libraries.iter().cloned());
+
+    for (name, dependency) in &manifest.dependencies {
+        let dependency_root = root.join(&dependency.path);
+        if let Some(previous) = loaded.get(name) {
+            if !same_file(previous, &dependency_root) {
+                return Err(format!(
+                    "dependency `{}` refers to both {} and {}",
+                    name, previous.display(), dependency_root.display()
+                ));
+            }
+            continue;
+        }
+        let dependency_manifest_path = dependency_root.join(MANIFEST_FILE);
+        let dependency_manifest = read_manifest(&dependency_manifest_path)?;
+        if dependency_manifest.project.name != *name {
+            return Err(format!(
+                "dependency `{}` points at project `{}` ({})",
+                name, dependency_manifest.project.name, dependency_manifest_path.display()
+            ));
+        }
+        if dependency_manifest.project.kind != ProjectKind::Library {
+            return Err(format!("dependency `{}` is not a library project", name));
+        }
+        loaded.insert(name.clone(), dependency_root.clone());
+        add_project(&dependency_manifest, &dependency_root, contents, loaded)?;
+    }
+
+    // A project's own C++ flags come after those of its dependencies so they take precedence
+    contents.cxxflags.extend(manifest.build.cxxflags.iter().cloned());
+
+    Ok(match section.kind {
+        ProjectKind::Executable => Some(entry),
+        ProjectKind::Library => None,
+    })
+}
+
+// All .nym files below `dir`, in a stable order
+fn collect_sources(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_sources(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "nym") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// `src/geometry/shapes.nym` in source dir `src` is module `geometry.shapes`
+fn module_name(source_dir: &Path, file: &Path, prefix: Option<&str>) -> Result<String, String> {
+    let relative = file.strip_prefix(source_dir).unwrap_or(file).with_extension("");
+    let mut segments: Vec<String> = prefix.map(str::to_string).into_iter().collect();
+    for component in relative.components() {
+        let segment = component.as_os_str().to_string_lossy().into_owned();
+        if !is_identifier(&segment) {
+            return Err(format!(
+                "{}: `{}` is not a valid module name; use letters, digits and `_`",
+                file.display(), segment
+            ));
+        }
+        segments.push(segment);
+    }
+    Ok(segments.join("."))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Order modules so that every module comes after the modules it imports.
+/// `imports` maps each module to the project modules it imports.
+pub fn initialization_order(imports: &BTreeMap<String, Vec<String>>) -> Result<Vec<String>, String> {
+    fn visit(
+        module: &str,
+        imports: &BTreeMap<String, Vec<String>>,
+        stack: &mut Vec<String>,
+        done: &mut BTreeSet<String>,
+        order: &mut Vec<String>,
+    ) -> Result<(), String> {
+        if done.contains(module) {
+            return Ok(());
+        }
+        if let Some(start) = stack.iter().position(|m| m == module) {
+            let mut cycle = stack[start..].to_vec();
+            cycle.push(module.to_string());
+            return Err(format!("import cycle between modules: {}", cycle.join(" -> ")));
+        }
+        stack.push(module.to_string());
+        for imported in imports.get(module).into_iter().flatten() {
+            visit(imported, imports, stack, done, order)?;
+        }
+        stack.pop();
+        done.insert(module.to_string());
+        order.push(module.to_string());
+        Ok(())
+    }
+
+    let mut order = Vec::new();
+    let mut done = BTreeSet::new();
+    for module in imports.keys() {
+        visit(module, imports, &mut Vec::new(), &mut done, &mut order)?;
+    }
+    Ok(order)
+}
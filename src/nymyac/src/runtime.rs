@@ -0,0 +1,198 @@
+// The NymyaLang C++ runtime library (libnymya).
+//
+// The runtime headers and sources live in `runtime/` next to this crate and
+// are embedded into the compiler binary, so an installed nymyac is always
+// self-contained. On first use they are written to a versioned cache
+// directory and compiled once into `libnymya.a` per toolchain configuration:
+//
+//   <cache>/runtime/<version>/include/nymya/*.hpp
+//   <cache>/runtime/<version>/src/*.cpp
+//   <cache>/runtime/<version>/lib/<toolchain-hash>/libnymya.a
+//
+// The cache root is $NYMYA_CACHE_DIR, then $XDG_CACHE_HOME/nymya, then
+// $HOME/.cache/nymya, then a directory under the system temp dir.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::incremental::{fnv1a, write_if_changed, FNV_OFFSET};
+use crate::toolchain::{CompileEvent, OutputKind, Toolchain};
+
+// A runtime file embedded in the compiler, with its path relative to runtime/
+struct EmbeddedFile {
+    path: &'static str,
+    contents: &'static str,
+}
+
+macro_rules! embed {
+    ($path:literal) => {
+        EmbeddedFile { path: $path, contents: include_str!(concat!("../runtime/", $path)) }
+    };
+}
+
+const HEADERS: &[EmbeddedFile] = &[
+    embed!("include/nymya/version.hpp"),
+    embed!("include/nymya/math.hpp"),
+    embed!("include/nymya/crystal.hpp"),
+    embed!("include/nymya/crystal/file.hpp"),
+    embed!("include/nymya/symbolic/numerology.hpp"),
+];
+
+const SOURCES: &[EmbeddedFile] = &[
+    embed!("src/math.cpp"),
+    embed!("src/crystal.cpp"),
+    embed!("src/crystal_file.cpp"),
+    embed!("src/symbolic_numerology.cpp"),
+];
+
+// Fail the Rust build if runtime/include/nymya/version.hpp was not bumped with Cargo.toml
+const _: () = assert!(
+    contains(HEADERS[0].contents.as_bytes(), concat!("\"", env!("CARGO_PKG_VERSION"), "\"").as_bytes()),
+    "runtime/include/nymya/version.hpp must match the crate version"
+);
+
+const fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    let mut start = 0;
+    while start + needle.len() <= haystack.len() {
+        let mut offset = 0;
+        while offset < needle.len() && haystack[start + offset] == needle[offset] {
+            offset += 1;
+        }
+        if offset == needle.len() {
+            return true;
+        }
+        start += 1;
+    }
+    false
+}
+
+/// A NymyaLang module implemented by the runtime, and the header declaring it.
+/// Namespace-only parents (like `symbolic`) have no header of their own.
+pub struct RuntimeModule {
+    pub name: &'static str,
+    pub header: Option<&'static str>,
+}
+
+pub const MODULES: &[RuntimeModule] = &[
+    RuntimeModule { name: "math", header: Some("nymya/math.hpp") },
+    RuntimeModule { name: "crystal", header: Some("nymya/crystal.hpp") },
+    RuntimeModule { name: "crystal.file", header: Some("nymya/crystal/file.hpp") },
+    RuntimeModule { name: "symbolic", header: None },
+    RuntimeModule { name: "symbolic.numerology", header: Some("nymya/symbolic/numerology.hpp") },
+];
+
+pub fn is_runtime_module(name: &str) -> bool {
+    MODULES.iter().any(|module| module.name == name)
+}
+
+/// Headers declaring the given (resolved, actually used) runtime modules, in
+/// `MODULES` order so the output does not depend on the order of use
+pub fn headers_for_modules(modules: &BTreeSet<String>) -> Vec<&'static str> {
+    MODULES
+        .iter()
+        .filter(|module| modules.contains(module.name))
+        .filter_map(|module| module.header)
+        .collect()
+}
+
+/// An installed and built runtime, ready to compile and link against
+pub struct Runtime {
+    pub include_dir: PathBuf,
+    pub library: PathBuf,
+}
+
+fn cache_root() -> PathBuf {
+    if let Some(dir) = std::env::var_os("NYMYA_CACHE_DIR") {
+        return PathBuf::from(dir);
+    }
+    if let Some(dir) = std::env::var_os("XDG_CACHE_HOME") {
+        return PathBuf::from(dir).join("nymya");
+    }
+    if let Some(home) = std::env::var_os("HOME") {
+        return PathBuf::from(home).join(".cache").join("nymya");
+    }
+    std::env::temp_dir().join("nymya-cache")
+}
+
+/// Where the runtime sources of this nymyac version are installed
+pub fn runtime_dir() -> PathBuf {
+    cache_root().join("runtime").join(env!("CARGO_PKG_VERSION"))
+}
+
+/// Write the runtime headers and sources to the cache; returns the include directory
+pub fn install_sources() -> Result<PathBuf, String> {
+    let dir = runtime_dir();
+    for file in HEADERS.iter().chain(SOURCES) {
+        write_if_changed(&dir.join(file.path), file.contents)
+            .map_err(|e| format!("Could not install runtime file {}: {}", file.path, e))?;
+    }
+    Ok(dir.join("include"))
+}
+
+// Everything that affects the compiled library: sources and the compile command
+fn library_key(toolchain: &Toolchain) -> String {
+    let mut hash = FNV_OFFSET;
+    for file in HEADERS.iter().chain(SOURCES) {
+        hash = fnv1a(file.path.as_bytes(), hash);
+        hash = fnv1a(file.contents.as_bytes(), hash);
+    }
+    let probe = toolchain.command_line(Path::new("runtime.cpp"), Path::new("runtime.o"), OutputKind::Object);
+    hash = fnv1a(probe.as_bytes(), hash);
+    format!("{:016x}", hash)
+}
+
+/// Install the runtime and build `libnymya.a` for this toolchain unless it is already cached
+pub fn prepare(toolchain: &Toolchain) -> Result<Runtime, String> {
+    let include_dir = install_sources()?;
+    let lib_dir = runtime_dir().join("lib").join(library_key(toolchain));
+    let library = lib_dir.join("libnymya.a");
+
+    if !library.exists() {
+        build_library(toolchain, &include_dir, &lib_dir, &library)?;
+    }
+
+    Ok(Runtime { include_dir, library })
+}
+
+fn build_library(toolchain: &Toolchain, include_dir: &Path, lib_dir: &Path, library: &Path) -> Result<(), String> {
+    // Build in a private directory and rename into place, so a concurrent
+    // nymyac never links against a half-written archive
+    let work_dir = lib_dir.join(format!("build-{}", std::process::id()));
+    fs::create_dir_all(&work_dir)
+        .map_err(|e| format!("Could not create runtime build directory {}: {}", work_dir.display(), e))?;
+
+    let result = compile_and_archive(toolchain, include_dir, &work_dir, library);
+    let _ = fs::remove_dir_all(&work_dir);
+    result
+}
+
+fn compile_and_archive(toolchain: &Toolchain, include_dir: &Path, work_dir: &Path, library: &Path) -> Result<(), String> {
+    let mut toolchain = toolchain.clone();
+    toolchain.include_dirs = vec![include_dir.to_path_buf()];
+    let source_dir = runtime_dir();
+
+    let units: Vec<(PathBuf, PathBuf)> = SOURCES
+        .iter()
+        .map(|source| {
+            let stem = Path::new(source.path).file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            (source_dir.join(source.path), work_dir.join(format!("{}.o", stem)))
+        })
+        .collect();
+    let mut errors = String::new();
+    let success = toolchain.compile_objects(&units, |event| {
+        if let CompileEvent::Finished { index, success: false, output } = event {
+            errors.push_str(&format!("Building the NymyaLang runtime ({}) failed:\n{}", SOURCES[index].path, output));
+        }
+    });
+    if !success {
+        return Err(errors);
+    }
+    let objects: Vec<PathBuf> = units.into_iter().map(|(_, object)| object).collect();
+
+    let archive = work_dir.join("libnymya.a");
+    toolchain.archive(&objects, &archive)?;
+
+    fs::rename(&archive, library)
+        .map_err(|e| format!("Could not install {}: {}", library.display(), e))
+}
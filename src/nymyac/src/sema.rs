@@ -0,0 +1,2735 @@
+// Semantic analysis for NymyaLang: name resolution and type checking.
+//
+// Runs over the parsed statements before any C++ is generated, so that
+// `nymyac check` can report problems without a C++ toolchain and builds
+// fail with NymyaLang-level messages instead of g++ errors.
+//
+// The checker also records what it resolved in the syntax tree for code
+// generation: calls of a class become `Construct`, method calls on lists
+// become `ArrayMethodCall`, calls of extension methods become `ExtensionCall`,
+// and inferred type arguments and list element types are filled in.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+use crate::diagnostics::Diagnostic;
+use crate::runtime;
+use crate::ast::{
+    AssignmentTarget, BinaryOperator, Bound, Class, Enum, Expression, Function, Interface, MatchArm, MatchBody,
+    Parameter, Pattern, Span, Spanned, Statement, TypeExpr, UnaryOperator, OPERATOR_METHODS,
+};
+
+/// Types known to the checker. `Unknown` is used wherever the front end
+/// cannot tell (e.g. method results on runtime objects) and never produces errors.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    Float,
+    String,
+    Bool,
+    List(Box<Type>),
+    Void,
+    Stream,
+    Meaning,
+    /// An instance of a class declared in the program, with its type arguments
+    Class(String, Vec<Type>),
+    /// A value of an enum declared in the program
+    Enum(String),
+    /// A type parameter of the generic function or class being checked
+    Param(String),
+    /// `T?`; `none` is an optional of unknown type
+    Optional(Box<Type>),
+    /// `Func[A, B, R]`: a function value taking `A` and `B` and returning `R`
+    Function(Vec<Type>, Box<Type>),
+    /// `(A, B)`: a tuple of two or more values
+    Tuple(Vec<Type>),
+    Unknown,
+}
+
+impl Type {
+    fn is_numeric(&self) -> bool {
+        matches!(self, Type::Int | Type::Float)
+    }
+
+    /// The type as written in NymyaLang source; `None` when it cannot be written
+    pub fn to_type_expr(&self) -> Option<TypeExpr> {
+        let name = match self {
+            Type::Int => "Int",
+            Type::Float => "Float",
+            Type::String => "String",
+            Type::Bool => "Bool",
+            Type::Void => "Void",
+            Type::List(element) => {
+                return Some(TypeExpr::Named { name: "List".to_string(), args: vec![element.to_type_expr()?] });
+            },
+            Type::Class(name, args) => {
+                let args = args.iter().map(Type::to_type_expr).collect::<Option<_>>()?;
+                return Some(TypeExpr::Named { name: name.clone(), args });
+            },
+            Type::Param(name) | Type::Enum(name) => name,
+            Type::Optional(inner) => return Some(TypeExpr::Optional(Box::new(inner.to_type_expr()?))),
+            Type::Function(params, returns) => {
+                let mut args = if params.is_empty() { vec![TypeExpr::named("Void")] } else { Vec::new() };
+                for ty in params.iter().chain([&**returns]) {
+                    args.push(ty.to_type_expr()?);
+                }
+                return Some(TypeExpr::Named { name: "Func".to_string(), args });
+            },
+            Type::Tuple(elements) => {
+                return Some(TypeExpr::Tuple(elements.iter().map(Type::to_type_expr).collect::<Option<_>>()?));
+            },
+            Type::Stream | Type::Meaning | Type::Unknown => return None,
+        };
+        Some(TypeExpr::named(name))
+    }
+
+    // Replace the type parameters bound in `bindings`
+    fn substitute(&self, bindings: &HashMap<String, Type>) -> Type {
+        match self {
+            Type::Param(name) => bindings.get(name).cloned().unwrap_or_else(|| self.clone()),
+            Type::List(element) => Type::List(Box::new(element.substitute(bindings))),
+            Type::Optional(inner) => Type::Optional(Box::new(inner.substitute(bindings))),
+            Type::Class(name, args) => Type::Class(name.clone(), args.iter().map(|arg| arg.substitute(bindings)).collect()),
+            Type::Function(params, returns) => Type::Function(
+                params.iter().map(|param| param.substitute(bindings)).collect(),
+                Box::new(returns.substitute(bindings)),
+            ),
+            Type::Tuple(elements) => Type::Tuple(elements.iter().map(|element| element.substitute(bindings)).collect()),
+            _ => self.clone(),
+        }
+    }
+
+    // The type as seen from other modules: classes and enums of `module` get its
+    // path, like `shapes.Circle`; those it imported are already qualified
+    fn qualify(&self, module: &str) -> Type {
+        match self {
+            Type::Class(name, args) => {
+                Type::Class(qualify_name(name, module), args.iter().map(|arg| arg.qualify(module)).collect())
+            },
+            Type::Enum(name) => Type::Enum(qualify_name(name, module)),
+            Type::List(element) => Type::List(Box::new(element.qualify(module))),
+            Type::Optional(inner) => Type::Optional(Box::new(inner.qualify(module))),
+            Type::Function(params, returns) => {
+                Type::Function(params.iter().map(|param| param.qualify(module)).collect(), Box::new(returns.qualify(module)))
+            },
+            Type::Tuple(elements) => Type::Tuple(elements.iter().map(|element| element.qualify(module)).collect()),
+            _ => self.clone(),
+        }
+    }
+
+    fn mentions(&self, param: &str) -> bool {
+        match self {
+            Type::Param(name) => name == param,
+            Type::List(element) | Type::Optional(element) => element.mentions(param),
+            Type::Class(_, args) | Type::Tuple(args) => args.iter().any(|arg| arg.mentions(param)),
+            Type::Function(params, returns) => params.iter().chain([&**returns]).any(|ty| ty.mentions(param)),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Type::Int => "Int",
+            Type::Float => "Float",
+            Type::String => "String",
+            Type::Bool => "Bool",
+            Type::List(element) if **element == Type::Unknown => "List",
+            Type::List(element) => return write!(f, "List[{}]", element),
+            Type::Void => "Void",
+            Type::Stream => "Stream",
+            Type::Meaning => "Meaning",
+            Type::Class(name, args) if args.is_empty() => name,
+            Type::Class(name, args) => {
+                let args: Vec<String> = args.iter().map(Type::to_string).collect();
+                return write!(f, "{}[{}]", name, args.join(", "));
+            },
+            Type::Param(name) | Type::Enum(name) => name,
+            Type::Optional(inner) if **inner == Type::Unknown => "none",
+            Type::Optional(inner) => return write!(f, "{}?", inner),
+            Type::Function(params, returns) if params.is_empty() => return write!(f, "Func[Void, {}]", returns),
+            Type::Function(params, returns) => {
+                let params: Vec<String> = params.iter().map(Type::to_string).collect();
+                return write!(f, "Func[{}, {}]", params.join(", "), returns);
+            },
+            Type::Tuple(elements) => {
+                let elements: Vec<String> = elements.iter().map(Type::to_string).collect();
+                return write!(f, "({})", elements.join(", "));
+            },
+            Type::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Signature of a function provided by the C++ runtime (libnymya)
+struct RuntimeFunction {
+    module: &'static str,
+    name: &'static str,
+    params: &'static [Type],
+    returns: Type,
+}
+
+const fn runtime_fn(module: &'static str, name: &'static str, params: &'static [Type], returns: Type) -> RuntimeFunction {
+    RuntimeFunction { module, name, params, returns }
+}
+
+// Everything libnymya declares, keyed by NymyaLang module path
+const RUNTIME_FUNCTIONS: &[RuntimeFunction] = &[
+    runtime_fn("math", "sqrt", &[Type::Float], Type::Float),
+    runtime_fn("math", "abs", &[Type::Float], Type::Float),
+    runtime_fn("math", "min", &[Type::Float, Type::Float], Type::Float),
+    runtime_fn("math", "max", &[Type::Float, Type::Float], Type::Float),
+    runtime_fn("math", "pow", &[Type::Float, Type::Float], Type::Float),
+    runtime_fn("math", "sin", &[Type::Float], Type::Float),
+    runtime_fn("math", "cos", &[Type::Float], Type::Float),
+    runtime_fn("math", "tan", &[Type::Float], Type::Float),
+    runtime_fn("math", "pow_int", &[Type::Int, Type::Int], Type::Int),
+    runtime_fn("math", "gcd", &[Type::Int, Type::Int], Type::Int),
+    runtime_fn("crystal", "manifest", &[Type::String], Type::Void),
+    runtime_fn("crystal", "print", &[Type::String], Type::Void),
+    runtime_fn("crystal.file", "dalan_orin", &[Type::String, Type::String], Type::Bool),
+    runtime_fn("crystal.file", "dalan_aya", &[Type::String], Type::Bool),
+    runtime_fn("crystal.file", "dalan_karma", &[Type::String], Type::String),
+    runtime_fn("crystal.file", "dalan_lora", &[Type::String], Type::Stream),
+    runtime_fn("crystal.file", "dalan_shira", &[Type::String], Type::Stream),
+    runtime_fn("symbolic.numerology", "get_meaning", &[Type::Int], Type::Meaning),
+];
+
+// Constants defined by the runtime headers
+const RUNTIME_CONSTANTS: &[(&str, &str, Type)] = &[("math", "PI", Type::Float)];
+
+// Extension methods built into the language: receiver, name, result and the C++ function implementing it
+const BUILTIN_EXTENSIONS: &[(Type, &str, Type, &str)] = &[
+    (Type::Int, "to_string", Type::String, "std::to_string"),
+    (Type::Float, "to_string", Type::String, "std::to_string"),
+    (Type::Bool, "to_string", Type::String, "nymya_bool_to_string"),
+];
+
+// Methods of `List[T]`
+const LIST_METHODS: &[&str] = &["append", "length", "size", "get", "at", "set"];
+
+/// What a project module declares at the top level, visible to the modules
+/// importing it as `module.name`
+#[derive(Debug, Clone, Default)]
+pub struct ModuleExports {
+    variables: BTreeMap<String, Type>,
+    functions: BTreeMap<String, Vec<Signature>>,
+    classes: BTreeMap<String, ClassInfo>,
+    enums: BTreeMap<String, Vec<(String, Vec<Type>)>>,
+    interfaces: BTreeMap<String, BTreeMap<String, Signature>>,
+}
+
+/// Result of checking a program
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    /// Runtime and project modules whose functions, constants or variables the program actually uses
+    pub used_modules: BTreeSet<String>,
+    /// Variables, functions, classes, enums and interfaces declared by the program, for other modules of a project
+    pub exports: ModuleExports,
+}
+
+/// Check a parsed program, returning every error and warning found.
+/// `modules` holds the exports of the project modules that were already checked.
+/// With `globals` the program is a project module whose variables are globals,
+/// so its functions can use those declared before them.
+pub fn check(statements: &mut [Spanned<Statement>], modules: &BTreeMap<String, ModuleExports>, globals: bool) -> Analysis {
+    let mut checker = Checker { modules: modules.clone(), globals, scopes: vec![HashMap::new()], ..Checker::default() };
+    for (module, exports) in modules {
+        checker.import_declarations(module, exports);
+    }
+    for (receiver, name, returns, function) in BUILTIN_EXTENSIONS {
+        let signature = Signature { type_params: Vec::new(), bounds: Vec::new(), params: Vec::new(), returns: returns.clone() };
+        let extension = Extension { receiver: receiver.clone(), signature, function: function.to_string() };
+        checker.extensions.entry(name.to_string()).or_default().push(extension);
+    }
+    checker.declare(statements);
+    checker.check_statements(statements);
+    checker.report_unused_imports();
+    let exports = checker.exports();
+    Analysis { diagnostics: checker.diagnostics, used_modules: checker.used_modules, exports }
+}
+
+// `name` of `module` as other modules refer to it, unless it is already qualified
+fn qualify_name(name: &str, module: &str) -> String {
+    if name.contains('.') {
+        name.to_string()
+    } else {
+        format!("{}.{}", module, name)
+    }
+}
+
+// Signature of a function, method or class initializer declared in the program.
+// Parameter and return types refer to the type parameters as `Type::Param`.
+#[derive(Debug, Clone)]
+struct Signature {
+    type_params: Vec<String>,
+    // Interfaces its own type parameters must implement
+    bounds: Vec<Bound>,
+    params: Vec<Type>,
+    returns: Type,
+}
+
+impl Signature {
+    fn qualify(&self, module: &str) -> Signature {
+        Signature {
+            type_params: self.type_params.clone(),
+            bounds: qualify_bounds(&self.bounds, module),
+            params: self.params.iter().map(|param| param.qualify(module)).collect(),
+            returns: self.returns.qualify(module),
+        }
+    }
+}
+
+fn qualify_bounds(bounds: &[Bound], module: &str) -> Vec<Bound> {
+    bounds.iter().map(|(param, interface)| (param.clone(), qualify_name(interface, module))).collect()
+}
+
+// An extension method like `func Int.to_hex_char() -> String`; the signature
+// does not include the receiver
+#[derive(Debug, Clone)]
+struct Extension {
+    receiver: Type,
+    signature: Signature,
+    // The C++ function it is lowered to
+    function: String,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ClassInfo {
+    type_params: Vec<String>,
+    bounds: Vec<Bound>,
+    // The class it extends, in terms of its own type parameters
+    base: Option<Type>,
+    // The interfaces it declares with `implements`, not those of its base classes
+    interfaces: Vec<String>,
+    fields: BTreeMap<String, Type>,
+    init: Option<Signature>,
+    // Overloads of each method, in declaration order
+    methods: BTreeMap<String, Vec<Signature>>,
+}
+
+impl ClassInfo {
+    fn qualify(&self, module: &str) -> ClassInfo {
+        let qualify_all = |overloads: &Vec<Signature>| overloads.iter().map(|signature| signature.qualify(module)).collect();
+        ClassInfo {
+            type_params: self.type_params.clone(),
+            bounds: qualify_bounds(&self.bounds, module),
+            base: self.base.as_ref().map(|base| base.qualify(module)),
+            interfaces: self.interfaces.iter().map(|interface| qualify_name(interface, module)).collect(),
+            fields: self.fields.iter().map(|(name, ty)| (name.clone(), ty.qualify(module))).collect(),
+            init: self.init.as_ref().map(|init| init.qualify(module)),
+            methods: self.methods.iter().map(|(name, overloads)| (name.clone(), qualify_all(overloads))).collect(),
+        }
+    }
+}
+
+// The function, method or lambda whose body is being checked
+struct FunctionContext {
+    // How diagnostics refer to it, like "`area`" or "the lambda"
+    name: String,
+    returns: Type,
+    // Type parameters in scope, those of the class first
+    type_params: Vec<String>,
+    // Interfaces the type parameters in scope must implement
+    bounds: Vec<Bound>,
+    // `this` in methods
+    this: Option<Type>,
+}
+
+#[derive(Default)]
+struct Checker {
+    // Variables of the enclosing blocks, innermost last; the first scope holds the module-level variables
+    scopes: Vec<HashMap<String, Type>>,
+    // The module-level scopes while a function body is checked, for better diagnostics
+    hidden_scopes: Vec<HashMap<String, Type>>,
+    // Overloads of each function, in declaration order
+    functions: HashMap<String, Vec<Signature>>,
+    classes: HashMap<String, ClassInfo>,
+    // Variants of each enum with their payload types, in declaration order
+    enums: HashMap<String, Vec<(String, Vec<Type>)>>,
+    // Methods each interface requires, with `Self` as `Type::Param("Self")`
+    interfaces: HashMap<String, BTreeMap<String, Signature>>,
+    // Extension methods by name, the built-in ones first
+    extensions: HashMap<String, Vec<Extension>>,
+    function: Option<FunctionContext>,
+    // Number of scopes outside each enclosing lambda, innermost last
+    lambda_scopes: Vec<usize>,
+    // Types of the values unwrapped by the enclosing `?.` chains, innermost last
+    unwrapped: Vec<Type>,
+    // Project modules that can be imported, with what they declare. Their
+    // functions, classes, enums and interfaces are also in the tables above,
+    // under names qualified by the module path like `shapes.circle.Circle`.
+    modules: BTreeMap<String, ModuleExports>,
+    // Whether the module-level variables are globals that functions can use
+    globals: bool,
+    // Imported module paths and where they were imported
+    imports: BTreeMap<String, Span>,
+    missing_imports: HashSet<String>,
+    used_modules: BTreeSet<String>,
+    diagnostics: Vec<Diagnostic>,
+    // Position of the statement being checked; expressions are reported there
+    span: Span,
+}
+
+impl Checker {
+    fn error(&mut self, message: String) {
+        self.diagnostics.push(Diagnostic::error(message).at(self.span));
+    }
+
+    // Make the declarations of a project module available as `module.name`
+    fn import_declarations(&mut self, module: &str, exports: &ModuleExports) {
+        for (name, overloads) in &exports.functions {
+            let overloads = overloads.iter().map(|signature| signature.qualify(module)).collect();
+            self.functions.insert(qualify_name(name, module), overloads);
+        }
+        for (name, class) in &exports.classes {
+            self.classes.insert(qualify_name(name, module), class.qualify(module));
+        }
+        for (name, variants) in &exports.enums {
+            let variants = variants
+                .iter()
+                .map(|(variant, payload)| (variant.clone(), payload.iter().map(|ty| ty.qualify(module)).collect()))
+                .collect();
+            self.enums.insert(qualify_name(name, module), variants);
+        }
+        for (name, methods) in &exports.interfaces {
+            let methods = methods.iter().map(|(method, signature)| (method.clone(), signature.qualify(module))).collect();
+            self.interfaces.insert(qualify_name(name, module), methods);
+        }
+    }
+
+    // What this program declares for the modules importing it; declarations
+    // imported from other modules have qualified names and are left out
+    fn exports(&mut self) -> ModuleExports {
+        fn own<T: Clone>(table: &HashMap<String, T>) -> BTreeMap<String, T> {
+            table.iter().filter(|(name, _)| !name.contains('.')).map(|(name, value)| (name.clone(), value.clone())).collect()
+        }
+        ModuleExports {
+            variables: self.scopes.swap_remove(0).into_iter().collect(),
+            functions: own(&self.functions),
+            classes: own(&self.classes),
+            enums: own(&self.enums),
+            interfaces: own(&self.interfaces),
+        }
+    }
+
+    // Record the use of a declaration of another module, named like `shapes.Circle`
+    fn use_qualified(&mut self, name: &str) {
+        if let Some((module, _)) = name.rsplit_once('.') {
+            if self.modules.contains_key(module) {
+                self.check_imported(module);
+                self.used_modules.insert(module.to_string());
+            }
+        }
+    }
+
+    // Collect the classes and functions of the file first, so they can be used before their declaration
+    fn declare(&mut self, statements: &[Spanned<Statement>]) {
+        // Signatures may name the types of imported modules
+        for stmt in statements {
+            if let Statement::Import(module) = &stmt.node {
+                self.imports.entry(module.clone()).or_insert(stmt.span);
+            }
+        }
+        // Class, enum and interface names come first, since any signature may refer to them
+        let mut duplicates = HashSet::new();
+        for (position, stmt) in statements.iter().enumerate() {
+            self.span = stmt.span;
+            match &stmt.node {
+                Statement::Class(class) if self.is_type_name(&class.name) => {
+                    self.error(format!("`{}` is already declared", class.name));
+                    duplicates.insert(position);
+                },
+                Statement::Class(class) => {
+                    let info = ClassInfo { type_params: class.type_params.clone(), ..ClassInfo::default() };
+                    self.classes.insert(class.name.clone(), info);
+                },
+                Statement::Enum(declaration) if self.is_type_name(&declaration.name) => {
+                    self.error(format!("`{}` is already declared", declaration.name));
+                    duplicates.insert(position);
+                },
+                Statement::Enum(declaration) => {
+                    self.enums.insert(declaration.name.clone(), Vec::new());
+                },
+                Statement::Interface(interface) if self.is_type_name(&interface.name) => {
+                    self.error(format!("`{}` is already declared", interface.name));
+                    duplicates.insert(position);
+                },
+                Statement::Interface(interface) => {
+                    self.interfaces.insert(interface.name.clone(), BTreeMap::new());
+                },
+                _ => {},
+            }
+        }
+        for (position, stmt) in statements.iter().enumerate() {
+            self.span = stmt.span;
+            match &stmt.node {
+                Statement::Class(class) if !duplicates.contains(&position) => {
+                    let info = self.declare_class(class);
+                    self.span = stmt.span;
+                    self.classes.insert(class.name.clone(), info);
+                },
+                Statement::Enum(declaration) if !duplicates.contains(&position) => {
+                    let variants = self.declare_enum(declaration);
+                    self.enums.insert(declaration.name.clone(), variants);
+                },
+                Statement::Interface(interface) if !duplicates.contains(&position) => {
+                    let methods = self.declare_interface(interface);
+                    self.interfaces.insert(interface.name.clone(), methods);
+                },
+                Statement::Function(function) if function.receiver.is_some() => self.declare_extension(function),
+                Statement::Function(function) => {
+                    let signature = self.signature(function, &[]);
+                    if self.is_type_name(&function.name) {
+                        self.error(format!("`{}` is already declared", function.name));
+                        continue;
+                    }
+                    let overloads = self.functions.get(&function.name).map(Vec::as_slice).unwrap_or_default();
+                    match overload_conflict(overloads, &signature) {
+                        Some(reason) => self.error(format!("`{}` {}", function.name, reason)),
+                        None => self.functions.entry(function.name.clone()).or_default().push(signature),
+                    }
+                },
+                _ => {},
+            }
+        }
+        // Enums holding each other, like a direct `enum A { X(A) }`, would need
+        // infinitely large values; report each cycle once, at its first enum
+        let mut in_cycle: HashSet<String> = HashSet::new();
+        for (position, stmt) in statements.iter().enumerate() {
+            if let Statement::Enum(declaration) = &stmt.node {
+                if duplicates.contains(&position) || in_cycle.contains(&declaration.name) {
+                    continue;
+                }
+                if let Some(path) = self.enum_cycle(&declaration.name) {
+                    self.span = stmt.span;
+                    self.error(format!(
+                        "enum `{}` contains itself through {}; keep one of the values in a class instead",
+                        declaration.name,
+                        path.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>().join(" and ")
+                    ));
+                    in_cycle.extend(path);
+                }
+            }
+        }
+        for (position, stmt) in statements.iter().enumerate() {
+            if let Statement::Class(class) = &stmt.node {
+                if class.base.is_some() && !duplicates.contains(&position) {
+                    self.span = stmt.span;
+                    self.check_inheritance(class);
+                }
+            }
+        }
+        // After inheritance, since inherited methods count
+        for (position, stmt) in statements.iter().enumerate() {
+            if let Statement::Class(class) = &stmt.node {
+                if !class.interfaces.is_empty() && !duplicates.contains(&position) {
+                    self.span = stmt.span;
+                    self.check_conformance(class);
+                }
+            }
+        }
+    }
+
+    fn is_type_name(&self, name: &str) -> bool {
+        self.classes.contains_key(name) || self.enums.contains_key(name) || self.interfaces.contains_key(name)
+    }
+
+    // Signatures of the methods an interface requires; they cannot be overloaded,
+    // so that a method call on a bounded type parameter means one of them
+    fn declare_interface(&mut self, interface: &Interface) -> BTreeMap<String, Signature> {
+        let mut methods = BTreeMap::new();
+        for method in &interface.methods {
+            self.span = method.span;
+            let signature = self.signature(&method.node, &["Self".to_string()]);
+            if methods.contains_key(&method.node.name) {
+                self.error(format!(
+                    "method `{}` is already declared in interface `{}`; interface methods cannot be overloaded",
+                    method.node.name, interface.name
+                ));
+                continue;
+            }
+            methods.insert(method.node.name.clone(), signature);
+        }
+        methods
+    }
+
+    // Whether `name` is an interface, reporting it when it is not
+    fn check_interface_name(&mut self, name: &str) -> bool {
+        if self.interfaces.contains_key(name) {
+            self.use_qualified(name);
+            return true;
+        }
+        if self.classes.contains_key(name) {
+            self.error(format!("`{}` is a class, not an interface", name));
+        } else {
+            self.error(format!("unknown interface `{}`", name));
+        }
+        false
+    }
+
+    // Each method of the interfaces a class implements must be declared by the class
+    // or inherited, with the same parameter and return types once `Self` is the class
+    fn check_conformance(&mut self, class: &Class) {
+        let own_args: Vec<Type> = class.type_params.iter().cloned().map(Type::Param).collect();
+        let itself = HashMap::from([("Self".to_string(), Type::Class(class.name.clone(), own_args.clone()))]);
+        for interface in self.classes[&class.name].interfaces.clone() {
+            for (name, required) in self.interfaces[&interface].clone() {
+                let params: Vec<Type> = required.params.iter().map(|param| param.substitute(&itself)).collect();
+                let returns = required.returns.substitute(&itself);
+                let conforms = self.lookup_method(&class.name, &own_args, &name).map(|(overloads, bindings)| {
+                    overloads.iter().any(|signature| {
+                        let own: Vec<Type> = signature.params.iter().map(|param| param.substitute(&bindings)).collect();
+                        signature.type_params.is_empty()
+                            && same_params(&own, &params)
+                            && same_type(&signature.returns.substitute(&bindings), &returns)
+                    })
+                });
+                match conforms {
+                    Some(true) => {},
+                    Some(false) => self.error(format!(
+                        "method `{}` of class `{}` does not match interface `{}`, where it takes {} and returns {}",
+                        name, class.name, interface, describe_params(&params), returns
+                    )),
+                    None => self.error(format!(
+                        "class `{}` does not implement `{}` of interface `{}`, which takes {} and returns {}",
+                        class.name, name, interface, describe_params(&params), returns
+                    )),
+                }
+            }
+        }
+    }
+
+    // Whether a value of type `ty` satisfies the bound `interface` of a type parameter
+    fn implements(&self, ty: &Type, interface: &str) -> bool {
+        match ty {
+            Type::Param(param) => self.function.as_ref().is_some_and(|function| {
+                function.bounds.iter().any(|(bounded, bound)| bounded == param && bound == interface)
+            }),
+            Type::Class(class, args) if self.classes.contains_key(class) => self
+                .ancestry(class, args)
+                .iter()
+                .any(|(ancestor, _)| self.classes[ancestor].interfaces.iter().any(|implemented| implemented == interface)),
+            // Classes of other modules are not checked
+            Type::Class(..) | Type::Unknown => true,
+            _ => false,
+        }
+    }
+
+    // Payload types of the variants of an enum. C++ stores payloads inline, so an
+    // enum cannot contain itself.
+    fn declare_enum(&mut self, declaration: &Enum) -> Vec<(String, Vec<Type>)> {
+        let mut variants: Vec<(String, Vec<Type>)> = Vec::new();
+        for variant in &declaration.variants {
+            self.span = variant.span;
+            let payload: Vec<Type> = variant.node.payload.iter().map(|ty| self.resolve_type(ty, &[])).collect();
+            if payload.iter().any(|ty| contains_enum(ty, &declaration.name)) {
+                self.error(format!(
+                    "variant `{}` cannot contain enum `{}` itself; keep the value in a class instead",
+                    variant.node.name, declaration.name
+                ));
+            }
+            if payload.contains(&Type::Void) {
+                self.error(format!("variant `{}` cannot hold a Void value", variant.node.name));
+            }
+            if variants.iter().any(|(name, _)| *name == variant.node.name) {
+                self.error(format!("variant `{}` is already declared in enum `{}`", variant.node.name, declaration.name));
+                continue;
+            }
+            variants.push((variant.node.name.clone(), payload));
+        }
+        if variants.is_empty() {
+            self.error(format!("enum `{}` needs at least one variant", declaration.name));
+        }
+        variants
+    }
+
+    // The other enums through which the enum `name` holds a value of itself, if
+    // any; holding itself directly is reported by `declare_enum`
+    fn enum_cycle(&self, name: &str) -> Option<Vec<String>> {
+        let mut path = Vec::new();
+        let mut visited = HashSet::new();
+        self.find_enum_cycle(name, name, &mut path, &mut visited).then_some(path)
+    }
+
+    fn find_enum_cycle(&self, start: &str, current: &str, path: &mut Vec<String>, visited: &mut HashSet<String>) -> bool {
+        let payload: Vec<&Type> = self.enums.get(current).into_iter().flatten().flat_map(|(_, payload)| payload).collect();
+        let mut others: Vec<&String> = self.enums.keys().collect();
+        others.sort();
+        for other in others {
+            if other == current || !payload.iter().any(|ty| contains_enum(ty, other)) {
+                continue;
+            }
+            if other == start {
+                return true;
+            }
+            if visited.insert(other.clone()) {
+                path.push(other.clone());
+                if self.find_enum_cycle(start, other, path, visited) {
+                    return true;
+                }
+                path.pop();
+            }
+        }
+        false
+    }
+
+    fn declare_extension(&mut self, function: &Function) {
+        let signature = self.signature(function, &[]);
+        let receiver = function.receiver.as_ref().expect("an extension method");
+        let receiver = self.resolve_type(receiver, &function.type_params);
+        match &receiver {
+            Type::Int | Type::Float | Type::String | Type::Bool => {},
+            Type::List(_) if LIST_METHODS.contains(&function.name.as_str()) => {
+                self.error(format!("`{}` is a built-in method of lists and cannot be redeclared", function.name));
+                return;
+            },
+            Type::List(_) => {},
+            Type::Unknown => return,
+            Type::Class(class, _) => {
+                self.error(format!("`{}` is a class; declare `{}` as a method inside it", class, function.name));
+                return;
+            },
+            _ => {
+                self.error(format!("extension methods can only be added to Int, Float, String, Bool and List, not {}", receiver));
+                return;
+            },
+        }
+        let overloads = self.extensions.entry(function.name.clone()).or_default();
+        let duplicate = overloads.iter().any(|extension| {
+            same_type(&extension.receiver, &receiver) && same_params(&extension.signature.params, &signature.params)
+        });
+        if duplicate {
+            self.error(format!("`{}.{}` is already declared with the same parameter types", receiver, function.name));
+            return;
+        }
+        let function = function.extension_name().expect("an extension method");
+        overloads.push(Extension { receiver, signature, function });
+    }
+
+    // The extension method `name` for a receiver of type `receiver`, with the type
+    // arguments its receiver binds; those without type parameters first
+    fn find_extension(&self, receiver: &Type, name: &str) -> Option<(Extension, HashMap<String, Type>)> {
+        let mut extensions: Vec<&Extension> = self.extensions.get(name)?.iter().collect();
+        extensions.sort_by_key(|extension| !extension.signature.type_params.is_empty());
+        extensions.into_iter().find_map(|extension| {
+            let mut bindings = HashMap::new();
+            infer(&extension.receiver, receiver, &extension.signature.type_params, &mut bindings);
+            same_type(&extension.receiver.substitute(&bindings), receiver).then(|| (extension.clone(), bindings))
+        })
+    }
+
+    // A derived class may not extend itself, redeclare inherited fields or
+    // change the signature of the methods it overrides
+    fn check_inheritance(&mut self, class: &Class) {
+        let mut ancestor = self.classes[&class.name].base.clone();
+        while let Some(Type::Class(name, _)) = ancestor {
+            if name == class.name {
+                self.error(format!("class `{}` cannot extend itself", class.name));
+                self.classes.get_mut(&class.name).expect("declared above").base = None;
+                return;
+            }
+            ancestor = self.classes.get(&name).and_then(|info| info.base.clone());
+        }
+
+        let own_args: Vec<Type> = class.type_params.iter().cloned().map(Type::Param).collect();
+        let Some((base, base_args)) = self.ancestry(&class.name, &own_args).into_iter().nth(1) else { return };
+        if class.init.is_none() && self.classes[&base].init.as_ref().is_some_and(|init| !init.params.is_empty()) {
+            self.error(format!("class `{}` needs an `init` that calls `super(...)`, since `{}` takes arguments", class.name, base));
+        }
+        for field in &class.fields {
+            if self.lookup_field(&base, &base_args, &field.node.name).is_some() {
+                self.span = field.span;
+                self.error(format!("field `{}` is already declared in base class `{}`", field.node.name, base));
+            }
+        }
+        // A method hides all the inherited overloads of its name, in C++ as here,
+        // so it must override one of them
+        for method in &class.methods {
+            let Some((inherited, bindings)) = self.lookup_method(&base, &base_args, &method.node.name) else { continue };
+            self.span = method.span;
+            let errors = self.diagnostics.len();
+            let own = self.signature(&method.node, &class.type_params);
+            self.diagnostics.truncate(errors);
+            if !own.type_params.is_empty() || inherited.iter().any(|signature| !signature.type_params.is_empty()) {
+                self.error(format!("generic method `{}` cannot be overridden", method.node.name));
+                continue;
+            }
+            let overridden = inherited.iter().find(|signature| {
+                let params: Vec<Type> = signature.params.iter().map(|param| param.substitute(&bindings)).collect();
+                same_params(&params, &own.params)
+            });
+            match overridden {
+                Some(signature) if same_type(&signature.returns.substitute(&bindings), &own.returns) => {},
+                Some(_) => self.error(format!(
+                    "`{}` overrides the method of base class `{}` and must have the same return type",
+                    method.node.name, base
+                )),
+                None => self.error(format!(
+                    "`{}` overrides the method of base class `{}` and must have the same parameter types",
+                    method.node.name, base
+                )),
+            }
+        }
+    }
+
+    // The class and its ancestors, nearest first, each with its type arguments
+    fn ancestry(&self, class: &str, args: &[Type]) -> Vec<(String, Vec<Type>)> {
+        let mut chain = Vec::new();
+        let mut current = Some((class.to_string(), args.to_vec()));
+        while let Some((name, args)) = current {
+            let Some(info) = self.classes.get(&name) else { break };
+            current = match info.base.as_ref().map(|base| base.substitute(&bindings(&info.type_params, &args))) {
+                Some(Type::Class(base, base_args)) if !chain.iter().any(|(seen, _)| *seen == base) => Some((base, base_args)),
+                _ => None,
+            };
+            chain.push((name, args));
+        }
+        chain
+    }
+
+    fn lookup_field(&self, class: &str, args: &[Type], name: &str) -> Option<Type> {
+        self.ancestry(class, args).into_iter().find_map(|(class, args)| {
+            let info = &self.classes[&class];
+            info.fields.get(name).map(|ty| ty.substitute(&bindings(&info.type_params, &args)))
+        })
+    }
+
+    // The overloads of a method of the class or its nearest ancestor declaring
+    // it, with the type arguments of that class
+    fn lookup_method(&self, class: &str, args: &[Type], name: &str) -> Option<(Vec<Signature>, HashMap<String, Type>)> {
+        self.ancestry(class, args).into_iter().find_map(|(class, args)| {
+            let info = &self.classes[&class];
+            info.methods.get(name).map(|overloads| (overloads.clone(), bindings(&info.type_params, &args)))
+        })
+    }
+
+    fn declare_class(&mut self, class: &Class) -> ClassInfo {
+        let mut info =
+            ClassInfo { type_params: class.type_params.clone(), bounds: class.bounds.clone(), ..ClassInfo::default() };
+        for (_, interface) in &class.bounds {
+            self.check_interface_name(interface);
+        }
+        for interface in &class.interfaces {
+            if self.classes.contains_key(interface) {
+                self.error(format!("`{}` is a class; use `extends {}` to inherit from it", interface, interface));
+            } else if info.interfaces.contains(interface) {
+                self.error(format!("class `{}` already implements `{}`", class.name, interface));
+            } else if self.check_interface_name(interface) {
+                info.interfaces.push(interface.clone());
+            }
+        }
+        if let Some(base) = &class.base {
+            match self.resolve_type(base, &class.type_params) {
+                base @ Type::Class(..) => info.base = Some(base),
+                Type::Unknown => {},
+                other => self.error(format!("class `{}` can only extend a class, not {}", class.name, other)),
+            }
+        }
+        for field in &class.fields {
+            self.span = field.span;
+            let ty = self.resolve_type(&field.node.ty, &class.type_params);
+            if info.fields.insert(field.node.name.clone(), ty).is_some() {
+                self.error(format!("field `{}` is already declared in class `{}`", field.node.name, class.name));
+            }
+        }
+        if let Some(init) = &class.init {
+            self.span = init.span;
+            info.init = Some(self.signature(&init.node, &class.type_params));
+        }
+        // C++ would leave these fields null or empty until `init` sets them
+        let init_body = class.init.as_ref().map_or(&[][..], |init| &init.node.body[..]);
+        for field in &class.fields {
+            let Some(ty) = info.fields.get(&field.node.name) else { continue };
+            if matches!(ty, Type::Class(..) | Type::Enum(_) | Type::Function(..) | Type::Param(_)) && !assigns_field(init_body, &field.node.name) {
+                self.span = field.span;
+                self.error(format!(
+                    "field `{}` of type {} must be assigned in the `init` of `{}`; declare it as `{}?` if it can be empty",
+                    field.node.name, ty, class.name, ty
+                ));
+            }
+        }
+        for method in &class.methods {
+            self.span = method.span;
+            let signature = self.signature(&method.node, &class.type_params);
+            if method.node.name.starts_with("op_") {
+                self.check_operator_method(&method.node, &signature);
+            }
+            if info.fields.contains_key(&method.node.name) {
+                self.error(format!("`{}` is already declared in class `{}`", method.node.name, class.name));
+                continue;
+            }
+            let overloads = info.methods.get(&method.node.name).map(Vec::as_slice).unwrap_or_default();
+            match overload_conflict(overloads, &signature) {
+                Some(reason) => self.error(format!("method `{}` of class `{}` {}", method.node.name, class.name, reason)),
+                None => info.methods.entry(method.node.name.clone()).or_default().push(signature),
+            }
+        }
+        info
+    }
+
+    // Methods named like `op_add` overload an operator, so their shape is fixed
+    fn check_operator_method(&mut self, method: &Function, signature: &Signature) {
+        let Some(&(_, symbol, arity)) = OPERATOR_METHODS.iter().find(|(name, ..)| *name == method.name) else {
+            let names: Vec<String> = OPERATOR_METHODS.iter().map(|(name, ..)| format!("`{}`", name)).collect();
+            self.error(format!("`{}` is not an operator method; classes can declare {}", method.name, names.join(", ")));
+            return;
+        };
+        if !method.type_params.is_empty() {
+            self.error(format!("operator method `{}` cannot be generic", method.name));
+        }
+        if signature.params.len() != arity {
+            let takes = if arity == 0 { "no parameters" } else { "one parameter" };
+            self.error(format!("operator method `{}` for `{}` must take {}", method.name, symbol, takes));
+        }
+        match &signature.returns {
+            Type::Void => self.error(format!("operator method `{}` must return a value", method.name)),
+            Type::Bool | Type::Unknown => {},
+            other if method.name == "op_eq" => self.error(format!("`op_eq` must return Bool, found {}", other)),
+            _ => {},
+        }
+    }
+
+    // `outer_type_params` are those of the enclosing class
+    fn signature(&mut self, function: &Function, outer_type_params: &[String]) -> Signature {
+        let mut type_params = outer_type_params.to_vec();
+        for param in &function.type_params {
+            if type_params.contains(param) {
+                self.error(format!("type parameter `{}` is already declared", param));
+            }
+            type_params.push(param.clone());
+        }
+        for (_, interface) in &function.bounds {
+            self.check_interface_name(interface);
+        }
+        let params = function.params.iter().map(|param| self.resolve_type(&param.ty, &type_params)).collect();
+        let returns = self.resolve_type(&function.returns, &type_params);
+        Signature { type_params: function.type_params.clone(), bounds: function.bounds.clone(), params, returns }
+    }
+
+    fn resolve_type(&mut self, ty: &TypeExpr, type_params: &[String]) -> Type {
+        let (name, args) = match ty {
+            TypeExpr::Named { name, args } => (name, args),
+            TypeExpr::Optional(inner) => {
+                let inner = self.resolve_type(inner, type_params);
+                if inner == Type::Void {
+                    self.error("Void cannot be optional".to_string());
+                }
+                return Type::Optional(Box::new(inner));
+            },
+            TypeExpr::Tuple(elements) => {
+                let elements: Vec<Type> = elements.iter().map(|element| self.resolve_type(element, type_params)).collect();
+                if elements.contains(&Type::Void) {
+                    self.error("a tuple element cannot be Void".to_string());
+                }
+                return Type::Tuple(elements);
+            },
+        };
+        if name.contains('.') {
+            self.use_qualified(name);
+        }
+        let resolved = match name.as_str() {
+            "Int" => Type::Int,
+            "Float" => Type::Float,
+            "String" => Type::String,
+            "Bool" => Type::Bool,
+            "Void" => Type::Void,
+            "List" => {
+                if args.len() != 1 {
+                    self.error(format!("`List` takes 1 type argument but {} were given", args.len()));
+                    return Type::List(Box::new(Type::Unknown));
+                }
+                return Type::List(Box::new(self.resolve_type(&args[0], type_params)));
+            },
+            // `Func[Void, R]` takes no parameters
+            "Func" => {
+                let Some((returns, params)) = args.split_last().filter(|(_, params)| !params.is_empty()) else {
+                    self.error("`Func` takes the parameter types and the return type, like `Func[Float, Void]`".to_string());
+                    return Type::Unknown;
+                };
+                let returns = Box::new(self.resolve_type(returns, type_params));
+                if let [TypeExpr::Named { name, args }] = params {
+                    if name == "Void" && args.is_empty() {
+                        return Type::Function(Vec::new(), returns);
+                    }
+                }
+                let params = params.iter().map(|param| self.resolve_type(param, type_params)).collect::<Vec<_>>();
+                if params.contains(&Type::Void) {
+                    self.error("a `Func` parameter cannot be Void".to_string());
+                }
+                return Type::Function(params, returns);
+            },
+            _ if type_params.contains(name) => Type::Param(name.clone()),
+            _ if self.enums.contains_key(name) => Type::Enum(name.clone()),
+            _ if self.interfaces.contains_key(name) => {
+                self.error(format!(
+                    "interface `{}` is not a type; take a type parameter bounded by it instead, like `[T: {}]`",
+                    name, name
+                ));
+                return Type::Unknown;
+            },
+            _ => {
+                let Some(expected) = self.classes.get(name).map(|class| class.type_params.len()) else {
+                    self.error(format!("unknown type `{}`", name));
+                    return Type::Unknown;
+                };
+                if args.len() != expected {
+                    self.error(format!("`{}` takes {} type argument(s) but {} were given", name, expected, args.len()));
+                    return Type::Unknown;
+                }
+                let args = args.iter().map(|arg| self.resolve_type(arg, type_params)).collect();
+                return Type::Class(name.clone(), args);
+            },
+        };
+        if !args.is_empty() {
+            self.error(format!("`{}` does not take type arguments", name));
+        }
+        resolved
+    }
+
+    // Type parameters usable at the current position
+    fn type_params(&self) -> Vec<String> {
+        self.function.as_ref().map(|function| function.type_params.clone()).unwrap_or_default()
+    }
+
+    fn check_statements(&mut self, statements: &mut [Spanned<Statement>]) {
+        for stmt in statements {
+            self.span = stmt.span;
+            self.check_statement(&mut stmt.node);
+        }
+    }
+
+    // Statements of an `if` or `while` body; their variables are local to it
+    fn check_block(&mut self, statements: &mut [Spanned<Statement>]) {
+        self.scopes.push(HashMap::new());
+        self.check_statements(statements);
+        self.scopes.pop();
+    }
+
+    fn check_statement(&mut self, stmt: &mut Statement) {
+        match stmt {
+            Statement::Import(module) => {
+                self.imports.entry(module.clone()).or_insert(self.span);
+            },
+            Statement::VariableDeclaration { name, ty, value } => {
+                let declared = ty.as_ref().map(|ty| {
+                    let type_params = self.type_params();
+                    self.resolve_type(ty, &type_params)
+                });
+                let value_ty = self.check_expression(value);
+                if self.scopes.last().is_some_and(|scope| scope.contains_key(name.as_str())) {
+                    self.error(format!("variable `{}` is already declared", name));
+                }
+                if value_ty == Type::Void {
+                    self.error(format!("cannot assign the result of a Void call to `{}`", name));
+                }
+                let ty = match declared {
+                    Some(declared) => {
+                        let value_ty = self.coerce_literal(value, &value_ty, &declared);
+                        if value_ty != Type::Void && !self.compatible(&value_ty, &declared) {
+                            self.error(format!(
+                                "cannot assign a value of type {} to `{}` of type {}{}",
+                                value_ty, name, declared, unwrap_hint(&value_ty, &declared)
+                            ));
+                        }
+                        declared
+                    },
+                    None => {
+                        if matches!(value, Expression::None) {
+                            self.error(format!(
+                                "cannot infer the type of `{}` from `none`; declare it like `var {}: Int? = none`",
+                                name, name
+                            ));
+                        } else if holds_untyped_none(&value_ty) {
+                            let example = if let Type::List(_) = value_ty { "List[Int?]" } else { "(Int, String?)" };
+                            self.error(format!(
+                                "cannot infer the type of `{}` from `none`; declare it like `var {}: {} = ...`",
+                                name, name, example
+                            ));
+                        }
+                        if let Type::Function(..) = value_ty {
+                            *ty = value_ty.to_type_expr();
+                        }
+                        value_ty
+                    },
+                };
+                self.scopes.last_mut().expect("the module scope is never popped").insert(name.clone(), ty);
+            },
+            Statement::Destructure { names, value } => {
+                let value_ty = self.check_expression(value);
+                let element_types = self.unpack(&value_ty, names.len(), "variables");
+                for (name, ty) in names.iter().zip(element_types) {
+                    if name == "_" {
+                        continue;
+                    }
+                    if self.scopes.last().is_some_and(|scope| scope.contains_key(name.as_str())) {
+                        self.error(format!("variable `{}` is already declared", name));
+                    }
+                    if ty == Type::Optional(Box::new(Type::Unknown)) {
+                        self.error(format!("cannot infer the type of `{}` from `none`; give the tuple a type first", name));
+                    }
+                    self.scopes.last_mut().expect("the module scope is never popped").insert(name.clone(), ty);
+                }
+            },
+            Statement::Assignment { target: AssignmentTarget::Tuple(targets), value, .. } => {
+                self.check_tuple_assignment(targets, value);
+            },
+            Statement::Assignment { target, operator, value } => {
+                let value_ty = self.check_expression(value);
+                let (target_ty, target_name) = self.check_assignment_target(target);
+                let errors = self.diagnostics.len();
+                let ty = match operator {
+                    Some(operator) => self.check_binary(*operator, &target_ty, &value_ty, value),
+                    None => self.coerce_literal(value, &value_ty, &target_ty),
+                };
+                if value_ty == Type::Void {
+                    self.error(format!("cannot assign the result of a Void call to {}", target_name));
+                } else if self.diagnostics.len() == errors && !self.compatible(&ty, &target_ty) {
+                    self.error(format!(
+                        "cannot assign a value of type {} to {} of type {}{}",
+                        ty, target_name, target_ty, unwrap_hint(&ty, &target_ty)
+                    ));
+                }
+            },
+            Statement::Expression(expression @ Expression::Match { .. }) => {
+                self.check_match(expression, true);
+            },
+            Statement::Expression(expression) => {
+                self.check_expression(expression);
+            },
+            Statement::If { condition, then_branch, else_branch } => {
+                self.check_condition("if", condition);
+                self.check_block(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.check_block(else_branch);
+                }
+            },
+            Statement::IfLet { name, value, then_branch, else_branch } => {
+                let ty = match self.check_expression(value) {
+                    Type::Optional(inner) => *inner,
+                    Type::Unknown => Type::Unknown,
+                    ty => {
+                        self.error(format!("`if let` needs an optional value, found {}", ty));
+                        ty
+                    },
+                };
+                self.scopes.push(HashMap::from([(name.clone(), ty)]));
+                self.check_statements(then_branch);
+                self.scopes.pop();
+                if let Some(else_branch) = else_branch {
+                    self.check_block(else_branch);
+                }
+            },
+            Statement::While { condition, body } => {
+                self.check_condition("while", condition);
+                self.check_block(body);
+            },
+            Statement::Return(value) => self.check_return(value.as_mut()),
+            Statement::Function(function) => {
+                let span = self.span;
+                // An extension method sees its receiver as `this`
+                let errors = self.diagnostics.len();
+                let receiver = function.receiver.as_ref().map(|receiver| self.resolve_type(receiver, &function.type_params));
+                self.diagnostics.truncate(errors);
+                self.check_function(function, receiver.as_ref().map(|receiver| (receiver, &[][..])), span);
+            },
+            Statement::Class(class) => {
+                let this = Type::Class(class.name.clone(), class.type_params.iter().cloned().map(Type::Param).collect());
+                let type_params = class.type_params.clone();
+                let members = class.init.iter_mut().chain(class.methods.iter_mut());
+                for member in members {
+                    self.span = member.span;
+                    self.check_function(&mut member.node, Some((&this, &type_params)), member.span);
+                }
+            },
+            // Checked when they were declared
+            Statement::Enum(_) | Statement::Interface(_) => {},
+        }
+    }
+
+    // Body of a function or method. Functions only see their parameters (and
+    // `this` in methods), not the variables of the file.
+    fn check_function(&mut self, function: &mut Function, class: Option<(&Type, &[String])>, span: Span) {
+        let (this, class_type_params) = class.map_or((None, &[][..]), |(this, type_params)| (Some(this), type_params));
+
+        // The signature was already checked when it was declared; resolve it again quietly
+        let errors = self.diagnostics.len();
+        let signature = self.signature(function, class_type_params);
+        self.diagnostics.truncate(errors);
+
+        let mut type_params = class_type_params.to_vec();
+        type_params.extend(signature.type_params.iter().cloned());
+        let mut bounds = match this {
+            Some(Type::Class(class, _)) => self.classes.get(class).map(|info| info.bounds.clone()).unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        bounds.extend(signature.bounds.iter().cloned());
+        let context = FunctionContext {
+            name: format!("`{}`", function.name),
+            returns: signature.returns.clone(),
+            type_params,
+            bounds,
+            this: this.cloned(),
+        };
+        let outer_function = self.function.replace(context);
+        // The variables of a project module declared so far are globals the
+        // function can use; those of a program are locals of `main()`
+        let mut scopes = Vec::new();
+        if self.globals && outer_function.is_none() {
+            scopes.push(self.scopes[0].clone());
+        }
+        scopes.push(HashMap::new());
+        let outer_scopes = std::mem::replace(&mut self.scopes, scopes);
+        if self.hidden_scopes.is_empty() {
+            self.hidden_scopes = outer_scopes.clone();
+        }
+
+        let scope = self.scopes.len() - 1;
+        if let Some(this) = this {
+            self.scopes[scope].insert("this".to_string(), this.clone());
+        }
+        for (param, ty) in function.params.iter().zip(signature.params) {
+            if self.scopes[scope].insert(param.name.clone(), ty).is_some() {
+                self.error(format!("parameter `{}` is already declared", param.name));
+            }
+        }
+        // The body shares the scope of the parameters, as in C++
+        let mut body = &mut function.body[..];
+        if let Some(Type::Class(class, args)) = this.filter(|_| function.name == "init") {
+            body = self.check_base_init(class, args, body, span);
+        }
+        self.check_statements(body);
+
+        if signature.returns != Type::Void && !always_returns(&function.body) {
+            self.span = span;
+            self.error(format!("`{}` must return a value of type {} on every path", function.name, signature.returns));
+        }
+
+        self.scopes = outer_scopes;
+        self.function = outer_function;
+        if self.function.is_none() {
+            self.hidden_scopes.clear();
+        }
+    }
+
+    // An `init` of a derived class starts with `super(...)` when the initializer
+    // of the base class takes arguments; returns the statements after it
+    fn check_base_init<'a>(
+        &mut self,
+        class: &str,
+        args: &[Type],
+        body: &'a mut [Spanned<Statement>],
+        span: Span,
+    ) -> &'a mut [Spanned<Statement>] {
+        let Some((base, base_args)) = self.ancestry(class, args).into_iter().nth(1) else { return body };
+        let info = self.classes[&base].clone();
+        let params = info.init.map(|init| init.params).unwrap_or_default();
+        let leading = matches!(
+            body.first().map(|stmt| &stmt.node),
+            Some(Statement::Expression(Expression::SuperCall { method, .. })) if method == "init"
+        );
+        if !leading {
+            if !params.is_empty() {
+                self.span = span;
+                self.error(format!("`init` of `{}` must start with `super(...)`, since `{}` takes arguments", class, base));
+            }
+            return body;
+        }
+        let (first, rest) = body.split_first_mut().expect("checked above");
+        let Statement::Expression(Expression::SuperCall { args, .. }) = &mut first.node else { unreachable!("checked above") };
+        self.span = first.span;
+        let signature = Signature { type_params: Vec::new(), bounds: Vec::new(), params, returns: Type::Void };
+        let callee = format!("the `init` of `{}`", base);
+        self.check_signature_call(&callee, &signature, bindings(&info.type_params, &base_args), &[], args);
+        rest
+    }
+
+    // A lambda sees the variables around it, as copies
+    fn check_lambda(&mut self, params: &[Parameter], returns: &TypeExpr, body: &mut [Spanned<Statement>]) -> Type {
+        let span = self.span;
+        let type_params = self.type_params();
+        let param_types: Vec<Type> = params.iter().map(|param| self.resolve_type(&param.ty, &type_params)).collect();
+        let returns = self.resolve_type(returns, &type_params);
+        let this = self.function.as_ref().and_then(|function| function.this.clone());
+        let bounds = self.function.as_ref().map(|function| function.bounds.clone()).unwrap_or_default();
+        let context = FunctionContext { name: "the lambda".to_string(), returns: returns.clone(), type_params, bounds, this };
+        let outer_function = self.function.replace(context);
+        self.lambda_scopes.push(self.scopes.len());
+        self.scopes.push(HashMap::new());
+        for (param, ty) in params.iter().zip(&param_types) {
+            if self.scopes.last_mut().expect("pushed above").insert(param.name.clone(), ty.clone()).is_some() {
+                self.error(format!("parameter `{}` is already declared", param.name));
+            }
+        }
+        self.check_statements(body);
+        if returns != Type::Void && !always_returns(body) {
+            self.span = span;
+            self.error(format!("the lambda must return a value of type {} on every path", returns));
+        }
+        self.scopes.pop();
+        self.lambda_scopes.pop();
+        self.function = outer_function;
+        Type::Function(param_types, Box::new(returns))
+    }
+
+    // Lambdas capture copies, so changing a variable from outside the lambda would be lost
+    fn check_not_captured(&mut self, expr: &Expression) {
+        let Expression::Variable(name) = expr else { return };
+        if name == "nymya_this" {
+            self.error("cannot change the value an extension method is called on, since it receives a copy".to_string());
+            return;
+        }
+        let Some(&outside) = self.lambda_scopes.last() else { return };
+        if self.scopes.iter().rposition(|scope| scope.contains_key(name)).is_some_and(|index| index < outside) {
+            self.error(format!(
+                "cannot change `{}` inside a lambda, which has a copy of it; keep the value in a field of an object to share it",
+                name
+            ));
+        }
+    }
+
+    fn check_return(&mut self, value: Option<&mut Expression>) {
+        let Some(function) = &self.function else {
+            self.error("`return` outside of a function".to_string());
+            if let Some(value) = value {
+                self.check_expression(value);
+            }
+            return;
+        };
+        let (name, returns) = (function.name.clone(), function.returns.clone());
+        match value {
+            None if returns != Type::Void => {
+                self.error(format!("{} must return a value of type {}", name, returns));
+            },
+            None => {},
+            Some(value) => {
+                let ty = self.check_expression(value);
+                if returns == Type::Void {
+                    self.error(format!("{} returns Void, so `return` cannot have a value", name));
+                    return;
+                }
+                let ty = self.coerce_literal(value, &ty, &returns);
+                if !self.compatible(&ty, &returns) {
+                    self.error(format!("{} must return {}, found {}{}", name, returns, ty, unwrap_hint(&ty, &returns)));
+                }
+            },
+        }
+    }
+
+    fn check_condition(&mut self, keyword: &str, condition: &mut Expression) {
+        let ty = self.check_expression(condition);
+        if !self.compatible(&ty, &Type::Bool) {
+            self.error(format!("`{}` condition must be Bool, found {}", keyword, ty));
+        }
+    }
+
+    fn check_expression(&mut self, expr: &mut Expression) -> Type {
+        match expr {
+            Expression::Integer(_) => Type::Int,
+            Expression::Float(_) => Type::Float,
+            Expression::String(_) => Type::String,
+            Expression::Boolean(_) => Type::Bool,
+            Expression::None => Type::Optional(Box::new(Type::Unknown)),
+            Expression::Unwrapped => self.unwrapped.last().cloned().unwrap_or(Type::Unknown),
+            // Only added to arguments that have been checked, and of a type compatible with `ty`
+            Expression::Convert { value, .. } => self.check_expression(value),
+            Expression::OptionalChain { object, member, void } => {
+                let inner = match self.check_expression(object) {
+                    Type::Optional(inner) => *inner,
+                    Type::Unknown => Type::Unknown,
+                    ty => {
+                        self.error(format!("a value of type {} is never none; use `.` instead of `?.`", ty));
+                        ty
+                    },
+                };
+                self.unwrapped.push(inner);
+                let member_ty = self.check_expression(member);
+                self.unwrapped.pop();
+                match member_ty {
+                    Type::Void => {
+                        *void = true;
+                        Type::Void
+                    },
+                    Type::Optional(_) => member_ty,
+                    _ => Type::Optional(Box::new(member_ty)),
+                }
+            },
+            Expression::ArrayLiteral { elements, element_type } => {
+                let element = self.check_list_elements(elements);
+                if element_type.is_none() {
+                    *element_type = element.to_type_expr();
+                }
+                Type::List(Box::new(element))
+            },
+            Expression::Tuple { elements, ty } => {
+                let element_types: Vec<Type> = elements.iter_mut().map(|element| self.check_expression(element)).collect();
+                if element_types.contains(&Type::Void) {
+                    self.error("a tuple element cannot be the result of a Void call".to_string());
+                }
+                let tuple = Type::Tuple(element_types);
+                *ty = tuple.to_type_expr();
+                tuple
+            },
+            Expression::TupleElement { tuple, index } => match self.check_expression(tuple) {
+                Type::Tuple(elements) => elements.get(*index).cloned().unwrap_or_else(|| {
+                    let ty = Type::Tuple(elements.clone());
+                    self.error(format!("{} has no element {}; its elements are numbered 0 to {}", ty, index, elements.len() - 1));
+                    Type::Unknown
+                }),
+                Type::Unknown => Type::Unknown,
+                ty => {
+                    self.error(format!(
+                        "a value of type {} has no element `.{}`; only tuples have numbered elements{}",
+                        ty, index, optional_hint(&ty)
+                    ));
+                    Type::Unknown
+                },
+            },
+            Expression::Variable(name) => {
+                let ty = self.resolve_variable(name);
+                // The receiver of an extension method is a parameter in C++, where `this` is reserved
+                if name == "this" && !matches!(ty, Type::Class(..) | Type::Unknown) {
+                    *name = "nymya_this".to_string();
+                }
+                ty
+            },
+            Expression::FunctionCall { module, .. } if self.modules.contains_key(module.as_str()) => self.check_module_call(expr),
+            Expression::FunctionCall { module, function, args } => self.check_call(module, function, args),
+            Expression::Call { .. } => self.check_function_call(expr),
+            Expression::Construct { class, type_args, args } => {
+                for arg in args.iter_mut() {
+                    self.check_expression(arg);
+                }
+                let type_params = self.type_params();
+                let type_args = type_args.iter().map(|ty| self.resolve_type(ty, &type_params)).collect();
+                Type::Class(class.clone(), type_args)
+            },
+            Expression::ModuleMember { module, name } => self.check_member(module, name),
+            Expression::Field { .. } => self.check_field_expression(expr),
+            Expression::ArrayAccess { .. } => self.check_array_access(expr),
+            Expression::ValueCall { callee, args } => {
+                let ty = self.check_expression(callee);
+                self.check_value_call("the called value", &ty, args)
+            },
+            Expression::ArrayMethodCall { array, method, args } => {
+                let array_ty = self.check_expression(array);
+                if method == "append" || method == "set" {
+                    self.check_not_captured(array);
+                }
+                self.check_list_method(&array_ty, method, args)
+            },
+            Expression::MethodCall { .. } => self.check_method_call(expr),
+            // Only created by check_method_call, from method calls that were already checked
+            Expression::ExtensionCall { args, .. } => {
+                self.check_each(args);
+                Type::Unknown
+            },
+            Expression::Lambda { params, returns, body } => self.check_lambda(params, returns, body),
+            Expression::EnumVariant { enum_name, variant, args } => self.check_enum_variant(enum_name, variant, args),
+            Expression::Match { .. } => self.check_match(expr, false),
+            Expression::SuperCall { method, args } => self.check_super_call(method, args),
+            Expression::UnaryOp { operator, operand } => {
+                let operand_ty = self.check_expression(operand);
+                self.check_unary(*operator, &operand_ty)
+            },
+            Expression::BinaryOp { left, operator, right } => {
+                let left_ty = self.check_expression(left);
+                let right_ty = self.check_expression(right);
+                self.check_binary(*operator, &left_ty, &right_ty, right)
+            },
+        }
+    }
+
+    // Element type of a list literal; `Int` and `Float` elements make a `List[Float]`
+    fn check_list_elements(&mut self, elements: &mut [Expression]) -> Type {
+        let mut element = Type::Unknown;
+        for expression in elements {
+            let ty = self.check_expression(expression);
+            element = match self.unify(&element, &ty) {
+                Some(unified) => unified,
+                None => {
+                    self.error(format!("list elements must have the same type, found {} and {}", element, ty));
+                    element
+                },
+            };
+        }
+        element
+    }
+
+    // A type both values can be used as, if there is one
+    fn unify(&self, left: &Type, right: &Type) -> Option<Type> {
+        match (left, right) {
+            (Type::Unknown, ty) | (ty, Type::Unknown) => Some(ty.clone()),
+            (Type::Int, Type::Float) | (Type::Float, Type::Int) => Some(Type::Float),
+            (Type::Tuple(left), Type::Tuple(right)) if left.len() == right.len() => {
+                let elements = left.iter().zip(right).map(|(left, right)| self.unify(left, right)).collect::<Option<_>>()?;
+                Some(Type::Tuple(elements))
+            },
+            (Type::Optional(left), Type::Optional(right)) => Some(Type::Optional(Box::new(self.unify(left, right)?))),
+            // `[1, none]` is a list of `Int?`
+            (Type::Optional(inner), ty) | (ty, Type::Optional(inner)) => Some(Type::Optional(Box::new(self.unify(inner, ty)?))),
+            _ if left == right => Some(left.clone()),
+            _ => self.common_ancestor(left, right),
+        }
+    }
+
+    // A variant of an enum whose payload holds a function, which C++ cannot compare
+    fn variant_holding_function(&self, ty: &Type) -> Option<String> {
+        let Type::Enum(name) = ty else { return None };
+        self.enums.get(name)?.iter().find(|(_, payload)| payload.iter().any(holds_function)).map(|(variant, _)| variant.clone())
+    }
+
+    // An enum name used as a value, as in `Gate.H(0)` or `shapes.Color.Red`,
+    // unless a variable hides it
+    fn enum_reference(&mut self, expr: &Expression) -> Option<String> {
+        match expr {
+            Expression::Variable(name) => {
+                let hidden = self.scopes.iter().any(|scope| scope.contains_key(name));
+                (self.enums.contains_key(name) && !hidden).then(|| name.clone())
+            },
+            Expression::ModuleMember { module, name } if self.modules.contains_key(module) => {
+                let name = format!("{}.{}", module, name);
+                self.enums.contains_key(&name).then(|| {
+                    self.use_qualified(&name);
+                    name
+                })
+            },
+            _ => None,
+        }
+    }
+
+    // `Gate.CNOT(0, 1)`: a variant with a value for each of its payload types
+    fn check_enum_variant(&mut self, enum_name: &str, variant: &str, args: &mut [Expression]) -> Type {
+        let payload = self.enums[enum_name].iter().find(|(name, _)| name == variant).map(|(_, payload)| payload.clone());
+        let arg_types: Vec<Type> = args.iter_mut().map(|arg| self.check_expression(arg)).collect();
+        match payload {
+            Some(payload) => {
+                let arg_types = self.coerce_literals(args, &arg_types, &payload);
+                self.check_arguments(&format!("`{}.{}`", enum_name, variant), &payload, &arg_types);
+            },
+            None => self.error(format!("enum `{}` has no variant `{}`", enum_name, variant)),
+        }
+        Type::Enum(enum_name.to_string())
+    }
+
+    // `match value { ... }`. Each arm sees the names its pattern binds. Arms on an
+    // enum must cover every variant or end with `_`; a match used as a value must
+    // be exhaustive and have expression arms of one type, while a statement may
+    // leave values of other types unmatched.
+    fn check_match(&mut self, expr: &mut Expression, statement: bool) -> Type {
+        let Expression::Match { value, arms, ty, exhaustive } = expr else { unreachable!("not a match") };
+        let span = self.span;
+        let value_ty = self.check_expression(value);
+        let variants = match &value_ty {
+            Type::Enum(name) => self.enums.get(name).cloned(),
+            _ => None,
+        };
+        let mut matched: Vec<String> = Vec::new();
+        let mut wildcard = false;
+        let mut result = Type::Unknown;
+        let mut blocks = false;
+        for MatchArm { pattern, body } in arms.iter_mut() {
+            if wildcard {
+                self.error("this `match` arm is unreachable after a `_` arm".to_string());
+            }
+            self.scopes.push(HashMap::new());
+            match pattern {
+                Pattern::Wildcard => wildcard = true,
+                Pattern::Literal(literal) => {
+                    let literal_ty = self.check_expression(literal);
+                    if !self.compatible(&literal_ty, &value_ty) && !self.compatible(&value_ty, &literal_ty) {
+                        self.error(format!("a pattern of type {} cannot match a value of type {}", literal_ty, value_ty));
+                    }
+                    if let Expression::Boolean(value) = literal {
+                        matched.push(value.to_string());
+                    }
+                },
+                Pattern::Variant { enum_name, variant, bindings } => {
+                    let payload = self.check_variant_pattern(&value_ty, enum_name, variant);
+                    if payload.is_some() && matched.contains(variant) {
+                        self.error(format!("`{}.{}` is already matched by an earlier arm", enum_name, variant));
+                    }
+                    matched.push(variant.clone());
+                    let payload = payload.unwrap_or_else(|| vec![Type::Unknown; bindings.len()]);
+                    if bindings.len() != payload.len() {
+                        self.error(format!(
+                            "`{}.{}` has {} value(s) but the pattern names {}",
+                            enum_name, variant, payload.len(), bindings.len()
+                        ));
+                    }
+                    let scope = self.scopes.last_mut().expect("pushed above");
+                    for (name, ty) in bindings.iter().zip(payload).filter(|(name, _)| *name != "_") {
+                        scope.insert(name.clone(), ty);
+                    }
+                },
+            }
+            match body {
+                MatchBody::Block(block) => {
+                    blocks = true;
+                    self.check_block(block);
+                    self.span = span;
+                },
+                MatchBody::Expression(body) => {
+                    let body_ty = self.check_expression(body);
+                    match self.unify(&result, &body_ty) {
+                        Some(unified) => result = unified,
+                        None if statement => {},
+                        None => self.error(format!("`match` arms must have the same type, found {} and {}", result, body_ty)),
+                    }
+                },
+            }
+            self.scopes.pop();
+        }
+
+        *exhaustive = wildcard
+            || match &variants {
+                Some(variants) => variants.iter().all(|(name, _)| matched.contains(name)),
+                None => value_ty == Type::Bool && matched.iter().any(|value| value == "true") && matched.iter().any(|value| value == "false"),
+            };
+        if let (Some(variants), false) = (&variants, *exhaustive) {
+            let missing: Vec<String> = variants
+                .iter()
+                .filter(|(name, _)| !matched.contains(name))
+                .map(|(name, _)| format!("`{}.{}`", value_ty, name))
+                .collect();
+            self.error(format!("`match` on {} does not cover {}; add arms for them or `_`", value_ty, missing.join(", ")));
+        } else if !statement && !*exhaustive && value_ty != Type::Unknown {
+            self.error(format!("a `match` on {} used as a value needs a `_` arm", value_ty));
+        }
+        if statement {
+            return Type::Void;
+        }
+        if blocks {
+            self.error("a `match` with `{ ... }` arms can only be used as a statement".to_string());
+            return Type::Unknown;
+        }
+        *ty = result.to_type_expr();
+        result
+    }
+
+    // Payload types of the variant a pattern names, when it can match `value_ty`
+    fn check_variant_pattern(&mut self, value_ty: &Type, enum_name: &str, variant: &str) -> Option<Vec<Type>> {
+        let Some(variants) = self.enums.get(enum_name).cloned() else {
+            self.error(format!("unknown enum `{}` in pattern `{}.{}`", enum_name, enum_name, variant));
+            return None;
+        };
+        self.use_qualified(enum_name);
+        let payload = variants.iter().find(|(name, _)| name == variant).map(|(_, payload)| payload.clone());
+        if !matches!(value_ty, Type::Unknown) && *value_ty != Type::Enum(enum_name.to_string()) {
+            self.error(format!("pattern `{}.{}` cannot match a value of type {}", enum_name, variant, value_ty));
+            return None;
+        }
+        if payload.is_none() {
+            self.error(format!("enum `{}` has no variant `{}`", enum_name, variant));
+        }
+        payload
+    }
+
+    // `(a, b) = value`: each target gets its element of the tuple
+    fn check_tuple_assignment(&mut self, targets: &mut [AssignmentTarget], value: &mut Expression) {
+        let value_ty = self.check_expression(value);
+        let targets: Vec<(Type, String)> = targets.iter_mut().map(|target| self.check_assignment_target(target)).collect();
+        let expected = Type::Tuple(targets.iter().map(|(ty, _)| ty.clone()).collect());
+        let value_ty = self.coerce_literal(value, &value_ty, &expected);
+        let element_types = self.unpack(&value_ty, targets.len(), "targets");
+        for ((target_ty, target_name), ty) in targets.iter().zip(element_types) {
+            if ty == Type::Void || !self.compatible(&ty, target_ty) {
+                self.error(format!(
+                    "cannot assign a value of type {} to {} of type {}{}",
+                    ty, target_name, target_ty, unwrap_hint(&ty, target_ty)
+                ));
+            }
+        }
+    }
+
+    // Types of the elements of a tuple unpacked into `count` variables or targets
+    fn unpack(&mut self, ty: &Type, count: usize, what: &str) -> Vec<Type> {
+        match ty {
+            Type::Tuple(elements) if elements.len() == count => elements.clone(),
+            Type::Tuple(elements) => {
+                self.error(format!("cannot unpack {} into {} {}; it has {} elements", ty, count, what, elements.len()));
+                vec![Type::Unknown; count]
+            },
+            Type::Unknown => vec![Type::Unknown; count],
+            _ => {
+                self.error(format!("cannot unpack a value of type {}; only tuples can be unpacked{}", ty, optional_hint(ty)));
+                vec![Type::Unknown; count]
+            },
+        }
+    }
+
+    // Type of what an assignment writes to, and how to name it in diagnostics
+    fn check_assignment_target(&mut self, target: &mut AssignmentTarget) -> (Type, String) {
+        match target {
+            AssignmentTarget::Variable(name) => {
+                self.check_not_captured(&Expression::Variable(name.clone()));
+                (self.resolve_variable(name), format!("`{}`", name))
+            },
+            AssignmentTarget::Field { object, name } => {
+                let object_ty = self.check_expression(object);
+                (self.field_type(&object_ty, name), format!("field `{}`", name))
+            },
+            AssignmentTarget::Index { array, index } => {
+                let array_ty = self.check_expression(array);
+                if let Type::Class(class, args) = &array_ty {
+                    if self.lookup_method(class, args, "op_index").is_some() {
+                        self.check_expression(index);
+                        self.error(format!("elements of {} cannot be assigned; `op_index` only reads them", array_ty));
+                        return (Type::Unknown, "an element".to_string());
+                    }
+                }
+                let ty = self.check_index(&array_ty, index);
+                self.check_not_captured(array);
+                (ty, "a list element".to_string())
+            },
+            AssignmentTarget::Tuple(targets) => {
+                let types = targets.iter_mut().map(|target| self.check_assignment_target(target).0).collect();
+                (Type::Tuple(types), format!("{} targets", targets.len()))
+            },
+            AssignmentTarget::Ignored => (Type::Unknown, "`_`".to_string()),
+        }
+    }
+
+    // `object.name`; `.length` and `.size` of a list become list method calls
+    fn check_field_expression(&mut self, expr: &mut Expression) -> Type {
+        let Expression::Field { object, name } = expr else { unreachable!("not a field") };
+        if let Some(enum_name) = self.enum_reference(object) {
+            let variant = std::mem::take(name);
+            *expr = Expression::EnumVariant { enum_name, variant, args: Vec::new() };
+            return self.check_expression(expr);
+        }
+        let object_ty = self.check_expression(object);
+        let list_size = matches!(object_ty, Type::List(_) | Type::Unknown) && (name == "length" || name == "size");
+        if !list_size {
+            let name = name.clone();
+            return self.field_type(&object_ty, &name);
+        }
+        let Expression::Field { object, name } = take(expr) else { unreachable!("not a field") };
+        *expr = Expression::ArrayMethodCall { array: object, method: name, args: Vec::new() };
+        Type::Int
+    }
+
+    fn field_type(&mut self, object_ty: &Type, name: &str) -> Type {
+        match object_ty {
+            Type::Class(class, args) if self.classes.contains_key(class) => {
+                if let Some(ty) = self.lookup_field(class, args, name) {
+                    return ty;
+                }
+                if self.lookup_method(class, args, name).is_some() {
+                    self.error(format!("`{}` is a method of class `{}`; call it with `{}()`", name, class, name));
+                } else {
+                    self.error(format!("class `{}` has no field `{}`", class, name));
+                }
+                Type::Unknown
+            },
+            // Classes of other modules are not checked
+            Type::Class(..) | Type::Unknown => Type::Unknown,
+            Type::Optional(_) => {
+                self.error(format!("cannot use field `{}` of a value of type {}{}", name, object_ty, optional_hint(object_ty)));
+                Type::Unknown
+            },
+            _ => {
+                self.error(format!("a value of type {} has no field `{}`", object_ty, name));
+                Type::Unknown
+            },
+        }
+    }
+
+    // `a[i]`; on a class value with an `op_index` method it becomes a call of that
+    // method, as C++ `operator[]` cannot be added to the `std::shared_ptr` of a class
+    fn check_array_access(&mut self, expr: &mut Expression) -> Type {
+        let Expression::ArrayAccess { array, index } = expr else { unreachable!("not an index") };
+        let array_ty = self.check_expression(array);
+        let overloaded = match &array_ty {
+            Type::Class(class, args) => self.lookup_method(class, args, "op_index").map(|method| (class.clone(), method)),
+            _ => None,
+        };
+        if let Some((class, (overloads, bindings))) = overloaded {
+            let callee = format!("`op_index` of class `{}`", class);
+            let Expression::ArrayAccess { array, index } = take(expr) else { unreachable!("not an index") };
+            *expr = Expression::MethodCall { object: array, method: "op_index".to_string(), args: vec![*index] };
+            let Expression::MethodCall { args, .. } = expr else { unreachable!("just built") };
+            return self.check_overloaded_call(&callee, &overloads, bindings, args);
+        }
+        self.check_index(&array_ty, index)
+    }
+
+    fn check_index(&mut self, array_ty: &Type, index: &mut Expression) -> Type {
+        let index_ty = self.check_expression(index);
+        if !self.compatible(&index_ty, &Type::Int) {
+            self.error(format!("list index must be an Int, found {}", index_ty));
+        }
+        match array_ty {
+            Type::List(element) => (**element).clone(),
+            Type::Unknown => Type::Unknown,
+            _ => {
+                let hint = operator_hint(array_ty, Some("op_index")).unwrap_or_else(|| optional_hint(array_ty).to_string());
+                self.error(format!("cannot index into a value of type {}{}", array_ty, hint));
+                Type::Unknown
+            },
+        }
+    }
+
+    fn resolve_variable(&mut self, name: &str) -> Type {
+        if let Some(ty) = self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            return ty.clone();
+        }
+        if let Some(overloads) = self.functions.get(name) {
+            match overloads.as_slice() {
+                [signature] if signature.type_params.is_empty() => {
+                    return Type::Function(signature.params.clone(), Box::new(signature.returns.clone()));
+                },
+                [_] => self.error(format!("generic function `{}` cannot be used as a value; wrap the call in a lambda", name)),
+                _ => self.error(format!("`{}` is overloaded and cannot be used as a value; wrap the call in a lambda", name)),
+            }
+            return Type::Unknown;
+        }
+        if name == "this" {
+            self.error("`this` can only be used in the methods of a class".to_string());
+        } else if self.hidden_scopes.iter().any(|scope| scope.contains_key(name)) {
+            self.error(format!(
+                "undefined variable `{}`; functions cannot use the variables of the file, pass the value as a parameter",
+                name
+            ));
+        } else {
+            self.error(format!("undefined variable `{}`", name));
+        }
+        Type::Unknown
+    }
+
+    // `function(args)`: a call of a function or a new instance of a class
+    fn check_function_call(&mut self, expr: &mut Expression) -> Type {
+        let Expression::Call { function, type_args, args } = expr else { unreachable!("not a call") };
+
+        if let Some(ty) = self.scopes.iter().rev().find_map(|scope| scope.get(function.as_str())).cloned() {
+            if !type_args.is_empty() {
+                self.error(format!("`{}` is not generic and takes no type arguments", function));
+                type_args.clear();
+            }
+            let callee = format!("`{}`", function);
+            return self.check_value_call(&callee, &ty, args);
+        }
+        if let Some(overloads) = self.functions.get(function.as_str()).cloned() {
+            let callee = format!("`{}`", function);
+            let [signature] = overloads.as_slice() else {
+                if !type_args.is_empty() {
+                    self.error(format!("`{}` is not generic and takes no type arguments", function));
+                    type_args.clear();
+                }
+                return self.check_overloaded_call(&callee, &overloads, HashMap::new(), args);
+            };
+            if !type_args.is_empty() && signature.type_params.is_empty() {
+                self.error(format!("`{}` is not generic and takes no type arguments", function));
+                type_args.clear();
+            }
+            let (returns, inferred) = self.check_signature_call(&callee, signature, HashMap::new(), type_args, args);
+            if type_args.is_empty() {
+                // Spell out what was inferred; C++ cannot deduce a type parameter used only in the result
+                if let Some(inferred) = inferred.iter().map(Type::to_type_expr).collect::<Option<Vec<_>>>() {
+                    *type_args = inferred;
+                }
+            }
+            return returns;
+        }
+
+        let Some(class) = self.classes.get(function.as_str()).cloned() else {
+            self.error(format!("undefined function `{}`", function));
+            for arg in args.iter_mut() {
+                self.check_expression(arg);
+            }
+            return Type::Unknown;
+        };
+        let this = Type::Class(function.clone(), class.type_params.iter().cloned().map(Type::Param).collect());
+        let params = class.init.map(|init| init.params).unwrap_or_default();
+        let init = Signature { type_params: class.type_params, bounds: class.bounds, params, returns: this };
+        let callee = format!("`{}`", function);
+        let errors = self.diagnostics.len();
+        let (ty, inferred) = self.check_signature_call(&callee, &init, HashMap::new(), type_args, args);
+        if type_args.is_empty() {
+            match inferred.iter().map(Type::to_type_expr).collect::<Option<Vec<_>>>() {
+                Some(inferred) => *type_args = inferred,
+                None if self.diagnostics.len() == errors => {
+                    self.error(format!(
+                        "cannot infer the type arguments of `{}`; give them explicitly, like `{}[Int](...)`",
+                        function, function
+                    ));
+                },
+                None => {},
+            }
+        }
+        let Expression::Call { function, type_args, args } = take(expr) else { unreachable!("not a call") };
+        *expr = Expression::Construct { class: function, type_args, args };
+        ty
+    }
+
+    // Check a call of `signature` and return its result type together with the
+    // type arguments of the callee's own type parameters. `bindings` holds the
+    // type arguments of the receiver's class, `explicit` those given at the call.
+    fn check_signature_call(
+        &mut self,
+        callee: &str,
+        signature: &Signature,
+        bindings: HashMap<String, Type>,
+        explicit: &[TypeExpr],
+        args: &mut [Expression],
+    ) -> (Type, Vec<Type>) {
+        let arg_types: Vec<Type> = args.iter_mut().map(|arg| self.check_expression(arg)).collect();
+        self.check_signature_arguments(callee, signature, bindings, explicit, args, arg_types)
+    }
+
+    // check_signature_call for arguments that were already checked
+    fn check_signature_arguments(
+        &mut self,
+        callee: &str,
+        signature: &Signature,
+        mut bindings: HashMap<String, Type>,
+        explicit: &[TypeExpr],
+        args: &mut [Expression],
+        arg_types: Vec<Type>,
+    ) -> (Type, Vec<Type>) {
+        if !explicit.is_empty() {
+            if explicit.len() != signature.type_params.len() {
+                self.error(format!(
+                    "{} takes {} type argument(s) but {} were given",
+                    callee,
+                    signature.type_params.len(),
+                    explicit.len()
+                ));
+            }
+            let type_params = self.type_params();
+            for (param, ty) in signature.type_params.iter().zip(explicit) {
+                let ty = self.resolve_type(ty, &type_params);
+                bindings.insert(param.clone(), ty);
+            }
+        } else {
+            let mut inferred = HashMap::new();
+            for (param, arg) in signature.params.iter().zip(&arg_types) {
+                infer(param, arg, &signature.type_params, &mut inferred);
+            }
+            for param in &signature.type_params {
+                if let Some(ty) = inferred.remove(param) {
+                    bindings.insert(param.clone(), ty);
+                } else if !bindings.contains_key(param) && !signature.params.iter().any(|ty| ty.mentions(param)) {
+                    let message = format!("cannot infer type parameter `{}` of {}", param, callee);
+                    match callee.strip_prefix('`').and_then(|name| name.strip_suffix('`')) {
+                        Some(name) => self.error(format!("{}; give it explicitly, like `{}[Int](...)`", message, name)),
+                        None => self.error(message),
+                    }
+                }
+            }
+        }
+        for param in &signature.type_params {
+            bindings.entry(param.clone()).or_insert(Type::Unknown);
+        }
+        for (param, interface) in &signature.bounds {
+            if !self.implements(&bindings[param], interface) {
+                self.error(format!(
+                    "{} does not implement interface `{}`, which {} requires of `{}`",
+                    bindings[param], interface, callee, param
+                ));
+            }
+        }
+
+        let params: Vec<Type> = signature.params.iter().map(|param| param.substitute(&bindings)).collect();
+        let arg_types = self.coerce_literals(args, &arg_types, &params);
+        self.check_arguments(callee, &params, &arg_types);
+        let type_args = signature.type_params.iter().map(|param| bindings[param].clone()).collect();
+        (signature.returns.substitute(&bindings), type_args)
+    }
+
+    // Pick the overload that fits the arguments best, ranking conversions like
+    // C++ does so both pick the same one, and check the call against it
+    fn check_overloaded_call(
+        &mut self,
+        callee: &str,
+        overloads: &[Signature],
+        bindings: HashMap<String, Type>,
+        args: &mut [Expression],
+    ) -> Type {
+        if let [signature] = overloads {
+            return self.check_signature_call(callee, signature, bindings, &[], args).0;
+        }
+        let arg_types: Vec<Type> = args.iter_mut().map(|arg| self.check_expression(arg)).collect();
+        let params: Vec<Vec<Type>> = overloads
+            .iter()
+            .map(|signature| signature.params.iter().map(|param| param.substitute(&bindings)).collect())
+            .collect();
+        match self.select_overload(&params, &arg_types) {
+            Ok(index) => {
+                self.convert_arguments(args, &arg_types, &params[index]);
+                return self.check_signature_arguments(callee, &overloads[index], bindings, &[], args, arg_types).0;
+            },
+            // Runtime values of unknown type may well pick one in C++
+            Err(_) if arg_types.contains(&Type::Unknown) => {},
+            Err(candidates) => {
+                let args = describe_params(&arg_types);
+                if candidates.is_empty() {
+                    let mut overloads: Vec<String> = params.iter().map(|params| describe_params(params)).collect();
+                    let last = overloads.pop().unwrap_or_default();
+                    self.error(format!("no overload of {} takes {}; it takes {} or {}", callee, args, overloads.join(", "), last));
+                } else {
+                    let tied: Vec<String> = candidates.iter().map(|index| describe_params(&params[*index])).collect();
+                    self.error(format!("call of {} with {} is ambiguous between {}", callee, args, tied.join(" and ")));
+                }
+            },
+        }
+        overloads_result(overloads, &bindings)
+    }
+
+    // The overload whose parameters are the best match for the arguments, or
+    // all those the arguments are compatible with when there is none
+    fn select_overload(&self, params: &[Vec<Type>], arg_types: &[Type]) -> Result<usize, Vec<usize>> {
+        let candidates: Vec<(usize, Vec<u32>)> = params
+            .iter()
+            .enumerate()
+            .filter(|(_, params)| {
+                params.len() == arg_types.len() && arg_types.iter().zip(params.iter()).all(|(arg, param)| self.compatible(arg, param))
+            })
+            .map(|(index, params)| (index, arg_types.iter().zip(params).map(|(arg, param)| self.conversion_rank(arg, param)).collect()))
+            .collect();
+        let best = candidates.iter().find(|(index, ranks)| {
+            candidates.iter().all(|(other, other_ranks)| other == index || ranks.iter().zip(other_ranks).all(|(a, b)| a <= b) && ranks != other_ranks)
+        });
+        match best {
+            Some(&(index, _)) => Ok(index),
+            None => Err(candidates.into_iter().map(|(index, _)| index).collect()),
+        }
+    }
+
+    // Convert the arguments of a call of an overloaded function to the parameter
+    // types of the overload picked, as C++ ranks some conversions differently:
+    // it finds `f(A)` and `f(B)` equally good for a class derived from `B`, and
+    // `f(Float)` and `f(Bool)` for an Int
+    fn convert_arguments(&self, args: &mut [Expression], arg_types: &[Type], params: &[Type]) {
+        for ((arg, arg_ty), param) in args.iter_mut().zip(arg_types).zip(params) {
+            // List and tuple literals take the parameter type themselves
+            let literal = matches!(arg, Expression::ArrayLiteral { .. } | Expression::Tuple { .. });
+            if literal || same_type(arg_ty, param) || *arg_ty == Type::Unknown {
+                continue;
+            }
+            if let Some(ty) = param.to_type_expr() {
+                *arg = Expression::Convert { value: Box::new(take(arg)), ty };
+            }
+        }
+    }
+
+    // `a + b`, `a == b` or `-a` on a class value that declares the operator
+    // method; `None` when it does not
+    fn check_operator_call(
+        &mut self,
+        symbol: &str,
+        method: &str,
+        operand: &Type,
+        args: &mut [Expression],
+        arg_types: &[Type],
+    ) -> Option<Type> {
+        let Type::Class(class, class_args) = operand else { return None };
+        let (overloads, bindings) = self.lookup_method(class, class_args, method)?;
+        let params: Vec<Vec<Type>> = overloads
+            .iter()
+            .map(|signature| signature.params.iter().map(|param| param.substitute(&bindings)).collect())
+            .collect();
+        match self.select_overload(&params, arg_types) {
+            Ok(index) => {
+                if overloads.len() > 1 {
+                    self.convert_arguments(args, arg_types, &params[index]);
+                }
+                return Some(overloads[index].returns.substitute(&bindings));
+            },
+            Err(_) if arg_types.contains(&Type::Unknown) => {},
+            Err(candidates) => {
+                let takes: Vec<String> = params.iter().map(|params| describe_params(params)).collect();
+                let operands = match arg_types {
+                    [right] => format!("{} and {}", operand, right),
+                    _ => operand.to_string(),
+                };
+                if candidates.is_empty() {
+                    self.error(format!(
+                        "operator `{}` is not defined for {}; `{}` of class `{}` takes {}",
+                        symbol, operands, method, class, takes.join(" or ")
+                    ));
+                } else {
+                    let tied: Vec<String> = candidates.iter().map(|index| takes[*index].clone()).collect();
+                    self.error(format!("operator `{}` for {} is ambiguous between {}", symbol, operands, tied.join(" and ")));
+                }
+            },
+        }
+        Some(overloads_result(&overloads, &bindings))
+    }
+
+    // How far an argument is from a parameter it is compatible with: 0 for the
+    // same type, the number of inheritance steps for a base class, and
+    // conversions to an optional rank last as they are user-defined in C++
+    fn conversion_rank(&self, arg: &Type, param: &Type) -> u32 {
+        match (arg, param) {
+            _ if same_type(arg, param) => 0,
+            (Type::Optional(_), _) | (_, Type::Optional(_)) => 1000,
+            (Type::Class(class, args), Type::Class(base, _)) => {
+                let ancestry = self.ancestry(class, args);
+                ancestry.iter().position(|(ancestor, _)| ancestor == base).map_or(1, |steps| steps as u32)
+            },
+            _ => 1,
+        }
+    }
+
+    // `object.method(args)`; method calls on lists become list method calls
+    fn check_method_call(&mut self, expr: &mut Expression) -> Type {
+        let Expression::MethodCall { object, method, args } = expr else { unreachable!("not a method call") };
+        if let Some(enum_name) = self.enum_reference(object) {
+            let variant = std::mem::take(method);
+            let args = std::mem::take(args);
+            *expr = Expression::EnumVariant { enum_name, variant, args };
+            return self.check_expression(expr);
+        }
+        let object_ty = self.check_expression(object);
+
+        if matches!(object_ty, Type::List(_) | Type::Unknown) && LIST_METHODS.contains(&method.as_str()) {
+            let Expression::MethodCall { object, method, args } = take(expr) else { unreachable!("not a method call") };
+            *expr = Expression::ArrayMethodCall { array: object, method, args };
+            let Expression::ArrayMethodCall { array, method, args } = expr else { unreachable!("not a list method call") };
+            if method == "append" || method == "set" {
+                self.check_not_captured(array);
+            }
+            return self.check_list_method(&object_ty, method, args);
+        }
+
+        if let Some((extension, bindings)) = self.find_extension(&object_ty, method) {
+            let Expression::MethodCall { object, method, mut args } = take(expr) else { unreachable!("not a method call") };
+            let arg_types: Vec<Type> = args.iter_mut().map(|arg| self.check_expression(arg)).collect();
+            let callee = format!("method `{}`", method);
+            let (returns, inferred) =
+                self.check_signature_arguments(&callee, &extension.signature, bindings, &[], &mut args, arg_types);
+            let type_args = inferred.iter().map(Type::to_type_expr).collect::<Option<Vec<_>>>().unwrap_or_default();
+            let args = std::iter::once(*object).chain(args).collect();
+            *expr = Expression::ExtensionCall { function: extension.function, type_args, args };
+            return returns;
+        }
+
+        match &object_ty {
+            Type::Class(class, type_args) if self.classes.contains_key(class) => {
+                let Some((overloads, bindings)) = self.lookup_method(class, type_args, method) else {
+                    let field = self.lookup_field(class, type_args, method);
+                    if let Some(ty @ Type::Function(..)) = field {
+                        return self.check_value_call(&format!("field `{}`", method), &ty, args);
+                    }
+                    let message = if field.is_some() {
+                        format!("`{}` is a field of class `{}`, not a method", method, class)
+                    } else {
+                        format!("class `{}` has no method `{}`", class, method)
+                    };
+                    self.error(message);
+                    self.check_each(args);
+                    return Type::Unknown;
+                };
+                let callee = format!("method `{}`", method);
+                self.check_overloaded_call(&callee, &overloads, bindings, args)
+            },
+            Type::Optional(_) => {
+                self.error(format!("cannot call `{}` on a value of type {}{}", method, object_ty, optional_hint(&object_ty)));
+                self.check_each(args);
+                Type::Unknown
+            },
+            // A method of an interface bounding the type parameter
+            Type::Param(param) => {
+                let interfaces: Vec<String> = self.function.as_ref().map_or_else(Vec::new, |function| {
+                    function.bounds.iter().filter(|(bounded, _)| bounded == param).map(|(_, bound)| bound.clone()).collect()
+                });
+                let required = interfaces.iter().find_map(|interface| self.interfaces.get(interface)?.get(method.as_str()).cloned());
+                if let Some(signature) = required {
+                    let callee = format!("method `{}`", method);
+                    let itself = HashMap::from([("Self".to_string(), object_ty.clone())]);
+                    return self.check_overloaded_call(&callee, &[signature], itself, args);
+                }
+                if interfaces.is_empty() {
+                    self.error(format!(
+                        "cannot call `{}` on a value of type parameter `{}`; bound it by an interface, like `[{}: Shape]`",
+                        method, param, param
+                    ));
+                } else {
+                    self.error(format!("no interface bounding `{}` declares a method `{}`", param, method));
+                }
+                self.check_each(args);
+                Type::Unknown
+            },
+            Type::Class(..) | Type::Unknown | Type::Stream | Type::Meaning => {
+                self.check_each(args);
+                Type::Unknown
+            },
+            _ => {
+                self.error(format!("a value of type {} has no method `{}`", object_ty, method));
+                self.check_each(args);
+                Type::Unknown
+            },
+        }
+    }
+
+    // `super.method(args)`; the leading `super(...)` of an `init` is checked by check_base_init
+    fn check_super_call(&mut self, method: &str, args: &mut [Expression]) -> Type {
+        let Some(Type::Class(class, type_args)) = self.function.as_ref().and_then(|function| function.this.clone()) else {
+            self.error("`super` can only be used in the methods of a class".to_string());
+            self.check_each(args);
+            return Type::Unknown;
+        };
+        let Some((base, base_args)) = self.ancestry(&class, &type_args).into_iter().nth(1) else {
+            self.error(format!("class `{}` has no base class", class));
+            self.check_each(args);
+            return Type::Unknown;
+        };
+        if method == "init" {
+            self.error("`super(...)` can only be the first statement of `init`".to_string());
+            self.check_each(args);
+            return Type::Void;
+        }
+        let Some((overloads, bindings)) = self.lookup_method(&base, &base_args, method) else {
+            self.error(format!("base class `{}` has no method `{}`", base, method));
+            self.check_each(args);
+            return Type::Unknown;
+        };
+        let callee = format!("method `{}`", method);
+        self.check_overloaded_call(&callee, &overloads, bindings, args)
+    }
+
+    // A call of a function value held by a variable or field
+    fn check_value_call(&mut self, callee: &str, ty: &Type, args: &mut [Expression]) -> Type {
+        let arg_types: Vec<Type> = args.iter_mut().map(|arg| self.check_expression(arg)).collect();
+        match ty {
+            Type::Function(params, returns) => {
+                let arg_types = self.coerce_literals(args, &arg_types, params);
+                self.check_arguments(callee, params, &arg_types);
+                (**returns).clone()
+            },
+            Type::Unknown => Type::Unknown,
+            _ => {
+                self.error(format!("{} has type {} and cannot be called{}", callee, ty, optional_hint(ty)));
+                Type::Unknown
+            },
+        }
+    }
+
+    fn check_each(&mut self, args: &mut [Expression]) {
+        for arg in args {
+            self.check_expression(arg);
+        }
+    }
+
+    fn check_unary(&mut self, operator: UnaryOperator, operand: &Type) -> Type {
+        let overloaded = operator.method().and_then(|method| self.check_operator_call(operator.symbol(), method, operand, &mut [], &[]));
+        if let Some(result) = overloaded {
+            return result;
+        }
+        match (operator, operand) {
+            (_, Type::Unknown) => Type::Unknown,
+            (UnaryOperator::Neg, Type::Int | Type::Float | Type::Param(_)) => operand.clone(),
+            (UnaryOperator::Not, Type::Bool) => Type::Bool,
+            _ => {
+                self.error(format!(
+                    "operator `{}` is not defined for {}{}",
+                    operator.symbol(), operand, operator_hint(operand, operator.method()).unwrap_or_else(|| optional_hint(operand).to_string())
+                ));
+                match operator {
+                    UnaryOperator::Neg => Type::Unknown,
+                    UnaryOperator::Not => Type::Bool,
+                }
+            },
+        }
+    }
+
+    // `right_operand` is converted when it picks one of several overloads of an operator method
+    fn check_binary(&mut self, operator: BinaryOperator, left: &Type, right: &Type, right_operand: &mut Expression) -> Type {
+        use BinaryOperator::*;
+        let boolean = matches!(operator, Eq | Ne | Lt | Le | Gt | Ge | And | Or);
+        if operator == Coalesce {
+            return self.check_coalesce(left, right);
+        }
+        if *left == Type::Unknown || *right == Type::Unknown {
+            return if boolean { Type::Bool } else { Type::Unknown };
+        }
+        let overloaded = operator.method().and_then(|method| {
+            self.check_operator_call(operator.symbol(), method, left, std::slice::from_mut(right_operand), std::slice::from_ref(right))
+        });
+        if let Some(result) = overloaded {
+            return if operator == Ne { Type::Bool } else { result };
+        }
+        let same_param = matches!((left, right), (Type::Param(a), Type::Param(b)) if a == b);
+        let result = match operator {
+            Add if *left == Type::String && *right == Type::String => Some(Type::String),
+            Add if *left == Type::String && right.is_numeric() || left.is_numeric() && *right == Type::String => {
+                let number = if left.is_numeric() { left } else { right };
+                self.error(format!("cannot add String and {}; convert the number with `.to_string()` first", number));
+                return Type::String;
+            },
+            Add | Sub | Mul | Div => match (left, right) {
+                (Type::Int, Type::Int) => Some(Type::Int),
+                _ if left.is_numeric() && right.is_numeric() => Some(Type::Float),
+                _ if same_param => Some(left.clone()),
+                _ => None,
+            },
+            Mod => match (left, right) {
+                (Type::Int, Type::Int) => Some(Type::Int),
+                _ if same_param => Some(left.clone()),
+                _ => None,
+            },
+            Lt | Le | Gt | Ge => {
+                let ordered = left.is_numeric() && right.is_numeric()
+                    || *left == Type::String && *right == Type::String
+                    || same_param;
+                ordered.then_some(Type::Bool)
+            },
+            And | Or => (*left == Type::Bool && *right == Type::Bool).then_some(Type::Bool),
+            Eq | Ne => {
+                // Any two values of the same type can be compared, and optionals with `none`
+                if matches!(left, Type::Function(..)) || matches!(right, Type::Function(..)) {
+                    self.error("functions cannot be compared".to_string());
+                } else if matches!(left, Type::Tuple(_)) && holds_function(left) {
+                    self.error(format!("values of {} cannot be compared, since they hold a function", left));
+                } else if let Some(variant) = self.variant_holding_function(left) {
+                    self.error(format!("values of {} cannot be compared, since variant `{}` holds a function", left, variant));
+                } else if !self.compatible(left, right) && !self.compatible(right, left) {
+                    self.error(format!("cannot compare {} with {}", left, right));
+                }
+                return Type::Bool;
+            },
+            Coalesce => unreachable!("checked by check_coalesce"),
+        };
+        result.unwrap_or_else(|| {
+            let optional = if matches!(left, Type::Optional(_)) { left } else { right };
+            let hint = operator_hint(left, operator.method()).unwrap_or_else(|| optional_hint(optional).to_string());
+            self.error(format!("operator `{}` is not defined for {} and {}{}", operator.symbol(), left, right, hint));
+            if boolean { Type::Bool } else { Type::Unknown }
+        })
+    }
+
+    // `optional ?? default`: the value inside the optional, or the default when it is none
+    fn check_coalesce(&mut self, left: &Type, right: &Type) -> Type {
+        let inner = match left {
+            Type::Optional(inner) => (**inner).clone(),
+            Type::Unknown => return right.clone(),
+            _ => {
+                self.error(format!("the left side of `??` must be optional, found {}", left));
+                return left.clone();
+            },
+        };
+        if self.compatible(right, &inner) {
+            return if inner == Type::Unknown { right.clone() } else { inner };
+        }
+        if self.compatible(right, left) {
+            return left.clone();
+        }
+        self.error(format!("the default of `??` must be {}, found {}", inner, right));
+        inner
+    }
+
+    fn check_list_method(&mut self, list_ty: &Type, method: &str, args: &mut [Expression]) -> Type {
+        let element = match list_ty {
+            Type::List(element) => (**element).clone(),
+            Type::Unknown => Type::Unknown,
+            _ => {
+                self.error(format!("`{}` is a list method but the value has type {}", method, list_ty));
+                Type::Unknown
+            },
+        };
+        let arg_types: Vec<Type> = args.iter_mut().map(|arg| self.check_expression(arg)).collect();
+
+        let (params, returns) = match method {
+            "append" => (vec![element], Type::Void),
+            "length" | "size" => (vec![], Type::Int),
+            "get" | "at" => (vec![Type::Int], element),
+            "set" => (vec![Type::Int, element], Type::Void),
+            _ => return Type::Unknown,
+        };
+        let arg_types = self.coerce_literals(args, &arg_types, &params);
+        self.check_arguments(&format!("list method `{}`", method), &params, &arg_types);
+        returns
+    }
+
+    // `module.function(args)` of a project module: a call of one of its functions,
+    // a new instance of one of its classes or a call of a function value it holds
+    fn check_module_call(&mut self, expr: &mut Expression) -> Type {
+        let Expression::FunctionCall { module, function, args } = expr else { unreachable!("not a module call") };
+        self.check_imported(module);
+        self.used_modules.insert(module.clone());
+        let name = format!("{}.{}", module, function);
+        if self.functions.contains_key(&name) || self.classes.contains_key(&name) {
+            *expr = Expression::Call { function: name, type_args: Vec::new(), args: std::mem::take(args) };
+            return self.check_function_call(expr);
+        }
+        match self.modules[module.as_str()].variables.get(function.as_str()).map(|ty| ty.qualify(module)) {
+            Some(ty @ Type::Function(..)) => self.check_value_call(&format!("`{}`", name), &ty, args),
+            variable => {
+                for arg in args.iter_mut() {
+                    self.check_expression(arg);
+                }
+                if variable.is_some() {
+                    self.error(format!("`{}` is a variable, not a function", name));
+                } else {
+                    self.error(format!("module `{}` has no function `{}`", module, function));
+                }
+                Type::Unknown
+            },
+        }
+    }
+
+    fn check_call(&mut self, module: &str, function: &str, args: &mut [Expression]) -> Type {
+        let arg_types: Vec<Type> = args.iter_mut().map(|arg| self.check_expression(arg)).collect();
+
+        if !runtime::is_runtime_module(module) {
+            self.report_unknown_module(module);
+            return Type::Unknown;
+        }
+
+        self.check_imported(module);
+        self.used_modules.insert(module.to_string());
+
+        if let Some((_, _, ty)) = RUNTIME_CONSTANTS.iter().find(|(m, n, _)| *m == module && *n == function) {
+            return ty.clone();
+        }
+
+        match RUNTIME_FUNCTIONS.iter().find(|f| f.module == module && f.name == function) {
+            Some(signature) => {
+                self.check_arguments(&format!("`{}.{}`", module, function), signature.params, &arg_types);
+                signature.returns.clone()
+            },
+            None => {
+                self.error(format!("module `{}` has no function `{}`", module, function));
+                Type::Unknown
+            }
+        }
+    }
+
+    // `module.name` without a call: a runtime constant, or a variable or function
+    // of a project module
+    fn check_member(&mut self, module: &str, name: &str) -> Type {
+        if let Some(exports) = self.modules.get(module) {
+            let ty = exports.variables.get(name).map(|ty| ty.qualify(module));
+            self.check_imported(module);
+            self.used_modules.insert(module.to_string());
+            if let Some(ty) = ty {
+                return ty;
+            }
+            let qualified = format!("{}.{}", module, name);
+            match self.functions.get(&qualified).map(Vec::as_slice) {
+                Some([signature]) if signature.type_params.is_empty() => {
+                    return Type::Function(signature.params.clone(), Box::new(signature.returns.clone()));
+                },
+                Some([_]) => self.error(format!("generic function `{}` cannot be used as a value; wrap the call in a lambda", qualified)),
+                Some(_) => self.error(format!("`{}` is overloaded and cannot be used as a value; wrap the call in a lambda", qualified)),
+                None => self.error(format!("module `{}` has no variable or function `{}`", module, name)),
+            }
+            return Type::Unknown;
+        }
+
+        if runtime::is_runtime_module(module) {
+            self.check_imported(module);
+            self.used_modules.insert(module.to_string());
+            if let Some((_, _, ty)) = RUNTIME_CONSTANTS.iter().find(|(m, n, _)| *m == module && *n == name) {
+                return ty.clone();
+            }
+            self.error(format!("module `{}` has no constant `{}`", module, name));
+            return Type::Unknown;
+        }
+
+        self.report_unknown_module(module);
+        Type::Unknown
+    }
+
+    fn report_unknown_module(&mut self, module: &str) {
+        let root = module.split('.').next().unwrap_or(module);
+        if !self.imports.contains_key(module) && !self.imports.contains_key(root) {
+            self.error(format!("unknown module `{}`", module));
+        } else if self.modules.is_empty() {
+            self.error(format!("module `{}` is not available in the nymyac runtime", module));
+        } else {
+            self.error(format!("module `{}` is neither a runtime module nor a module of this project", module));
+        }
+    }
+
+    // Runtime headers are only included for imported modules
+    fn check_imported(&mut self, module: &str) {
+        let imported = self.imports.keys().any(|import| import == module || module.starts_with(&format!("{}.", import)));
+        if !imported && self.missing_imports.insert(module.to_string()) {
+            self.error(format!("`{}` is used without `import {}`", module, module));
+        }
+    }
+
+    // An import is used when the program uses the module itself or one of its submodules
+    fn report_unused_imports(&mut self) {
+        for (import, span) in &self.imports {
+            let used = self.used_modules.iter().any(|used| used == import || used.starts_with(&format!("{}.", import)));
+            if !used {
+                self.diagnostics.push(Diagnostic::warning(format!("unused import `{}`", import)).at(*span));
+            }
+        }
+    }
+
+    // `Unknown` is compatible with everything so that missing inference never causes
+    // errors, an Int can be used where a Float is expected, a `T` where a `T?` is
+    // expected and a class where one of its base classes is expected. A tuple
+    // converts element by element, like `std::tuple`; list and class type
+    // arguments must match exactly, since C++ containers do not convert.
+    fn compatible(&self, actual: &Type, expected: &Type) -> bool {
+        match (actual, expected) {
+            (Type::Int, Type::Float) => true,
+            (Type::Tuple(actual), Type::Tuple(expected)) => {
+                actual.len() == expected.len() && actual.iter().zip(expected).all(|(a, e)| self.compatible(a, e))
+            },
+            (Type::Optional(actual), Type::Optional(expected)) => same_type(actual, expected) || self.upcasts(actual, expected),
+            (_, Type::Optional(inner)) => self.compatible(actual, inner),
+            _ => same_type(actual, expected) || self.upcasts(actual, expected),
+        }
+    }
+
+    // Whether `actual` is a class derived from the class `expected`
+    fn upcasts(&self, actual: &Type, expected: &Type) -> bool {
+        let (Type::Class(class, args), Type::Class(base, base_args)) = (actual, expected) else { return false };
+        self.ancestry(class, args).iter().skip(1).any(|(ancestor, args)| {
+            ancestor == base && args.iter().zip(base_args).all(|(a, e)| same_type(a, e))
+        })
+    }
+
+    // The nearest class both values can be used as, for list literals like `[Circle(1.0), Square(2.0)]`
+    fn common_ancestor(&self, left: &Type, right: &Type) -> Option<Type> {
+        let Type::Class(class, args) = left else { return None };
+        self.ancestry(class, args)
+            .into_iter()
+            .map(|(ancestor, args)| Type::Class(ancestor, args))
+            .find(|ancestor| same_type(right, ancestor) || self.upcasts(right, ancestor))
+    }
+
+    // Give a list literal the element type its destination expects, so `[]`
+    // becomes a list of the right type and `[Circle(1.0)]` a list of its base
+    // class. A tuple literal takes the expected type and coerces its elements.
+    fn coerce_literal(&self, expr: &mut Expression, actual: &Type, expected: &Type) -> Type {
+        if let (Expression::Tuple { elements, ty }, Type::Tuple(actual_types), Type::Tuple(expected_types)) =
+            (&mut *expr, actual, expected)
+        {
+            if elements.len() != expected_types.len() {
+                return actual.clone();
+            }
+            if let Some(expected) = expected.to_type_expr() {
+                *ty = Some(expected);
+            }
+            return Type::Tuple(self.coerce_literals(elements, actual_types, expected_types));
+        }
+        let (Expression::ArrayLiteral { element_type, .. }, Type::List(element)) = (expr, expected) else {
+            return actual.clone();
+        };
+        if let Some(ty) = element.to_type_expr() {
+            *element_type = Some(ty);
+        }
+        match actual {
+            Type::List(actual) if self.upcasts(actual, element) => expected.clone(),
+            _ => actual.clone(),
+        }
+    }
+
+    fn coerce_literals(&self, args: &mut [Expression], arg_types: &[Type], params: &[Type]) -> Vec<Type> {
+        let mut arg_types = arg_types.to_vec();
+        for ((arg, ty), param) in args.iter_mut().zip(arg_types.iter_mut()).zip(params) {
+            *ty = self.coerce_literal(arg, ty, param);
+        }
+        arg_types
+    }
+
+    fn check_arguments(&mut self, callee: &str, params: &[Type], args: &[Type]) {
+        if params.len() != args.len() {
+            self.error(format!("{} takes {} argument(s) but {} were given", callee, params.len(), args.len()));
+            return;
+        }
+        for (position, (param, arg)) in params.iter().zip(args).enumerate() {
+            if !self.compatible(arg, param) {
+                self.error(format!(
+                    "argument {} of {} must be {}, found {}{}",
+                    position + 1, callee, param, arg, unwrap_hint(arg, param)
+                ));
+            }
+        }
+    }
+}
+
+// Why `signature` cannot be added to the overloads of a function or method
+fn overload_conflict(overloads: &[Signature], signature: &Signature) -> Option<&'static str> {
+    if overloads.is_empty() {
+        return None;
+    }
+    if !signature.type_params.is_empty() || overloads.iter().any(|overload| !overload.type_params.is_empty()) {
+        return Some("is generic and cannot be overloaded");
+    }
+    let duplicate = overloads.iter().any(|overload| same_params(&overload.params, &signature.params));
+    duplicate.then_some("is already declared with the same parameter types")
+}
+
+fn same_params(left: &[Type], right: &[Type]) -> bool {
+    left.len() == right.len() && left.iter().zip(right).all(|(a, b)| same_type(a, b))
+}
+
+// `(Int, String)`, for overload diagnostics
+fn describe_params(params: &[Type]) -> String {
+    let params: Vec<String> = params.iter().map(Type::to_string).collect();
+    format!("({})", params.join(", "))
+}
+
+// Result of a call that matched no single overload: the shared return
+// type, if the overloads agree on one
+fn overloads_result(overloads: &[Signature], bindings: &HashMap<String, Type>) -> Type {
+    let returns = overloads[0].returns.substitute(bindings);
+    let same_returns = overloads.iter().all(|signature| same_type(&signature.returns.substitute(bindings), &returns));
+    if same_returns { returns } else { Type::Unknown }
+}
+
+// Type arguments of a class instance by type parameter name
+fn bindings(type_params: &[String], type_args: &[Type]) -> HashMap<String, Type> {
+    type_params.iter().cloned().zip(type_args.iter().cloned()).collect()
+}
+
+// Bind the type parameters in `param` by matching it against the argument type
+fn infer(param: &Type, arg: &Type, type_params: &[String], inferred: &mut HashMap<String, Type>) {
+    match (param, arg) {
+        (_, Type::Unknown) => {},
+        (Type::Param(name), _) if type_params.contains(name) => match inferred.get(name) {
+            None | Some(Type::Unknown) => {
+                inferred.insert(name.clone(), arg.clone());
+            },
+            // `pair(1, 2.5)` widens to Float
+            Some(Type::Int) if *arg == Type::Float => {
+                inferred.insert(name.clone(), Type::Float);
+            },
+            Some(_) => {},
+        },
+        (Type::List(param), Type::List(arg)) | (Type::Optional(param), Type::Optional(arg)) => {
+            infer(param, arg, type_params, inferred)
+        },
+        // A plain value passed for a `T?`
+        (Type::Optional(param), _) => infer(param, arg, type_params, inferred),
+        (Type::Class(param_class, params), Type::Class(arg_class, args)) if param_class == arg_class => {
+            for (param, arg) in params.iter().zip(args) {
+                infer(param, arg, type_params, inferred);
+            }
+        },
+        (Type::Function(params, returns), Type::Function(args, arg_returns)) if params.len() == args.len() => {
+            for (param, arg) in params.iter().zip(args).chain([(&**returns, &**arg_returns)]) {
+                infer(param, arg, type_params, inferred);
+            }
+        },
+        (Type::Tuple(params), Type::Tuple(args)) if params.len() == args.len() => {
+            for (param, arg) in params.iter().zip(args) {
+                infer(param, arg, type_params, inferred);
+            }
+        },
+        _ => {},
+    }
+}
+
+// Whether every path through `block` ends in a `return`
+fn always_returns(block: &[Spanned<Statement>]) -> bool {
+    match block.last().map(|stmt| &stmt.node) {
+        Some(Statement::Return(_)) => true,
+        Some(Statement::If { then_branch, else_branch: Some(else_branch), .. })
+        | Some(Statement::IfLet { then_branch, else_branch: Some(else_branch), .. }) => {
+            always_returns(then_branch) && always_returns(else_branch)
+        },
+        Some(Statement::Expression(Expression::Match { arms, exhaustive: true, .. })) => {
+            arms.iter().all(|arm| matches!(&arm.body, MatchBody::Block(block) if always_returns(block)))
+        },
+        _ => false,
+    }
+}
+
+// Whether a block sets `this.field` on every path, at its top level or in
+// both branches of an `if`
+fn assigns_field(block: &[Spanned<Statement>], field: &str) -> bool {
+    fn assigns(target: &AssignmentTarget, field: &str) -> bool {
+        match target {
+            AssignmentTarget::Field { object: Expression::Variable(object), name } => object == "this" && name == field,
+            AssignmentTarget::Tuple(targets) => targets.iter().any(|target| assigns(target, field)),
+            _ => false,
+        }
+    }
+    block.iter().any(|stmt| match &stmt.node {
+        Statement::Assignment { target, operator: None, .. } => assigns(target, field),
+        Statement::If { then_branch, else_branch: Some(else_branch), .. }
+        | Statement::IfLet { then_branch, else_branch: Some(else_branch), .. } => {
+            assigns_field(then_branch, field) && assigns_field(else_branch, field)
+        },
+        _ => false,
+    })
+}
+
+fn holds_function(ty: &Type) -> bool {
+    match ty {
+        Type::Function(..) => true,
+        Type::List(element) | Type::Optional(element) => holds_function(element),
+        Type::Tuple(elements) => elements.iter().any(holds_function),
+        _ => false,
+    }
+}
+
+// Whether `ty` has a `none` whose type is unknown, as in `[none]` or `(1, none)`
+fn holds_untyped_none(ty: &Type) -> bool {
+    match ty {
+        Type::Optional(inner) => **inner == Type::Unknown,
+        Type::List(element) => holds_untyped_none(element),
+        Type::Tuple(elements) => elements.iter().any(holds_untyped_none),
+        _ => false,
+    }
+}
+
+// Whether `ty` holds a value of the enum `name` without a class in between
+fn contains_enum(ty: &Type, name: &str) -> bool {
+    match ty {
+        Type::Enum(inner) => inner == name,
+        Type::List(element) | Type::Optional(element) => contains_enum(element, name),
+        Type::Tuple(elements) => elements.iter().any(|element| contains_enum(element, name)),
+        _ => false,
+    }
+}
+
+// Move a node out of the tree so it can be rebuilt as another kind of node
+fn take(expr: &mut Expression) -> Expression {
+    std::mem::replace(expr, Expression::Boolean(false))
+}
+
+// Appended to diagnostics about an optional used where its value is needed
+fn optional_hint(ty: &Type) -> &'static str {
+    match ty {
+        Type::Optional(inner) if **inner != Type::Unknown => "; it may be none, so unwrap it with `if let`, `?.` or `??` first",
+        _ => "",
+    }
+}
+
+// Points at the operator method a class would declare to support the operator
+fn operator_hint(ty: &Type, method: Option<&str>) -> Option<String> {
+    match (ty, method) {
+        (Type::Class(class, _), Some(method)) => Some(format!("; declare `{}` in class `{}` to define it", method, class)),
+        _ => None,
+    }
+}
+
+fn unwrap_hint(actual: &Type, expected: &Type) -> &'static str {
+    match expected {
+        Type::Optional(_) | Type::Unknown => "",
+        _ => optional_hint(actual),
+    }
+}
+
+fn same_type(actual: &Type, expected: &Type) -> bool {
+    match (actual, expected) {
+        (Type::Unknown, _) | (_, Type::Unknown) => true,
+        (Type::List(actual), Type::List(expected)) | (Type::Optional(actual), Type::Optional(expected)) => {
+            same_type(actual, expected)
+        },
+        (Type::Class(actual, actual_args), Type::Class(expected, expected_args)) => {
+            actual == expected && actual_args.iter().zip(expected_args).all(|(a, e)| same_type(a, e))
+        },
+        (Type::Tuple(actual), Type::Tuple(expected)) => {
+            actual.len() == expected.len() && actual.iter().zip(expected).all(|(a, e)| same_type(a, e))
+        },
+        (Type::Function(actual_params, actual_returns), Type::Function(expected_params, expected_returns)) => {
+            actual_params.len() == expected_params.len()
+                && actual_params.iter().zip(expected_params).all(|(a, e)| same_type(a, e))
+                && same_type(actual_returns, expected_returns)
+        },
+        _ => actual == expected,
+    }
+}
@@ -0,0 +1,309 @@
+// C++ toolchain configuration: which compiler to run and with which flags.
+//
+// Resolution order for the compiler executable:
+//   1. `--cxx <path>`
+//   2. the `CXX` environment variable
+//   3. `<target>-g++` when `--target <triple>` is given (Debian cross compiler naming)
+//   4. `g++`
+//
+// The archiver used for the runtime library follows the same scheme with
+// `AR`, `<target>-ar` and `ar`.
+//
+// Independent translation units are compiled in parallel, with up to `-j`
+// compilers running at once (default: the number of available cores).
+//
+// `--reproducible` (implied when `SOURCE_DATE_EPOCH` is set, as in Debian
+// package builds) maps the build directories out of debug information with
+// `-ffile-prefix-map` and creates archives in deterministic mode.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+/// Command-line options controlling the C++ compiler invocation
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct ToolchainArgs {
+    /// C++ compiler to use (defaults to $CXX, then g++)
+    #[clap(long, value_parser)]
+    pub cxx: Option<String>,
+
+    /// Extra flags passed to the C++ compiler (whitespace separated, repeatable)
+    #[clap(long, value_parser, allow_hyphen_values = true)]
+    pub cxxflags: Vec<String>,
+
+    /// C++ language standard
+    #[clap(long = "std", value_parser, default_value = "c++17")]
+    pub std: String,
+
+    /// Optimization level (-O0, -O1, -O2, -O3)
+    #[clap(short = 'O', value_parser = clap::value_parser!(u8).range(0..=3), default_value = "2")]
+    pub opt_level: u8,
+
+    /// Emit debug information (-g)
+    #[clap(long)]
+    pub debug: bool,
+
+    /// Target triple for cross compilation, e.g. aarch64-linux-gnu or arm-linux-gnueabihf
+    #[clap(long, value_parser)]
+    pub target: Option<String>,
+
+    /// Sysroot passed to the C++ compiler
+    #[clap(long, value_parser)]
+    pub sysroot: Option<String>,
+
+    /// Library to link (repeatable)
+    #[clap(short = 'l', value_parser)]
+    pub libs: Vec<String>,
+
+    /// Library search directory (repeatable)
+    #[clap(short = 'L', value_parser)]
+    pub lib_dirs: Vec<String>,
+
+    /// Number of C++ compilers to run in parallel (default: available cores)
+    #[clap(short = 'j', long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub jobs: Option<u32>,
+
+    /// Make the output independent of build paths and time (implied by SOURCE_DATE_EPOCH)
+    #[clap(long)]
+    pub reproducible: bool,
+}
+
+impl ToolchainArgs {
+    pub fn is_reproducible(&self) -> bool {
+        self.reproducible || std::env::var_os("SOURCE_DATE_EPOCH").is_some()
+    }
+}
+
+/// What the C++ compiler should produce
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputKind {
+    Object,
+    Executable,
+}
+
+/// Progress of `Toolchain::compile_objects`, reported on the calling thread
+pub enum CompileEvent<'a> {
+    /// A compiler was started for the unit at this index
+    Started(usize),
+    /// The compiler for the unit at `index` exited; `output` is everything it printed
+    Finished { index: usize, success: bool, output: &'a str },
+}
+
+enum JobMessage {
+    Started(usize),
+    Finished(usize, bool, String),
+}
+
+/// A fully resolved toolchain ready to build commands
+#[derive(Clone, Debug)]
+pub struct Toolchain {
+    pub cxx: String,
+    pub ar: String,
+    pub args: ToolchainArgs,
+    /// Extra `-I` directories (the runtime headers)
+    pub include_dirs: Vec<PathBuf>,
+    /// Archives and objects linked into executables (libnymya.a)
+    pub link_inputs: Vec<PathBuf>,
+    /// Directory prefixes rewritten in debug information of reproducible builds
+    pub prefix_maps: Vec<(PathBuf, String)>,
+}
+
+impl Toolchain {
+    pub fn from_args(args: &ToolchainArgs) -> Self {
+        let cxx = args.cxx.clone()
+            .or_else(|| std::env::var("CXX").ok().filter(|cxx| !cxx.trim().is_empty()))
+            .or_else(|| args.target.as_ref().map(|target| format!("{}-g++", target)))
+            .unwrap_or_else(|| "g++".to_string());
+        let ar = std::env::var("AR").ok().filter(|ar| !ar.trim().is_empty())
+            .or_else(|| args.target.as_ref().map(|target| format!("{}-ar", target)))
+            .unwrap_or_else(|| "ar".to_string());
+        let mut prefix_maps = Vec::new();
+        if args.is_reproducible() {
+            if let Ok(dir) = std::env::current_dir() {
+                prefix_maps.push((dir, ".".to_string()));
+            }
+        }
+        Toolchain { cxx, ar, args: args.clone(), include_dirs: Vec::new(), link_inputs: Vec::new(), prefix_maps }
+    }
+
+    // clang cross-compiles with --target; GCC uses a differently named driver instead
+    fn is_clang(&self) -> bool {
+        Path::new(&self.cxx)
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().contains("clang"))
+    }
+
+    /// Arguments (without the compiler itself) to compile `source` into `output`
+    pub fn arguments(&self, source: &Path, output: &Path, kind: OutputKind) -> Vec<String> {
+        let mut arguments = Vec::new();
+        if kind == OutputKind::Object {
+            arguments.push("-c".to_string());  // Compile only, no linking
+        }
+        arguments.push(source.display().to_string());
+        arguments.push("-o".to_string());
+        arguments.push(output.display().to_string());
+        self.push_compile_flags(&mut arguments);
+        if kind == OutputKind::Executable {
+            self.push_link_flags(&mut arguments);
+        }
+        arguments
+    }
+
+    /// Arguments (without the compiler itself) to link object files into an executable
+    pub fn link_arguments(&self, objects: &[PathBuf], output: &Path) -> Vec<String> {
+        let mut arguments: Vec<String> = objects.iter().map(|object| object.display().to_string()).collect();
+        arguments.push("-o".to_string());
+        arguments.push(output.display().to_string());
+        self.push_compile_flags(&mut arguments);
+        self.push_link_flags(&mut arguments);
+        arguments
+    }
+
+    fn push_compile_flags(&self, arguments: &mut Vec<String>) {
+        arguments.push(format!("-std={}", self.args.std));
+        arguments.push(format!("-O{}", self.args.opt_level));
+        if self.args.debug {
+            arguments.push("-g".to_string());
+        }
+        if let Some(target) = &self.args.target {
+            if self.is_clang() {
+                arguments.push(format!("--target={}", target));
+            }
+        }
+        if let Some(sysroot) = &self.args.sysroot {
+            arguments.push(format!("--sysroot={}", sysroot));
+        }
+        for dir in &self.include_dirs {
+            arguments.push(format!("-I{}", dir.display()));
+        }
+        // Later (more specific) maps take precedence
+        for (from, to) in &self.prefix_maps {
+            arguments.push(format!("-ffile-prefix-map={}={}", from.display(), to));
+        }
+        for flags in &self.args.cxxflags {
+            arguments.extend(flags.split_whitespace().map(str::to_string));
+        }
+    }
+
+    // Libraries come after the sources and objects that reference them
+    fn push_link_flags(&self, arguments: &mut Vec<String>) {
+        for input in &self.link_inputs {
+            arguments.push(input.display().to_string());
+        }
+        for dir in &self.args.lib_dirs {
+            arguments.push(format!("-L{}", dir));
+        }
+        for lib in &self.args.libs {
+            arguments.push(format!("-l{}", lib));
+        }
+        arguments.push("-lm".to_string());  // Link math library
+    }
+
+    pub fn command(&self, source: &Path, output: &Path, kind: OutputKind) -> Command {
+        let mut command = Command::new(&self.cxx);
+        command.args(self.arguments(source, output, kind));
+        command
+    }
+
+    pub fn link_command(&self, objects: &[PathBuf], output: &Path) -> Command {
+        let mut command = Command::new(&self.cxx);
+        command.args(self.link_arguments(objects, output));
+        command
+    }
+
+    /// `ar` operation and modifiers; `D` zeroes timestamps, owners and modes
+    pub fn archive_mode(&self) -> &'static str {
+        if self.args.is_reproducible() { "rcsD" } else { "rcs" }
+    }
+
+    /// Bundle object files into a static library with `ar`
+    pub fn archive(&self, objects: &[PathBuf], library: &Path) -> Result<(), String> {
+        let output = Command::new(&self.ar)
+            .arg(self.archive_mode())
+            .arg(library)
+            .args(objects)
+            .output()
+            .map_err(|e| format!("Failed to run {}: {}", self.ar, e))?;
+        if !output.status.success() {
+            return Err(format!("Archiving {} failed:\n{}", library.display(), String::from_utf8_lossy(&output.stderr)));
+        }
+        Ok(())
+    }
+
+    /// How many compilers may run at once
+    pub fn jobs(&self) -> usize {
+        match self.args.jobs {
+            Some(jobs) => jobs as usize,
+            None => thread::available_parallelism().map_or(1, |cores| cores.get()),
+        }
+    }
+
+    /// Compile each `(source, object)` pair, running up to `jobs()` compilers at
+    /// once. Once a unit fails no further compilers are started, but running
+    /// ones are waited for. Returns whether every unit compiled.
+    pub fn compile_objects(&self, units: &[(PathBuf, PathBuf)], mut report: impl FnMut(CompileEvent)) -> bool {
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let (sender, receiver) = mpsc::channel();
+
+        thread::scope(|scope| {
+            for _ in 0..self.jobs().min(units.len()) {
+                let sender = sender.clone();
+                let (next, failed) = (&next, &failed);
+                scope.spawn(move || {
+                    while !failed.load(Ordering::SeqCst) {
+                        let index = next.fetch_add(1, Ordering::SeqCst);
+                        let Some((source, object)) = units.get(index) else { break };
+                        let _ = sender.send(JobMessage::Started(index));
+                        let (success, output) = match self.command(source, object, OutputKind::Object).output() {
+                            Ok(output) => {
+                                let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+                                text.push_str(&String::from_utf8_lossy(&output.stderr));
+                                (output.status.success(), text)
+                            },
+                            Err(e) => (false, format!("Failed to run {}: {}\n", self.cxx, e)),
+                        };
+                        if !success {
+                            failed.store(true, Ordering::SeqCst);
+                        }
+                        let _ = sender.send(JobMessage::Finished(index, success, output));
+                    }
+                });
+            }
+            // The loop below ends once every worker has dropped its sender
+            drop(sender);
+
+            let mut all_succeeded = true;
+            for message in receiver {
+                match message {
+                    JobMessage::Started(index) => report(CompileEvent::Started(index)),
+                    JobMessage::Finished(index, success, output) => {
+                        all_succeeded &= success;
+                        report(CompileEvent::Finished { index, success, output: &output });
+                    },
+                }
+            }
+            all_succeeded
+        })
+    }
+
+    /// The command line as a shell-style string, for "compile manually" hints
+    pub fn command_line(&self, source: &Path, output: &Path, kind: OutputKind) -> String {
+        shell_line(&self.cxx, self.arguments(source, output, kind))
+    }
+
+    pub fn link_command_line(&self, objects: &[PathBuf], output: &Path) -> String {
+        shell_line(&self.cxx, self.link_arguments(objects, output))
+    }
+}
+
+fn shell_line(program: &str, arguments: Vec<String>) -> String {
+    let mut line = program.to_string();
+    for argument in arguments {
+        line.push(' ');
+        line.push_str(&argument);
+    }
+    line
+}
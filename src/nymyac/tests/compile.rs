@@ -0,0 +1,826 @@
+// Regressions of the compiler pipeline, run through the library API
+
+use std::collections::BTreeMap;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use nymyac::toolchain::{OutputKind, Toolchain, ToolchainArgs};
+use nymyac::{build, check_parsed, compile, parser, CompileOptions};
+
+fn cpp_for(source: &str) -> String {
+    match compile(source, &CompileOptions::new("test.nym")) {
+        Ok(output) => output.cpp,
+        Err(diagnostics) => panic!("compile failed: {:?}", diagnostics.0),
+    }
+}
+
+// The toolchain with libnymya, built once for all tests
+fn toolchain() -> &'static Toolchain {
+    static TOOLCHAIN: OnceLock<Toolchain> = OnceLock::new();
+    TOOLCHAIN.get_or_init(|| {
+        let args = ToolchainArgs { std: "c++17".to_string(), ..ToolchainArgs::default() };
+        build::toolchain_with_runtime(&Toolchain::from_args(&args), OutputKind::Executable).expect("the runtime builds")
+    })
+}
+
+// Compile `source` down to an executable, run it and return what it printed
+fn output_of(source: &str) -> String {
+    static PROGRAMS: AtomicUsize = AtomicUsize::new(0);
+    let cpp = cpp_for(source);
+    let dir = std::env::temp_dir().join(format!("nymyac-test-{}-{}", std::process::id(), PROGRAMS.fetch_add(1, Ordering::Relaxed)));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (cpp_file, exe_file) = (dir.join("test.cpp"), dir.join("test"));
+    std::fs::write(&cpp_file, &cpp).unwrap();
+    let compiled = toolchain().command(&cpp_file, &exe_file, OutputKind::Executable).output().unwrap();
+    assert!(compiled.status.success(), "{}\n{}", String::from_utf8_lossy(&compiled.stderr), cpp);
+    let run = Command::new(&exe_file).output().unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(run.status.success(), "exit status {}", run.status);
+    String::from_utf8(run.stdout).unwrap()
+}
+
+fn errors_for(source: &str) -> Vec<String> {
+    match compile(source, &CompileOptions::new("test.nym")) {
+        Ok(_) => panic!("compile succeeded"),
+        Err(diagnostics) => diagnostics.0.iter().filter(|d| d.is_error()).map(|d| d.message.clone()).collect(),
+    }
+}
+
+#[test]
+fn stray_closing_brackets_are_errors_instead_of_a_hang() {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(nymyac::parser::parse("var x = 1\n)\n]\nvar y = 2\n"));
+    });
+    let (statements, diagnostics) = receiver.recv_timeout(Duration::from_secs(5)).expect("parser hangs");
+    assert_eq!(statements.len(), 2);
+    let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(messages, ["expected a statement, found `)`", "expected a statement, found `]`"]);
+}
+
+#[test]
+fn nested_negation_keeps_its_parentheses() {
+    let cpp = cpp_for("var x = 5\nvar y = -(-x)\nvar z = - -x\nvar w = -(-1)\nvar b = !(!true)\n");
+    assert!(cpp.contains("auto y = -(-x);"), "{}", cpp);
+    assert!(cpp.contains("auto z = -(-x);"), "{}", cpp);
+    assert!(cpp.contains("auto w = -(-1);"), "{}", cpp);
+    assert!(cpp.contains("auto b = !(!true);"), "{}", cpp);
+    assert!(!cpp.contains("--"), "{}", cpp);
+}
+
+#[test]
+fn enums_holding_each_other_are_rejected() {
+    let errors = errors_for("enum A { X(B) }\nenum B { Y(A) }\n");
+    assert_eq!(errors, ["enum `A` contains itself through `B`; keep one of the values in a class instead"]);
+
+    let errors = errors_for("enum A { X(B), Z }\nenum B { Y(List[C]) }\nenum C { W(A?), V }\n");
+    assert_eq!(errors, ["enum `A` contains itself through `B` and `C`; keep one of the values in a class instead"]);
+}
+
+#[test]
+fn enums_are_defined_after_the_enums_they_hold() {
+    let cpp = cpp_for("enum A { X(B) }\nenum B { Y(Int) }\nvar a = A.X(B.Y(1))\n");
+    assert!(cpp.find("struct B_Y").unwrap() < cpp.find("struct A_X").unwrap(), "{}", cpp);
+}
+
+#[test]
+fn lists_mixing_values_and_none_are_optional() {
+    let cpp = cpp_for("var l: List[Int?] = [1, none]\nvar m = [none, 1]\n");
+    assert!(cpp.contains("auto m = std::vector<std::optional<int>>{std::nullopt, 1};"), "{}", cpp);
+
+    let errors = errors_for("var l = [none]\nvar d: Int = none\n");
+    assert_eq!(
+        errors,
+        [
+            "cannot infer the type of `l` from `none`; declare it like `var l: List[Int?] = ...`",
+            "cannot assign a value of type none to `d` of type Int",
+        ]
+    );
+}
+
+#[test]
+fn unknown_statements_are_errors() {
+    let errors = errors_for("var x = 1\nvra y = 2\nx++\nx\nvar z = 3\n");
+    assert_eq!(
+        errors,
+        [
+            "unknown statement `vra`; only calls and assignments can be used as statements",
+            "expected an expression, found `+`",
+            "only calls and assignments can be used as statements",
+        ]
+    );
+}
+
+#[test]
+fn namespaces_are_skipped_with_a_warning() {
+    let source = "namespace tools {\n    func f() -> Int {\n        return 1\n    }\n}\n";
+    let output = compile(source, &CompileOptions::new("test.nym")).unwrap();
+    let warnings: Vec<&str> = output.warnings.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(warnings, ["namespace `tools` is not compiled yet; its contents were skipped without checking"]);
+}
+
+// Options for a program importing one project module `shapes` with `source`
+fn options_with_module(source: &str) -> CompileOptions {
+    let (statements, diagnostics) = parser::parse(source);
+    let front_end = check_parsed(statements, diagnostics, &BTreeMap::new(), true);
+    assert!(!front_end.diagnostics.iter().any(|d| d.is_error()), "{:?}", front_end.diagnostics);
+    let mut options = CompileOptions::new("test.nym");
+    options.modules.insert("shapes".to_string(), front_end.exports);
+    options
+}
+
+#[test]
+fn modules_export_functions_classes_and_enums() {
+    let shapes = [
+        "var made = 0",
+        "enum Color { Red, Custom(Int) }",
+        "interface Shape {",
+        "    func area() -> Float",
+        "}",
+        "class Circle implements Shape {",
+        "    radius: Float",
+        "    init(radius: Float) {",
+        "        this.radius = radius",
+        "        made += 1",
+        "    }",
+        "    func area() -> Float {",
+        "        return disk(this.radius)",
+        "    }",
+        "}",
+        "func disk(r: Float) -> Float {",
+        "    return 3.0 * r * r",
+        "}",
+    ];
+    let options = options_with_module(&shapes.join("\n"));
+    let source = [
+        "import shapes",
+        "func total[T: shapes.Shape](items: List[T]) -> Float {",
+        "    return items[0].area()",
+        "}",
+        "func name(color: shapes.Color) -> String {",
+        "    return match color {",
+        "        shapes.Color.Red => \"red\",",
+        "        shapes.Color.Custom(n) => n.to_string()",
+        "    }",
+        "}",
+        "var c: shapes.Circle = shapes.Circle(1.0)",
+        "var a = total([c]) + shapes.disk(2.0)",
+        "var n = name(shapes.Color.Custom(3))",
+    ]
+    .join("\n");
+    let cpp = compile(&source, &options).unwrap().cpp;
+    assert!(cpp.contains("shapes::Circle c = std::make_shared<shapes::Circle_impl>(1.0);"), "{}", cpp);
+    assert!(cpp.contains("shapes::disk(2.0)"), "{}", cpp);
+    assert!(cpp.contains("shapes::Color{shapes::Color_Custom(3)}"), "{}", cpp);
+    assert!(cpp.contains("[&](const shapes::Color_Red&) -> std::string"), "{}", cpp);
+
+    let errors = match compile("import shapes\nvar a = shapes.made(1)\nvar b = shapes.square(1.0)\n", &options) {
+        Ok(_) => panic!("compile succeeded"),
+        Err(diagnostics) => diagnostics.0.iter().map(|d| d.message.clone()).collect::<Vec<_>>(),
+    };
+    assert_eq!(errors, ["`shapes.made` is a variable, not a function", "module `shapes` has no function `square`"]);
+}
+
+#[test]
+fn module_functions_use_the_module_variables() {
+    let source = "var count = 0\nfunc next() -> Int {\n    count += 1\n    return count\n}\n";
+    options_with_module(source);
+    let errors = errors_for(source);
+    assert_eq!(
+        errors,
+        [
+            "undefined variable `count`; functions cannot use the variables of the file, pass the value as a parameter",
+            "undefined variable `count`; functions cannot use the variables of the file, pass the value as a parameter",
+        ]
+    );
+}
+
+#[test]
+fn projects_are_checked_through_the_library() {
+    let root = std::env::temp_dir().join(format!("nymyac-test-project-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::write(root.join("nymya.toml"), "[project]\nname = \"demo\"\nversion = \"0.1.0\"\n").unwrap();
+    std::fs::write(root.join("src/util.nym"), "var base = 40\nfunc answer() -> Int {\n    return base + 2\n}\n").unwrap();
+    std::fs::write(root.join("src/main.nym"), "import util\nvar x: Int = util.answer()\nvar y: String = util.base\n").unwrap();
+
+    let project = nymyac::project::load(&root.join("nymya.toml")).unwrap();
+    let front_end = nymyac::build::analyze(&project).unwrap();
+    let _ = std::fs::remove_dir_all(&root);
+    let files: Vec<&str> = front_end.files().map(|file| file.name.as_str()).collect();
+    assert_eq!(files, ["util", "main"]);
+    let errors: Vec<&str> = front_end.files().flat_map(|file| &file.front_end.diagnostics).map(|d| d.message.as_str()).collect();
+    assert_eq!(errors, ["cannot assign a value of type Int to `y` of type String"]);
+}
+
+#[test]
+fn match_statements_bind_values_in_every_arm() {
+    let source = [
+        "import crystal",
+        "enum G { H(Int), X(Int), Y(Int, Int) }",
+        "func show(g: G) {",
+        "    match g {",
+        "        G.H(q) => crystal.manifest(\"H \" + q.to_string()),",
+        "        G.X(q) => crystal.manifest(\"X \" + q.to_string()),",
+        "        G.Y(a, b) => {",
+        "            crystal.manifest(\"Y \" + (a + b).to_string())",
+        "        }",
+        "    }",
+        "}",
+        "show(G.H(1))",
+        "show(G.X(2))",
+        "show(G.Y(3, 4))",
+    ];
+    assert_eq!(output_of(&source.join("\n")), "H 1\nX 2\nY 7\n");
+}
+
+#[test]
+fn extension_methods_are_called_on_built_in_types() {
+    let source = [
+        "import crystal",
+        "func Int.to_hex_char() -> String {",
+        "    var digits = [\"0\", \"1\", \"2\", \"3\", \"4\", \"5\", \"6\", \"7\", \"8\", \"9\", \"a\", \"b\", \"c\", \"d\", \"e\", \"f\"]",
+        "    return digits[this % 16]",
+        "}",
+        "func List[Int].sum() -> Int {",
+        "    var total = 0",
+        "    var i = 0",
+        "    while i < this.length {",
+        "        total += this[i]",
+        "        i += 1",
+        "    }",
+        "    return total",
+        "}",
+        "func List[T].last[T]() -> T {",
+        "    return this[this.length - 1]",
+        "}",
+        "func String.shout() -> String {",
+        "    return this + \"!\"",
+        "}",
+        "crystal.manifest(255.to_hex_char() + \" \" + [1, 2, 3].sum().to_string() + \" \" + [\"a\", \"b\"].last() + \" \" + \"hey\".shout())",
+    ];
+    assert_eq!(output_of(&source.join("\n")), "f 6 b hey!\n");
+
+    let errors = errors_for("func Int.half() -> Int {\n    return this / 2\n}\nvar a = 1.5.half()\n");
+    assert_eq!(errors, ["a value of type Float has no method `half`"]);
+}
+
+#[test]
+fn bools_are_converted_to_true_and_false() {
+    let source = "import crystal\nvar b = 1 > 2\ncrystal.manifest(true.to_string() + \" \" + b.to_string())\n";
+    assert_eq!(output_of(source), "true false\n");
+}
+
+#[test]
+fn any_function_value_can_be_called() {
+    let source = [
+        "import crystal",
+        "func adder(n: Int) -> Func[Int, Int] {",
+        "    return |x: Int| -> Int { return x + n }",
+        "}",
+        "var fs: List[Func[Int, Int]] = [adder(1), adder(10)]",
+        "crystal.manifest((fs[1](5) + adder(100)(5)).to_string())",
+        "fs[0](1)",
+    ];
+    assert_eq!(output_of(&source.join("\n")), "120\n");
+
+    let errors = errors_for("var l = [1, 2]\nvar x = l[0](1)\nvar y = (1.5)()\n");
+    assert_eq!(errors, ["the called value has type Int and cannot be called", "the called value has type Float and cannot be called"]);
+}
+
+#[test]
+fn class_fields_without_an_empty_value_are_assigned_in_init() {
+    let source = [
+        "class P {",
+        "    v: Int",
+        "}",
+        "enum E { A, B }",
+        "class H[T] {",
+        "    p: P",
+        "    q: P?",
+        "    e: E",
+        "    f: Func[Int, Int]",
+        "    t: T",
+        "    init(t: T, flag: Bool) {",
+        "        this.t = t",
+        "        if flag {",
+        "            this.e = E.A",
+        "        } else {",
+        "            this.e = E.B",
+        "        }",
+        "        if flag {",
+        "            this.p = P()",
+        "        }",
+        "    }",
+        "}",
+    ];
+    let errors = errors_for(&source.join("\n"));
+    assert_eq!(
+        errors,
+        [
+            "field `p` of type P must be assigned in the `init` of `H`; declare it as `P?` if it can be empty",
+            "field `f` of type Func[Int, Int] must be assigned in the `init` of `H`; declare it as `Func[Int, Int]?` if it can be empty",
+        ]
+    );
+}
+
+#[test]
+fn overloads_picked_by_sema_are_called_in_cpp() {
+    let source = [
+        "import crystal",
+        "class A {",
+        "    init() {}",
+        "}",
+        "class B extends A {",
+        "    init() {}",
+        "}",
+        "class C extends B {",
+        "    init() {}",
+        "}",
+        "func f(a: A) -> String {",
+        "    return \"A\"",
+        "}",
+        "func f(b: B) -> String {",
+        "    return \"B\"",
+        "}",
+        "func g(x: Float) -> String {",
+        "    return \"Float\"",
+        "}",
+        "func g(x: Bool) -> String {",
+        "    return \"Bool\"",
+        "}",
+        "class V {",
+        "    init() {}",
+        "    func op_add(x: Float) -> String {",
+        "        return \"+Float\"",
+        "    }",
+        "    func op_add(x: Bool) -> String {",
+        "        return \"+Bool\"",
+        "    }",
+        "    func h(a: A) -> String {",
+        "        return \"hA\"",
+        "    }",
+        "    func h(b: B) -> String {",
+        "        return \"hB\"",
+        "    }",
+        "}",
+        "var n = 1",
+        "var v = V()",
+        "crystal.manifest(f(C()) + \" \" + g(1) + \" \" + g(n) + \" \" + (v + n) + \" \" + v.h(C()))",
+    ];
+    assert_eq!(output_of(&source.join("\n")), "B Float Float +Float hB\n");
+}
+
+#[test]
+fn overloads_that_do_not_fit_are_errors() {
+    let source = [
+        "func f(a: Int, b: Float) -> Int {",
+        "    return 1",
+        "}",
+        "func f(a: Float, b: Int) -> Int {",
+        "    return 2",
+        "}",
+        "func g(s: String) -> Int {",
+        "    return 1",
+        "}",
+        "func g(b: Bool) -> Int {",
+        "    return 2",
+        "}",
+        "func g(t: String) -> String {",
+        "    return t",
+        "}",
+        "var x = f(1, 2)",
+        "var y = g(3)",
+    ];
+    assert_eq!(
+        errors_for(&source.join("\n")),
+        [
+            "`g` is already declared with the same parameter types",
+            "call of `f` with (Int, Int) is ambiguous between (Int, Float) and (Float, Int)",
+            "no overload of `g` takes (Int); it takes (String) or (Bool)",
+        ]
+    );
+}
+
+#[test]
+fn changing_a_function_body_only_recompiles_its_module() {
+    let root = std::env::temp_dir().join(format!("nymyac-test-rebuild-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::write(root.join("nymya.toml"), "[project]\nname = \"demo\"\nversion = \"0.1.0\"\n").unwrap();
+    std::fs::write(root.join("src/main.nym"), "import crystal\nimport util\ncrystal.manifest(util.answer().to_string())\n").unwrap();
+
+    let options = build::BuildOptions {
+        stage: build::Stage::Objects,
+        output: None,
+        toolchain: ToolchainArgs { std: "c++17".to_string(), ..ToolchainArgs::default() },
+    };
+    let mut compiled = Vec::new();
+    for body in ["return 40 + 2", "return 40 + 3"] {
+        std::fs::write(root.join("src/util.nym"), format!("func answer() -> Int {{\n    {}\n}}\n", body)).unwrap();
+        let project = nymyac::project::load(&root.join("nymya.toml")).unwrap();
+        let built = build::build(&project, build::analyze(&project).unwrap(), &options, |_| {}).unwrap();
+        compiled.push(built.compiled);
+    }
+    let _ = std::fs::remove_dir_all(&root);
+    assert_eq!(compiled, [2, 1]);
+}
+
+#[test]
+fn classes_extend_classes_of_other_modules() {
+    let root = std::env::temp_dir().join(format!("nymyac-test-extends-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("src/ui")).unwrap();
+    std::fs::write(root.join("nymya.toml"), "[project]\nname = \"demo\"\nversion = \"0.1.0\"\n").unwrap();
+    let base = [
+        "class Widget {",
+        "    size: Int",
+        "    init(size: Int) {",
+        "        this.size = size",
+        "    }",
+        "    func draw() -> String {",
+        "        return \"widget\"",
+        "    }",
+        "}",
+        "func show(w: Widget) -> String {",
+        "    return w.draw() + \" \" + w.size.to_string()",
+        "}",
+    ];
+    std::fs::write(root.join("src/ui/base.nym"), base.join("\n")).unwrap();
+    let main = [
+        "import crystal",
+        "import ui.base",
+        "class Button extends ui.base.Widget {",
+        "    init() {",
+        "        super(2)",
+        "    }",
+        "    func draw() -> String {",
+        "        return \"button\"",
+        "    }",
+        "}",
+        "crystal.manifest(ui.base.show(Button()))",
+    ];
+    std::fs::write(root.join("src/main.nym"), main.join("\n")).unwrap();
+
+    let project = nymyac::project::load(&root.join("nymya.toml")).unwrap();
+    let options = build::BuildOptions {
+        stage: build::Stage::Output,
+        output: None,
+        toolchain: ToolchainArgs { std: "c++17".to_string(), ..ToolchainArgs::default() },
+    };
+    let built = build::build(&project, build::analyze(&project).unwrap(), &options, |_| {}).unwrap();
+    let run = Command::new(built.output.unwrap()).output().unwrap();
+    let _ = std::fs::remove_dir_all(&root);
+    assert_eq!(String::from_utf8(run.stdout).unwrap(), "button 2\n");
+}
+
+#[test]
+fn type_arguments_of_generic_calls_are_inferred() {
+    let source = [
+        "import crystal",
+        "func first[T](items: List[T]) -> T {",
+        "    return items[0]",
+        "}",
+        "func twice[A, B](a: A, b: B) -> List[B] {",
+        "    return [b, b]",
+        "}",
+        "class Queue[T] {",
+        "    items: List[T]",
+        "    capacity: Int",
+        "    init(capacity: Int) {",
+        "        this.capacity = capacity",
+        "    }",
+        "    func enqueue(item: T) -> Bool {",
+        "        if this.items.length >= this.capacity {",
+        "            return false",
+        "        }",
+        "        this.items.append(item)",
+        "        return true",
+        "    }",
+        "}",
+        "var queue = Queue[String](1)",
+        "var added = queue.enqueue(\"a\")",
+        "var full = queue.enqueue(\"b\")",
+        "crystal.manifest(first([\"x\", \"y\"]) + \" \" + first([1.5]).to_string() + \" \" + twice(1, \"z\")[1])",
+        "crystal.manifest(added.to_string() + \" \" + full.to_string())",
+    ];
+    assert_eq!(output_of(&source.join("\n")), "x 1.500000 z\ntrue false\n");
+
+    let source = [
+        "func make[T]() -> List[T] {",
+        "    return []",
+        "}",
+        "func first[T](items: List[T]) -> T {",
+        "    return items[0]",
+        "}",
+        "var a = make()",
+        "var b: String = first([1, 2])",
+    ];
+    assert_eq!(
+        errors_for(&source.join("\n")),
+        [
+            "cannot infer type parameter `T` of `make`; give it explicitly, like `make[Int](...)`",
+            "cannot assign a value of type Int to `b` of type String",
+        ]
+    );
+}
+
+#[test]
+fn optional_values_are_unwrapped_before_use() {
+    let source = [
+        "import crystal",
+        "class Node {",
+        "    value: String",
+        "    next: Node?",
+        "    init(value: String) {",
+        "        this.value = value",
+        "    }",
+        "}",
+        "func find(items: List[String], wanted: String) -> Int? {",
+        "    var i = 0",
+        "    while i < items.length {",
+        "        if items[i] == wanted {",
+        "            return i",
+        "        }",
+        "        i += 1",
+        "    }",
+        "    return none",
+        "}",
+        "var names = [\"a\", \"b\"]",
+        "if let index = find(names, \"b\") {",
+        "    crystal.manifest(\"found at \" + index.to_string())",
+        "}",
+        "var head = Node(\"first\")",
+        "var label = head.next?.value ?? \"end\"",
+        "head.next = Node(\"second\")",
+        "crystal.manifest((find(names, \"z\") ?? -1).to_string() + \" \" + label + \" \" + (head.next?.value ?? \"end\"))",
+    ];
+    assert_eq!(output_of(&source.join("\n")), "found at 1\n-1 end second\n");
+
+    let source = "func find(wanted: String) -> Int? {\n    return none\n}\nvar i: Int = find(\"a\")\nvar j = find(\"b\") + 1\nvar k: Int = none\n";
+    assert_eq!(
+        errors_for(source),
+        [
+            "cannot assign a value of type Int? to `i` of type Int; it may be none, so unwrap it with `if let`, `?.` or `??` first",
+            "operator `+` is not defined for Int? and Int; it may be none, so unwrap it with `if let`, `?.` or `??` first",
+            "cannot assign a value of type none to `k` of type Int",
+        ]
+    );
+}
+
+#[test]
+fn functions_and_lambdas_are_values() {
+    let source = [
+        "import crystal",
+        "func apply(f: Func[Int, Int], x: Int) -> Int {",
+        "    return f(x)",
+        "}",
+        "func twice(x: Int) -> Int {",
+        "    return x * 2",
+        "}",
+        "var base = 10",
+        "var add_base = |x: Int| -> Int { return x + base }",
+        "base = 100",
+        "var ops: List[Func[Int, Int]] = [twice, add_base]",
+        "var hello = || -> String { return \"hi\" }",
+        "crystal.manifest(apply(twice, 4).to_string() + \" \" + apply(add_base, 1).to_string() + \" \" + ops[0](5).to_string() + \" \" + hello())",
+    ];
+    // The lambda keeps the value `base` had when it was created
+    assert_eq!(output_of(&source.join("\n")), "8 11 10 hi\n");
+
+    let source = [
+        "var count = 0",
+        "var items = [1]",
+        "var bump = || -> Void { count += 1 }",
+        "var push = |x: Int| -> Void { items.append(x) }",
+        "var f: Func[Int, Int] = |x: String| -> Int { return 1 }",
+    ];
+    assert_eq!(
+        errors_for(&source.join("\n")),
+        [
+            "cannot change `count` inside a lambda, which has a copy of it; keep the value in a field of an object to share it",
+            "cannot change `items` inside a lambda, which has a copy of it; keep the value in a field of an object to share it",
+            "cannot assign a value of type Func[String, Int] to `f` of type Func[Int, Int]",
+        ]
+    );
+}
+
+#[test]
+fn classes_overload_operators() {
+    let source = [
+        "import crystal",
+        "class Complex {",
+        "    re: Float",
+        "    im: Float",
+        "    init(re: Float, im: Float) {",
+        "        this.re = re",
+        "        this.im = im",
+        "    }",
+        "    func op_add(other: Complex) -> Complex {",
+        "        return Complex(this.re + other.re, this.im + other.im)",
+        "    }",
+        "    func op_mul(other: Complex) -> Complex {",
+        "        return Complex(this.re * other.re - this.im * other.im, this.re * other.im + this.im * other.re)",
+        "    }",
+        "    func op_mul(scale: Float) -> Complex {",
+        "        return Complex(this.re * scale, this.im * scale)",
+        "    }",
+        "    func op_eq(other: Complex) -> Bool {",
+        "        return this.re == other.re and this.im == other.im",
+        "    }",
+        "    func op_neg() -> Complex {",
+        "        return Complex(-this.re, -this.im)",
+        "    }",
+        "    func op_index(i: Int) -> Float {",
+        "        if i == 0 {",
+        "            return this.re",
+        "        }",
+        "        return this.im",
+        "    }",
+        "}",
+        "var i = Complex(0.0, 1.0)",
+        "var sum = Complex(1.0, 0.0)",
+        "sum += i * i",
+        "var flipped = -(i * 2.0)",
+        "crystal.manifest(sum[0].to_string() + \" \" + flipped[1].to_string())",
+        "crystal.manifest((i == Complex(0.0, 1.0)).to_string() + \" \" + (i != i * i).to_string())",
+    ];
+    assert_eq!(output_of(&source.join("\n")), "0.000000 -2.000000\ntrue true\n");
+
+    let source = [
+        "class V {",
+        "    x: Int",
+        "    func op_add(other: V) -> V {",
+        "        return other",
+        "    }",
+        "    func op_eq(other: V) -> Int {",
+        "        return 1",
+        "    }",
+        "}",
+        "var v = V()",
+        "var w = v + 1",
+        "var z = 2 * v",
+    ];
+    assert_eq!(
+        errors_for(&source.join("\n")),
+        [
+            "`op_eq` must return Bool, found Int",
+            "operator `+` is not defined for V and Int; `op_add` of class `V` takes (V)",
+            "operator `*` is not defined for Int and V",
+        ]
+    );
+}
+
+#[test]
+fn enums_are_matched_on_their_variants() {
+    let source = [
+        "import crystal",
+        "enum Gate {",
+        "    H(Int),",
+        "    CNOT(control: Int, target: Int),",
+        "    Measure",
+        "}",
+        "func describe(gate: Gate) -> String {",
+        "    return match gate {",
+        "        Gate.H(qubit) => \"H on \" + qubit.to_string(),",
+        "        Gate.CNOT(control, _) => \"CNOT from \" + control.to_string(),",
+        "        Gate.Measure => \"measure\"",
+        "    }",
+        "}",
+        "func code(name: String) -> Int {",
+        "    return match name {",
+        "        \"x\" => 1,",
+        "        \"y\" => 2,",
+        "        _ => 0",
+        "    }",
+        "}",
+        "var gates = [Gate.H(0), Gate.CNOT(1, 2), Gate.Measure]",
+        "crystal.manifest(describe(gates[0]) + \", \" + describe(gates[1]) + \", \" + describe(gates[2]))",
+        "crystal.manifest((code(\"y\") + code(\"q\")).to_string() + \" \" + (gates[2] == Gate.Measure).to_string())",
+    ];
+    assert_eq!(output_of(&source.join("\n")), "H on 0, CNOT from 1, measure\n2 true\n");
+
+    let source = [
+        "enum Gate {",
+        "    H(Int),",
+        "    CNOT(control: Int, target: Int),",
+        "    Measure",
+        "}",
+        "func describe(gate: Gate) -> String {",
+        "    return match gate {",
+        "        Gate.H(qubit) => \"H\",",
+        "        Gate.Measure => \"measure\"",
+        "    }",
+        "}",
+        "func count(gate: Gate) -> Int {",
+        "    return match gate {",
+        "        Gate.H(a, b) => 1,",
+        "        Gate.Swap => 2,",
+        "        _ => 3",
+        "    }",
+        "}",
+    ];
+    assert_eq!(
+        errors_for(&source.join("\n")),
+        [
+            "`match` on Gate does not cover `Gate.CNOT`; add arms for them or `_`",
+            "`Gate.H` has 1 value(s) but the pattern names 2",
+            "enum `Gate` has no variant `Swap`",
+        ]
+    );
+}
+
+#[test]
+fn tuples_are_built_and_destructured() {
+    let source = [
+        "import crystal",
+        "func divmod(a: Int, b: Int) -> (Int, Int) {",
+        "    return a / b, a % b",
+        "}",
+        "var (quotient, remainder) = divmod(17, 5)",
+        "var entry: (Int, String) = (7, \"seven\")",
+        "var (id, _) = entry",
+        "(quotient, remainder) = (remainder, quotient)",
+        "var point: (Float, Float) = (1, 2.5)",
+        "crystal.manifest(quotient.to_string() + \" \" + remainder.to_string())",
+        "crystal.manifest(entry.1 + \" \" + id.to_string() + \" \" + point.0.to_string())",
+    ];
+    assert_eq!(output_of(&source.join("\n")), "2 3\nseven 7 1.000000\n");
+
+    let errors = errors_for("var entry: (Int, String) = (7, \"seven\")\nvar x = entry.2\nvar (a, b, c) = entry\n");
+    assert_eq!(
+        errors,
+        [
+            "(Int, String) has no element 2; its elements are numbered 0 to 1",
+            "cannot unpack (Int, String) into 3 variables; it has 2 elements",
+        ]
+    );
+}
+
+#[test]
+fn classes_implement_the_interfaces_bounding_type_parameters() {
+    let source = [
+        "import crystal",
+        "import math",
+        "interface VectorLike {",
+        "    func dot(other: Self) -> Float",
+        "    func magnitude() -> Float",
+        "}",
+        "interface Named {",
+        "    func name() -> String",
+        "}",
+        "class Vec2 implements VectorLike, Named {",
+        "    x: Float",
+        "    y: Float",
+        "    init(x: Float, y: Float) {",
+        "        this.x = x",
+        "        this.y = y",
+        "    }",
+        "    func dot(other: Vec2) -> Float {",
+        "        return this.x * other.x + this.y * other.y",
+        "    }",
+        "    func magnitude() -> Float {",
+        "        return math.sqrt(this.dot(this))",
+        "    }",
+        "    func name() -> String {",
+        "        return \"vec2\"",
+        "    }",
+        "}",
+        "func describe[T: VectorLike + Named](v: T) -> String {",
+        "    return v.name() + \" \" + v.magnitude().to_string()",
+        "}",
+        "crystal.manifest(describe(Vec2(3.0, 4.0)))",
+    ];
+    assert_eq!(output_of(&source.join("\n")), "vec2 5.000000\n");
+
+    let source = [
+        "interface VectorLike {",
+        "    func dot(other: Self) -> Float",
+        "    func magnitude() -> Float",
+        "}",
+        "class Vec2 implements VectorLike {",
+        "    x: Float",
+        "    func dot(other: Vec2) -> Int {",
+        "        return 1",
+        "    }",
+        "}",
+        "class Other {",
+        "    x: Int",
+        "}",
+        "func norm[T: VectorLike](v: T) -> Float {",
+        "    return v.magnitude()",
+        "}",
+        "var n = norm(Other())",
+    ];
+    assert_eq!(
+        errors_for(&source.join("\n")),
+        [
+            "method `dot` of class `Vec2` does not match interface `VectorLike`, where it takes (Vec2) and returns Float",
+            "class `Vec2` does not implement `magnitude` of interface `VectorLike`, which takes () and returns Float",
+            "Other does not implement interface `VectorLike`, which `norm` requires of `T`",
+        ]
+    );
+}